@@ -0,0 +1,299 @@
+use std::{fmt, sync::Arc};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::storage::{now, Timestamp};
+
+pub type UserId = u64;
+
+/**
+ * How long a session token stays valid, 30 days
+ */
+const SESSION_TTL_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    pub created_at: Timestamp,
+}
+
+/**
+ * A registered user sitting at one side of a game
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seat {
+    pub user_id: UserId,
+    pub username: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    WrongCredentials,
+    InvalidUsername,
+    WeakPassword,
+}
+
+impl AuthError {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingToken => "missing_token",
+            Self::InvalidToken => "invalid_token",
+            Self::ExpiredToken => "expired_token",
+            Self::WrongCredentials => "wrong_credentials",
+            Self::InvalidUsername => "invalid_username",
+            Self::WeakPassword => "weak_password",
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingToken => write!(f, "sign in to do this"),
+            Self::InvalidToken => write!(f, "the session token is not valid"),
+            Self::ExpiredToken => write!(f, "the session has expired, sign in again"),
+            Self::WrongCredentials => write!(f, "wrong username or password"),
+            Self::InvalidUsername => write!(
+                f,
+                "usernames are 1 to {MAX_USERNAME_LENGTH} letters, digits, '-' or '_'"
+            ),
+            Self::WeakPassword => write!(
+                f,
+                "passwords must be at least {MIN_PASSWORD_LENGTH} characters"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::InvalidUsername | Self::WeakPassword => StatusCode::BAD_REQUEST,
+            Self::MissingToken
+            | Self::InvalidToken
+            | Self::ExpiredToken
+            | Self::WrongCredentials => StatusCode::UNAUTHORIZED,
+        };
+        (
+            status,
+            Json(json!({ "error": self.to_string(), "reason": self.as_str() })),
+        )
+            .into_response()
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub fn validate_username(username: &str) -> Result<(), AuthError> {
+    let valid = !username.is_empty()
+        && username.len() <= MAX_USERNAME_LENGTH
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(AuthError::InvalidUsername)
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub fn hash_password(password: &str) -> Result<String, AuthError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AuthError::WeakPassword);
+    }
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .expect("16 bytes is a valid salt length");
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Argon2 with default parameters always hashes")
+        .to_string())
+}
+
+#[must_use]
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    user_id: UserId,
+    username: String,
+    expires_at: Timestamp,
+}
+
+/**
+ * Issues and checks session tokens, which are a base64 payload and its HMAC-SHA256 signature
+ */
+pub struct Sessions {
+    secret: Vec<u8>,
+}
+
+impl Sessions {
+    #[must_use]
+    pub const fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+
+    /**
+     * Sessions signed with a random secret, which do not survive a restart
+     */
+    #[must_use]
+    pub fn with_random_secret() -> Self {
+        Self::new(rand::random::<[u8; 32]>().to_vec())
+    }
+
+    fn signature(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    #[must_use]
+    pub fn issue(&self, user: &User, issued_at: Timestamp) -> String {
+        let claims = Claims {
+            user_id: user.id,
+            username: user.username.clone(),
+            expires_at: issued_at + SESSION_TTL_MS,
+        };
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).expect("Claims are always serializable"));
+        let signature = URL_SAFE_NO_PAD.encode(self.signature(&payload).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn verify(&self, token: &str, at: Timestamp) -> Result<Seat, AuthError> {
+        let (payload, signature) = token.split_once('.').ok_or(AuthError::InvalidToken)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AuthError::InvalidToken)?;
+        self.signature(payload)
+            .verify_slice(&signature)
+            .map_err(|_| AuthError::InvalidToken)?;
+        let claims: Claims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or(AuthError::InvalidToken)?;
+        if claims.expires_at <= at {
+            return Err(AuthError::ExpiredToken);
+        }
+        Ok(Seat {
+            user_id: claims.user_id,
+            username: claims.username,
+        })
+    }
+}
+
+/**
+ * The signed in user, from an `Authorization: Bearer` header or a `token` query parameter.
+ * Browsers cannot set headers on websocket upgrades, so those use the query parameter.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthUser(pub Seat);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let sessions = parts
+            .extensions
+            .get::<Arc<Sessions>>()
+            .expect("Sessions extension is missing");
+        let header_token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let query_token = parts.uri.query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+        });
+        let token = header_token
+            .or(query_token)
+            .ok_or(AuthError::MissingToken)?;
+        sessions.verify(token, now()).map(AuthUser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords_work() {
+        assert_eq!(hash_password("short"), Err(AuthError::WeakPassword));
+        let hash = hash_password("correct horse").expect("password is long enough");
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn usernames_are_validated() {
+        assert!(validate_username("alice_1").is_ok());
+        assert!(validate_username("").is_err());
+        assert!(validate_username("alice smith").is_err());
+        assert!(validate_username(&"a".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn session_tokens_work() {
+        let sessions = Sessions::new(b"secret".to_vec());
+        let user = User {
+            id: 7,
+            username: "alice".to_string(),
+            created_at: 0,
+        };
+        let token = sessions.issue(&user, 1_000);
+        let seat = sessions.verify(&token, 2_000).expect("token is valid");
+        assert_eq!(seat.user_id, 7);
+        assert_eq!(seat.username, "alice");
+
+        assert_eq!(
+            sessions.verify(&token, 1_000 + SESSION_TTL_MS),
+            Err(AuthError::ExpiredToken)
+        );
+        assert_eq!(
+            Sessions::new(b"other".to_vec()).verify(&token, 2_000),
+            Err(AuthError::InvalidToken)
+        );
+        let (payload, _) = token.split_once('.').expect("token has a signature");
+        let forged = format!("{payload}.AAAA");
+        assert_eq!(
+            sessions.verify(&forged, 2_000),
+            Err(AuthError::InvalidToken)
+        );
+    }
+}
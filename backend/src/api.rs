@@ -0,0 +1,731 @@
+mod auth;
+mod lobby;
+mod positions;
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use chess::{
+    board_builder::BoardError,
+    fen::to_fen,
+    make_chess_move::MoveError,
+    notation::move_to_uci,
+    pgn::{to_pgn, to_pgn_from},
+    piece::PieceColor,
+    variant::GameVariant,
+    Chess,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::{
+    accounts::{AuthError, AuthUser, Seat},
+    chat::{ChatChannel, ChatMessage},
+    clock::TimeControl,
+    games::{replay, start_position, Game, GameError, TakebackScope},
+    lobby::LobbyError,
+    ratings::{PlayerRating, Rating, RatingSystem},
+    storage::{GameId, GameRecord, StorageError, Timestamp},
+    State,
+};
+
+const PGN_CONTENT_TYPES: [&str; 2] = ["application/x-chess-pgn", "application/vnd.chess-pgn"];
+const FEN_CONTENT_TYPE: &str = "application/x-chess-fen";
+const LIST_LIMIT: usize = 100;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/games", get(list_games))
+        .route("/games/:id", get(get_game))
+        .route("/games/:id/moves", post(post_move))
+        .route("/games/:id/legal-moves", get(get_legal_moves))
+        .route("/games/:id/chat", get(get_chat))
+        .route("/games/:id/resign", post(post_resign))
+        .route("/games/:id/takeback", post(post_takeback))
+        .route("/games/:id/takeback/answer", post(post_takeback_answer))
+        .route("/leaderboard", get(get_leaderboard))
+        .route("/players/:name/rating", get(get_player_rating))
+        .merge(auth::routes())
+        .merge(lobby::routes())
+        .merge(positions::routes())
+}
+
+pub enum ApiError {
+    NotFound(GameId),
+    InvalidMove(MoveError),
+    /**
+     * A position set up by hand that could not come up in a game
+     */
+    InvalidPosition(BoardError),
+    BadRequest(GameError),
+    OutOfTime,
+    /**
+     * The game refused the action because of who asked for it
+     */
+    Forbidden(GameError),
+    /**
+     * The action does not fit the current state of the game
+     */
+    Conflict(GameError),
+    Lobby(LobbyError),
+    Auth(AuthError),
+    UsernameTaken,
+    Storage(StorageError),
+}
+
+impl From<AuthError> for ApiError {
+    fn from(err: AuthError) -> Self {
+        Self::Auth(err)
+    }
+}
+
+impl From<LobbyError> for ApiError {
+    fn from(err: LobbyError) -> Self {
+        Self::Lobby(err)
+    }
+}
+
+impl From<GameError> for ApiError {
+    fn from(err: GameError) -> Self {
+        match err {
+            GameError::Move(err) => Self::InvalidMove(err),
+            GameError::InvalidColor | GameError::Chat(_) => Self::BadRequest(err),
+            GameError::OutOfTime => Self::OutOfTime,
+            GameError::NotYourSeat | GameError::SeatedGame => Self::Forbidden(err),
+            GameError::NothingToTakeBack | GameError::NoTakebackRequest => Self::Conflict(err),
+            GameError::Storage(err) => err.into(),
+        }
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::UsernameTaken => Self::UsernameTaken,
+            err => Self::Storage(err),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            Self::NotFound(id) => (
+                StatusCode::NOT_FOUND,
+                json!({ "error": format!("game {id} not found") }),
+            ),
+            Self::InvalidMove(err) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::InvalidPosition(err) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::BadRequest(err) => (
+                StatusCode::BAD_REQUEST,
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::OutOfTime => (
+                StatusCode::CONFLICT,
+                json!({
+                    "error": GameError::OutOfTime.to_string(),
+                    "reason": GameError::OutOfTime.as_str()
+                }),
+            ),
+            Self::Forbidden(err) => (
+                StatusCode::FORBIDDEN,
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::Conflict(err) => (
+                StatusCode::CONFLICT,
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::Auth(err) => return err.into_response(),
+            Self::UsernameTaken => (
+                StatusCode::CONFLICT,
+                json!({
+                    "error": StorageError::UsernameTaken.to_string(),
+                    "reason": "username_taken"
+                }),
+            ),
+            Self::Lobby(err) => (
+                match err {
+                    LobbyError::ChallengeNotFound(_) => StatusCode::NOT_FOUND,
+                    LobbyError::OwnChallenge => StatusCode::CONFLICT,
+                    LobbyError::NotYourChallenge => StatusCode::FORBIDDEN,
+                    LobbyError::InvalidTimeControl | LobbyError::InvalidVariant => {
+                        StatusCode::BAD_REQUEST
+                    }
+                    LobbyError::ServerFull => StatusCode::SERVICE_UNAVAILABLE,
+                },
+                json!({ "error": err.to_string(), "reason": err.as_str() }),
+            ),
+            Self::Storage(err) => {
+                tracing::error!(%err, "storage error");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "error": "storage error" }),
+                )
+            }
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GameSummary {
+    id: GameId,
+    white: Option<Seat>,
+    black: Option<Seat>,
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+    variant: GameVariant,
+    state: chess::game_state::GameState,
+    moves: usize,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+    finished_at: Option<Timestamp>,
+}
+
+impl From<GameRecord> for GameSummary {
+    fn from(record: GameRecord) -> Self {
+        let moves = record.played_moves().count();
+        Self {
+            id: record.id,
+            white: record.white,
+            black: record.black,
+            time_control: record.time_control,
+            days_per_move: record.days_per_move,
+            variant: record.variant,
+            state: record.state,
+            moves,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            finished_at: record.finished_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GameView {
+    id: GameId,
+    variant: GameVariant,
+    state: chess::game_state::GameState,
+    turn: PieceColor,
+    fen: String,
+    moves: Vec<String>,
+    /**
+     * When the side to move of a correspondence game loses on time
+     */
+    deadline: Option<Timestamp>,
+}
+
+impl GameView {
+    fn new(id: GameId, chess: &Chess, deadline: Option<Timestamp>) -> Self {
+        Self {
+            id,
+            variant: chess.variant,
+            state: chess.gamestate,
+            turn: chess.side_to_move(),
+            fen: to_fen(chess),
+            moves: chess.list_of_moves.iter().map(move_to_uci).collect(),
+            deadline: deadline.filter(|_| !chess.gamestate.is_finished()),
+        }
+    }
+
+    fn of(game: &Game) -> Self {
+        Self::new(game.id, &game.chess, game.deadline)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveBody {
+    uci: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardParams {
+    #[serde(default)]
+    system: RatingSystem,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResignBody {
+    color: PieceColor,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakebackBody {
+    color: PieceColor,
+    #[serde(default)]
+    scope: TakebackScope,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakebackAnswerBody {
+    color: PieceColor,
+    accept: bool,
+}
+
+async fn live_game(state: &Arc<Mutex<State>>, id: GameId) -> Result<Arc<Mutex<Game>>, ApiError> {
+    state
+        .lock()
+        .await
+        .games
+        .get(id)
+        .ok_or(ApiError::NotFound(id))
+}
+
+async fn list_games(
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<Vec<GameSummary>>, ApiError> {
+    let store = state.lock().await.games.store();
+    let games = store.list_games(LIST_LIMIT)?;
+    Ok(Json(games.into_iter().map(GameSummary::from).collect()))
+}
+
+/**
+ * Responds with JSON by default, or FEN or PGN when asked for in the Accept header
+ */
+async fn get_game(
+    Path(id): Path<GameId>,
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Response, ApiError> {
+    let store = state.lock().await.games.store();
+    let record = store.load_game(id)?.ok_or(ApiError::NotFound(id))?;
+    let (chess, deadline) = match live_game(&state, id).await {
+        Ok(game) => {
+            let game = game.lock().await;
+            (game.chess.clone(), game.deadline)
+        }
+        Err(_) => (replay(&record)?, None),
+    };
+
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let wants = |content_type: &str| {
+        accept
+            .split(',')
+            .any(|media_range| media_range.trim().starts_with(content_type))
+    };
+
+    if PGN_CONTENT_TYPES
+        .iter()
+        .any(|content_type| wants(content_type))
+    {
+        let tags = [
+            ("Event", format!("Rust chess server game {id}")),
+            ("Date", pgn_date(record.created_at)),
+            (
+                "White",
+                record
+                    .white
+                    .map_or_else(|| "?".to_string(), |seat| seat.username),
+            ),
+            (
+                "Black",
+                record
+                    .black
+                    .map_or_else(|| "?".to_string(), |seat| seat.username),
+            ),
+        ];
+        let pgn = if record.start_fen.is_some() {
+            let start = start_position(record.variant, record.start_fen.as_deref())?;
+            to_pgn_from(&start, &chess, &tags)
+        } else {
+            to_pgn(&chess, &tags)
+        };
+        Ok(([(header::CONTENT_TYPE, PGN_CONTENT_TYPES[0])], pgn).into_response())
+    } else if wants(FEN_CONTENT_TYPE) {
+        Ok(([(header::CONTENT_TYPE, FEN_CONTENT_TYPE)], to_fen(&chess)).into_response())
+    } else {
+        Ok(Json(GameView::new(id, &chess, deadline)).into_response())
+    }
+}
+
+/**
+ * Seated sides can only be played by their user, everyone may play the shared board
+ */
+async fn post_move(
+    Path(id): Path<GameId>,
+    user: Option<AuthUser>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<MoveBody>,
+) -> Result<Json<GameView>, ApiError> {
+    let game = live_game(&state, id).await?;
+    let mut game = game.lock().await;
+    game.play(&body.uci, user.map(|AuthUser(seat)| seat.user_id))?;
+    let view = GameView::of(&game);
+    drop(game);
+    Ok(Json(view))
+}
+
+async fn get_legal_moves(
+    Path(id): Path<GameId>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let game = live_game(&state, id).await?;
+    let moves: Vec<String> = game
+        .lock()
+        .await
+        .chess
+        .legal_moves()
+        .iter()
+        .map(move_to_uci)
+        .collect();
+    Ok(Json(json!({ "moves": moves })))
+}
+
+/**
+ * Chat history of a game, the players channel is only included for its players
+ */
+async fn get_chat(
+    Path(id): Path<GameId>,
+    user: Option<AuthUser>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<Vec<ChatMessage>>, ApiError> {
+    let store = state.lock().await.games.store();
+    let record = store.load_game(id)?.ok_or(ApiError::NotFound(id))?;
+    let is_player = user.is_some_and(|AuthUser(user)| {
+        [record.white, record.black]
+            .into_iter()
+            .flatten()
+            .any(|seat| seat.user_id == user.user_id)
+    });
+    let messages = store
+        .chat_messages(id)?
+        .into_iter()
+        .filter(|message| is_player || message.channel == ChatChannel::Room)
+        .collect();
+    Ok(Json(messages))
+}
+
+async fn post_resign(
+    Path(id): Path<GameId>,
+    user: Option<AuthUser>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<ResignBody>,
+) -> Result<Json<GameView>, ApiError> {
+    let game = live_game(&state, id).await?;
+    let mut game = game.lock().await;
+    game.resign(body.color, user.map(|AuthUser(seat)| seat.user_id))?;
+    let view = GameView::of(&game);
+    drop(game);
+    Ok(Json(view))
+}
+
+async fn post_takeback(
+    Path(id): Path<GameId>,
+    user: Option<AuthUser>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<TakebackBody>,
+) -> Result<StatusCode, ApiError> {
+    let game = live_game(&state, id).await?;
+    game.lock().await.request_takeback(
+        body.color,
+        body.scope,
+        user.map(|AuthUser(seat)| seat.user_id),
+    )?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/**
+ * Responds with the game as it is after the answer, rolled back if the takeback was accepted
+ */
+async fn post_takeback_answer(
+    Path(id): Path<GameId>,
+    user: Option<AuthUser>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<TakebackAnswerBody>,
+) -> Result<Json<GameView>, ApiError> {
+    let game = live_game(&state, id).await?;
+    let mut game = game.lock().await;
+    game.answer_takeback(
+        body.color,
+        body.accept,
+        user.map(|AuthUser(seat)| seat.user_id),
+    )?;
+    let view = GameView::of(&game);
+    drop(game);
+    Ok(Json(view))
+}
+
+/**
+ * Best players first, by Glicko-2 unless `system=elo` is asked for
+ */
+async fn get_leaderboard(
+    Query(params): Query<LeaderboardParams>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<Vec<PlayerRating>>, ApiError> {
+    let store = state.lock().await.games.store();
+    let limit = params.limit.unwrap_or(LIST_LIMIT).min(LIST_LIMIT);
+    Ok(Json(store.leaderboard(params.system, limit)?))
+}
+
+/**
+ * Players who have not finished a rated game yet get the starting rating
+ */
+async fn get_player_rating(
+    Path(name): Path<String>,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<PlayerRating>, ApiError> {
+    let store = state.lock().await.games.store();
+    let Some((user, _)) = store.find_user(&name)? else {
+        return Ok(Json(PlayerRating {
+            player: name,
+            rating: Rating::default(),
+        }));
+    };
+    let rating = store.rating(user.id)?.unwrap_or_default();
+    Ok(Json(PlayerRating {
+        player: user.username,
+        rating,
+    }))
+}
+
+/**
+ * Formats a timestamp as a PGN date, YYYY.MM.DD in UTC
+ */
+fn pgn_date(timestamp: Timestamp) -> String {
+    // Days to civil date conversion from Howard Hinnant's date algorithms
+    let days = timestamp / 86_400_000 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        accounts::Sessions,
+        games::GameRegistry,
+        lobby::Lobby,
+        storage::{memory::MemoryStore, now, GameStore, NewGame},
+    };
+    use axum::{
+        body::{to_bytes, Body},
+        http::Request,
+    };
+    use chess::game_state::GameState;
+    use serde_json::Value;
+    use tokio::sync::{broadcast, watch};
+    use tower::ServiceExt;
+
+    /**
+     * The API over an in-memory store, with the shared board as game 1
+     */
+    fn app() -> Router {
+        app_over(Arc::new(MemoryStore::new()))
+    }
+
+    /**
+     * The API as it comes back up over `store`, with a new shared board when the last one finished
+     */
+    fn app_over(store: Arc<dyn GameStore>) -> Router {
+        let mut games = GameRegistry::restore(store).expect("restore works");
+        let game = games.shared().unwrap_or_else(|| {
+            games
+                .create_game(&NewGame::default())
+                .expect("game is created")
+        });
+        let state = State {
+            started_at: now(),
+            clients_count: 0,
+            games,
+            game,
+            lobby: Lobby::new(),
+            lobby_updates: broadcast::channel(1).0,
+            shutdown: watch::channel(false).0,
+        };
+        routes()
+            .layer(Extension(Arc::new(Mutex::new(state))))
+            .layer(Extension(Arc::new(Sessions::with_random_secret())))
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, String, String) {
+        let response = app
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("router is infallible");
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body is read");
+        let body = String::from_utf8(body.to_vec()).expect("body is text");
+        (status, content_type, body)
+    }
+
+    fn get(uri: &str, accept: &str) -> Request<Body> {
+        Request::get(uri)
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .expect("request is valid")
+    }
+
+    fn post(uri: &str, body: &Value) -> Request<Body> {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("request is valid")
+    }
+
+    fn json_of(body: &str) -> Value {
+        serde_json::from_str(body).expect("body is JSON")
+    }
+
+    #[tokio::test]
+    async fn games_are_negotiated_by_accept() {
+        let app = app();
+        let (status, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "e2e4" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json_of(&body)["moves"], json!(["e2e4"]));
+
+        let (status, content_type, body) = send(&app, get("/games/1", "*/*")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+        let view = json_of(&body);
+        assert_eq!(view["id"], 1);
+        assert_eq!(view["turn"], json!(PieceColor::Black));
+
+        let (status, content_type, body) = send(&app, get("/games/1", FEN_CONTENT_TYPE)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, FEN_CONTENT_TYPE);
+        assert_eq!(
+            body,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        for accept in PGN_CONTENT_TYPES {
+            let (status, content_type, body) =
+                send(&app, get("/games/1", &format!("text/html, {accept}"))).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(content_type, PGN_CONTENT_TYPES[0]);
+            assert!(
+                body.contains("[Event \"Rust chess server game 1\"]"),
+                "{body}"
+            );
+            assert!(body.contains("1. e4"), "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn moves_are_played_or_rejected() {
+        let app = app();
+        let (status, _, body) = send(&app, get("/games/1/legal-moves", "*/*")).await;
+        assert_eq!(status, StatusCode::OK);
+        let moves = json_of(&body)["moves"].clone();
+        assert_eq!(moves.as_array().map(Vec::len), Some(20));
+        assert!(moves
+            .as_array()
+            .is_some_and(|moves| moves.contains(&json!("g1f3"))));
+
+        let (status, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "e2e5" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json_of(&body)["reason"], "illegal_move");
+        let (status, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "e2" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json_of(&body)["reason"], "invalid_notation");
+
+        let (status, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "g1f3" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json_of(&body)["turn"], json!(PieceColor::Black));
+        let (_, _, body) = send(&app, get("/games/1/legal-moves", "*/*")).await;
+        assert!(!body.contains("g1f3"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn resigning_ends_the_game() {
+        let app = app();
+        let (status, _, body) =
+            send(&app, post("/games/1/resign", &json!({ "color": "None" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json_of(&body)["reason"], "invalid_color");
+
+        let (status, _, body) =
+            send(&app, post("/games/1/resign", &json!({ "color": "White" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json_of(&body)["state"], json!(GameState::BlackVictory));
+
+        let (status, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "e2e4" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json_of(&body)["reason"], "game_over");
+    }
+
+    #[tokio::test]
+    async fn finished_games_keep_their_result_after_a_restart() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let app = app_over(store.clone());
+        send(&app, post("/games/1/moves", &json!({ "uci": "e2e4" }))).await;
+        let (status, _, _) =
+            send(&app, post("/games/1/resign", &json!({ "color": "Black" }))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The resigned game is not restored, so it is read back from storage
+        let app = app_over(store);
+        let (status, _, body) = send(&app, get("/games/1", "*/*")).await;
+        assert_eq!(status, StatusCode::OK);
+        let view = json_of(&body);
+        assert_eq!(view["state"], json!(GameState::WhiteVictory));
+        assert_eq!(view["moves"], json!(["e2e4"]));
+
+        let (_, _, body) = send(&app, get("/games/1", PGN_CONTENT_TYPES[0])).await;
+        assert!(body.contains("[Result \"1-0\"]"), "{body}");
+        assert!(body.trim_end().ends_with("1-0"), "{body}");
+        let (_, _, body) = send(&app, post("/games/1/moves", &json!({ "uci": "e7e5" }))).await;
+        assert_eq!(json_of(&body)["error"], "game 1 not found");
+    }
+
+    #[tokio::test]
+    async fn unknown_games_are_not_found() {
+        let app = app();
+        for request in [
+            get("/games/2", "*/*"),
+            get("/games/2", FEN_CONTENT_TYPE),
+            get("/games/2/legal-moves", "*/*"),
+            post("/games/2/moves", &json!({ "uci": "e2e4" })),
+            post("/games/2/resign", &json!({ "color": "White" })),
+        ] {
+            let (status, _, body) = send(&app, request).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(json_of(&body)["error"], "game 2 not found");
+        }
+    }
+
+    #[test]
+    fn pgn_date_works() {
+        assert_eq!(pgn_date(0), "1970.01.01");
+        assert_eq!(pgn_date(951_782_400_000), "2000.02.29");
+        assert_eq!(pgn_date(1_792_386_624_366), "2026.10.19");
+    }
+}
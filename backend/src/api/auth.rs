@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use axum::{
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::ApiError;
+use crate::{
+    accounts::{
+        hash_password, validate_username, verify_password, AuthError, AuthUser, Sessions, User,
+    },
+    storage::now,
+    workers::WorkerPool,
+    State,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/auth/register", post(register))
+        .route("/auth/login", post(login))
+        .route("/auth/me", get(get_me))
+}
+
+#[derive(Debug, Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+fn session(sessions: &Sessions, user: &User) -> Json<Value> {
+    Json(json!({ "token": sessions.issue(user, now()), "user": user }))
+}
+
+async fn register(
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Extension(sessions): Extension<Arc<Sessions>>,
+    Extension(workers): Extension<Arc<WorkerPool>>,
+    Json(body): Json<Credentials>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    validate_username(&body.username)?;
+    // Hashing is slow on purpose, keep it off the async workers
+    let password_hash = workers
+        .run(move || hash_password(&body.password))
+        .await
+        .expect("Password hashing panicked")?;
+    let store = state.lock().await.games.store();
+    let user = store.create_user(&body.username, &password_hash, now())?;
+    Ok((StatusCode::CREATED, session(&sessions, &user)))
+}
+
+async fn login(
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Extension(sessions): Extension<Arc<Sessions>>,
+    Extension(workers): Extension<Arc<WorkerPool>>,
+    Json(body): Json<Credentials>,
+) -> Result<Json<Value>, ApiError> {
+    let store = state.lock().await.games.store();
+    let (user, password_hash) = store
+        .find_user(&body.username)?
+        .ok_or(AuthError::WrongCredentials)?;
+    let verified = workers
+        .run(move || verify_password(&body.password, &password_hash))
+        .await
+        .expect("Password verification panicked");
+    if !verified {
+        return Err(AuthError::WrongCredentials.into());
+    }
+    Ok(session(&sessions, &user))
+}
+
+async fn get_me(AuthUser(seat): AuthUser) -> Json<Value> {
+    Json(json!(seat))
+}
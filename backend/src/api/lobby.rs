@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chess::variant::GameVariant;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::ApiError;
+use crate::{
+    accounts::AuthUser,
+    clock::TimeControl,
+    lobby::{ChallengeId, ColorPreference, LobbyError},
+    protocol::ServerMessage,
+    storage::{now, NewGame},
+    State,
+};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/lobby", get(get_lobby))
+        .route("/lobby/challenges", post(post_challenge))
+        .route("/lobby/challenges/:id", delete(delete_challenge))
+        .route("/lobby/challenges/:id/accept", post(accept_challenge))
+        .route("/lobby/queue", post(join_queue).delete(leave_queue))
+}
+
+#[derive(Debug, Deserialize)]
+struct ChallengeBody {
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+    #[serde(default)]
+    variant: GameVariant,
+    color: ColorPreference,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeekBody {
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+    #[serde(default)]
+    variant: GameVariant,
+}
+
+async fn get_lobby(Extension(state): Extension<Arc<Mutex<State>>>) -> Json<Value> {
+    let state = state.lock().await;
+    Json(json!({
+        "challenges": state.lobby.challenges(),
+        "queue": state.lobby.queue_len(),
+    }))
+}
+
+async fn post_challenge(
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<ChallengeBody>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let mut state = state.lock().await;
+    let id = state.lobby.post_challenge(
+        &player,
+        body.time_control,
+        body.days_per_move,
+        body.variant,
+        body.color,
+        now(),
+    )?;
+    broadcast_challenges(&state);
+    drop(state);
+    Ok((StatusCode::CREATED, Json(json!({ "id": id }))))
+}
+
+async fn delete_challenge(
+    Path(id): Path<ChallengeId>,
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<StatusCode, ApiError> {
+    let mut state = state.lock().await;
+    state.lobby.cancel_challenge(id, &player)?;
+    broadcast_challenges(&state);
+    drop(state);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn accept_challenge(
+    Path(id): Path<ChallengeId>,
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> Result<Json<Value>, ApiError> {
+    let mut state = state.lock().await;
+    if state.games.is_full() {
+        return Err(LobbyError::ServerFull.into());
+    }
+    let new_game = state.lobby.accept_challenge(id, &player)?;
+    let started = start_game(&mut state, new_game).await?;
+    state.lobby.close_challenge(id);
+    broadcast_challenges(&state);
+    drop(state);
+    Ok(Json(started))
+}
+
+/**
+ * Starts a game right away when someone with the same time control and variant is waiting
+ */
+async fn join_queue(
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<SeekBody>,
+) -> Result<Json<Value>, ApiError> {
+    let mut state = state.lock().await;
+    if state.games.is_full() {
+        return Err(LobbyError::ServerFull.into());
+    }
+    let response =
+        match state
+            .lobby
+            .seek(&player, body.time_control, body.days_per_move, body.variant)?
+        {
+            Some(new_game) => start_game(&mut state, new_game).await?,
+            None => json!({ "status": "waiting" }),
+        };
+    drop(state);
+    Ok(Json(response))
+}
+
+async fn leave_queue(
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+) -> StatusCode {
+    if state.lock().await.lobby.leave_queue(&player) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+fn broadcast_challenges(state: &State) {
+    let message = ServerMessage::Challenges {
+        challenges: state.lobby.challenges(),
+    };
+    // No receivers just means nobody is connected right now
+    let _ = state.lobby_updates.send(message.to_json());
+}
+
+/**
+ * Creates the paired game and lets both players know through the websocket
+ */
+async fn start_game(state: &mut State, new_game: NewGame) -> Result<Value, ApiError> {
+    let game = state.games.create_game(&new_game)?;
+    let game_id = game.lock().await.id;
+    let (Some(white), Some(black)) = (new_game.white, new_game.black) else {
+        unreachable!("The lobby only pairs two seated players");
+    };
+    let message = ServerMessage::GameStarted {
+        game_id,
+        white,
+        black,
+        time_control: new_game.time_control,
+        days_per_move: new_game.days_per_move,
+        variant: new_game.variant,
+    };
+    let _ = state.lobby_updates.send(message.to_json());
+    Ok(serde_json::to_value(&message).expect("Server messages are always serializable"))
+}
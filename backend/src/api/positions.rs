@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, routing::post, Extension, Json, Router};
+use chess::{
+    board_builder::{BoardBuilder, BoardError},
+    castling::Castling,
+    chess::SquareCoordinates,
+    fen::{fen_char_to_piece, to_fen},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    notation::{move_to_uci, parse_uci_square},
+    piece::{Piece, PieceColor},
+    Chess,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::ApiError;
+use crate::{accounts::AuthUser, lobby::LobbyError, storage::NewGame, State};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/positions", post(post_position))
+        .route("/positions/games", post(post_position_game))
+}
+
+/**
+ * A square in UCI notation such as e4
+ */
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+struct UciSquare(SquareCoordinates);
+
+impl TryFrom<String> for UciSquare {
+    type Error = String;
+
+    fn try_from(square: String) -> Result<Self, Self::Error> {
+        parse_uci_square(&square)
+            .map(Self)
+            .ok_or_else(|| format!("invalid square {square}"))
+    }
+}
+
+/**
+ * A piece as its FEN letter, uppercase for white
+ */
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "char")]
+struct FenPiece(Piece);
+
+impl TryFrom<char> for FenPiece {
+    type Error = String;
+
+    fn try_from(piece: char) -> Result<Self, Self::Error> {
+        fen_char_to_piece(piece)
+            .map(Self)
+            .ok_or_else(|| format!("invalid piece {piece}"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlacedPiece {
+    square: UciSquare,
+    piece: FenPiece,
+}
+
+/**
+ * A position set up in the board editor, without castling rights unless given
+ */
+#[derive(Debug, Deserialize)]
+struct PositionBody {
+    pieces: Vec<PlacedPiece>,
+    turn: PieceColor,
+    castling: Option<Castling>,
+    en_passant: Option<UciSquare>,
+}
+
+impl PositionBody {
+    fn build(self) -> Result<Chess, BoardError> {
+        let mut builder = BoardBuilder::new()
+            .side_to_move(self.turn)
+            .en_passant(self.en_passant.map(|UciSquare(square)| square));
+        if let Some(castling) = self.castling {
+            builder = builder.castling(castling);
+        }
+        for PlacedPiece {
+            square: UciSquare((file, rank)),
+            piece: FenPiece(piece),
+        } in self.pieces
+        {
+            builder = builder.place(file, rank, piece);
+        }
+        builder.build()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PositionView {
+    fen: String,
+    turn: PieceColor,
+    state: GameState,
+    in_check: bool,
+    moves: Vec<String>,
+}
+
+impl PositionView {
+    fn new(chess: &Chess) -> Self {
+        let in_check = match chess.side_to_move() {
+            PieceColor::Black => chess.players.1.in_check,
+            _ => chess.players.0.in_check,
+        };
+        Self {
+            fen: to_fen(chess),
+            turn: chess.side_to_move(),
+            state: chess.gamestate,
+            in_check,
+            moves: chess.legal_moves().iter().map(move_to_uci).collect(),
+        }
+    }
+}
+
+/**
+ * Checks a study or lesson position and answers with its FEN and legal moves
+ */
+async fn post_position(Json(body): Json<PositionBody>) -> Result<Json<PositionView>, ApiError> {
+    let chess = body.build().map_err(ApiError::InvalidPosition)?;
+    Ok(Json(PositionView::new(&chess)))
+}
+
+/**
+ * Starts a game from the position with the player on both sides, to study it or
+ * play a lesson through. The game is stored with its position so it replays from it.
+ */
+async fn post_position_game(
+    AuthUser(player): AuthUser,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Json(body): Json<PositionBody>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let chess = body.build().map_err(ApiError::InvalidPosition)?;
+    if chess.gamestate.is_finished() {
+        return Err(ApiError::InvalidMove(MoveError::GameOver));
+    }
+    let mut state = state.lock().await;
+    if state.games.is_full() {
+        return Err(LobbyError::ServerFull.into());
+    }
+    let game = state.games.create_game(&NewGame {
+        white: Some(player.clone()),
+        black: Some(player),
+        start_fen: Some(to_fen(&chess)),
+        ..NewGame::default()
+    })?;
+    drop(state);
+    let game_id = game.lock().await.id;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "game_id": game_id, "position": PositionView::new(&chess) })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(body: &str) -> Result<Chess, BoardError> {
+        serde_json::from_str::<PositionBody>(body)
+            .expect("body is well formed")
+            .build()
+    }
+
+    #[test]
+    fn positions_are_set_up() {
+        let chess = build(
+            r#"{
+                "pieces": [
+                    { "square": "e1", "piece": "K" },
+                    { "square": "h1", "piece": "R" },
+                    { "square": "e8", "piece": "k" }
+                ],
+                "turn": "White",
+                "castling": {
+                    "white": { "kingside": true, "queenside": false },
+                    "black": { "kingside": false, "queenside": false }
+                }
+            }"#,
+        )
+        .expect("position is valid");
+        let view = PositionView::new(&chess);
+        assert_eq!(view.fen, "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert!(view.moves.contains(&"e1g1".to_string()));
+
+        assert_eq!(
+            build(r#"{ "pieces": [{ "square": "e1", "piece": "K" }], "turn": "Black" }"#).err(),
+            Some(BoardError::KingCount)
+        );
+        assert!(serde_json::from_str::<PositionBody>(
+            r#"{ "pieces": [{ "square": "e9", "piece": "K" }], "turn": "White" }"#
+        )
+        .is_err());
+    }
+}
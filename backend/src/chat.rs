@@ -0,0 +1,171 @@
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Timestamp;
+
+pub const MAX_CHAT_LENGTH: usize = 500;
+/**
+ * A connection may send this many chat messages in any `CHAT_WINDOW_MS`
+ */
+const CHAT_BURST: usize = 5;
+const CHAT_WINDOW_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatChannel {
+    /**
+     * Everyone watching the game
+     */
+    #[default]
+    Room,
+    /**
+     * Only the two seated players
+     */
+    Players,
+}
+
+impl ChatChannel {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Room => "room",
+            Self::Players => "players",
+        }
+    }
+}
+
+impl FromStr for ChatChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "room" => Ok(Self::Room),
+            "players" => Ok(Self::Players),
+            _ => Err(format!("unknown chat channel {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    /**
+     * Username of the sender, None for spectators who are not signed in
+     */
+    pub author: Option<String>,
+    pub text: String,
+    pub sent_at: Timestamp,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChatError {
+    Empty,
+    TooLong,
+    RateLimited,
+    PlayersOnly,
+}
+
+impl ChatError {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Empty => "chat_empty",
+            Self::TooLong => "chat_too_long",
+            Self::RateLimited => "chat_rate_limited",
+            Self::PlayersOnly => "chat_players_only",
+        }
+    }
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "chat messages cannot be empty"),
+            Self::TooLong => write!(
+                f,
+                "chat messages can be at most {MAX_CHAT_LENGTH} characters"
+            ),
+            Self::RateLimited => write!(f, "slow down, too many chat messages"),
+            Self::PlayersOnly => write!(f, "only the players of this game can use that channel"),
+        }
+    }
+}
+
+impl std::error::Error for ChatError {}
+
+/**
+ * Trims the message and checks its length
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn clean_text(text: &str) -> Result<String, ChatError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ChatError::Empty);
+    }
+    if text.chars().count() > MAX_CHAT_LENGTH {
+        return Err(ChatError::TooLong);
+    }
+    Ok(text.to_owned())
+}
+
+/**
+ * Sliding window limit on the chat messages of one connection
+ */
+#[derive(Debug, Default)]
+pub struct ChatLimiter {
+    sent: VecDeque<Timestamp>,
+}
+
+impl ChatLimiter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Counts a message sent at `now`, unless the window is already full
+     */
+    pub fn allow(&mut self, now: Timestamp) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|&sent_at| sent_at + CHAT_WINDOW_MS <= now)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= CHAT_BURST {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_checked() {
+        assert_eq!(clean_text("  hi  "), Ok("hi".to_string()));
+        assert_eq!(clean_text(" \n "), Err(ChatError::Empty));
+        assert_eq!(
+            clean_text(&"a".repeat(MAX_CHAT_LENGTH + 1)),
+            Err(ChatError::TooLong)
+        );
+        assert!(clean_text(&"é".repeat(MAX_CHAT_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn limiter_works() {
+        let mut limiter = ChatLimiter::new();
+        for sent_at in 0..5 {
+            assert!(limiter.allow(sent_at * 1_000));
+        }
+        assert!(!limiter.allow(5_000));
+        assert!(!limiter.allow(9_999));
+        assert!(limiter.allow(10_000));
+        assert!(!limiter.allow(10_500));
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use chess::piece::PieceColor;
+
+use crate::storage::Timestamp;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/**
+ * Longest time per move a correspondence game may give
+ */
+pub const MAX_DAYS_PER_MOVE: u32 = 30;
+/**
+ * Longest starting time a clock may have, three hours
+ */
+pub const MAX_INITIAL_MS: u64 = 3 * 60 * 60 * 1000;
+/**
+ * Largest increment a clock may add after every move, three minutes
+ */
+pub const MAX_INCREMENT_MS: u64 = 3 * 60 * 1000;
+
+/**
+ * Starting time and the increment added after every move, in milliseconds
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub initial_ms: u64,
+    pub increment_ms: u64,
+}
+
+impl TimeControl {
+    /**
+     * Whether a game can be played with it, starting with some time and within the maximums
+     */
+    #[must_use]
+    pub const fn is_valid(self) -> bool {
+        self.initial_ms > 0
+            && self.initial_ms <= MAX_INITIAL_MS
+            && self.increment_ms <= MAX_INCREMENT_MS
+    }
+}
+
+/**
+ * Remaining time of both players. The clock starts running after the first move.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    #[serde(skip)]
+    increment_ms: u64,
+    #[serde(skip)]
+    running_since: Option<Timestamp>,
+}
+
+impl Clock {
+    #[must_use]
+    pub const fn new(time_control: TimeControl) -> Self {
+        Self {
+            white_ms: time_control.initial_ms,
+            black_ms: time_control.initial_ms,
+            increment_ms: time_control.increment_ms,
+            running_since: None,
+        }
+    }
+
+    const fn time_mut(&mut self, color: PieceColor) -> Option<&mut u64> {
+        match color {
+            PieceColor::White => Some(&mut self.white_ms),
+            PieceColor::Black => Some(&mut self.black_ms),
+            PieceColor::None => None,
+        }
+    }
+
+    /**
+     * Time `color` has left at `now`, counting the move they may be thinking about
+     */
+    #[must_use]
+    pub fn remaining(&self, color: PieceColor, to_move: PieceColor, now: Timestamp) -> u64 {
+        let time = match color {
+            PieceColor::White => self.white_ms,
+            PieceColor::Black => self.black_ms,
+            PieceColor::None => return 0,
+        };
+        match self.running_since {
+            Some(since) if color == to_move => time.saturating_sub(now.saturating_sub(since)),
+            _ => time,
+        }
+    }
+
+    /**
+     * Charges the time since the previous move to `color` and adds the increment.
+     * Returns false when `color` had already run out of time.
+     */
+    pub fn punch(&mut self, color: PieceColor, now: Timestamp) -> bool {
+        let elapsed = self
+            .running_since
+            .map_or(0, |since| now.saturating_sub(since));
+        let increment_ms = self.increment_ms;
+        let Some(time) = self.time_mut(color) else {
+            return false;
+        };
+        if elapsed >= *time && elapsed > 0 {
+            *time = 0;
+            return false;
+        }
+        *time = (*time - elapsed).saturating_add(increment_ms);
+        self.running_since = Some(now);
+        true
+    }
+
+    /**
+     * The clock with the time `to_move` has spent thinking until `now` charged to them
+     */
+    #[must_use]
+    pub const fn at(mut self, to_move: PieceColor, now: Timestamp) -> Self {
+        if let Some(since) = self.running_since {
+            if let Some(time) = self.time_mut(to_move) {
+                *time = time.saturating_sub(now.saturating_sub(since));
+            }
+            self.running_since = Some(now);
+        }
+        self
+    }
+
+    /**
+     * Restarts a running clock from `now`, used when a takeback restores an earlier clock
+     */
+    pub const fn resume(&mut self, now: Timestamp) {
+        if self.running_since.is_some() {
+            self.running_since = Some(now);
+        }
+    }
+}
+
+/**
+ * When a correspondence player who got the move at `since` loses on time
+ */
+#[must_use]
+pub fn move_deadline(since: Timestamp, days_per_move: u32) -> Timestamp {
+    since + u64::from(days_per_move) * DAY_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_works() {
+        let mut clock = Clock::new(TimeControl {
+            initial_ms: 60_000,
+            increment_ms: 1_000,
+        });
+        assert!(clock.punch(PieceColor::White, 1_000));
+        assert_eq!(clock.white_ms, 61_000);
+        assert_eq!(
+            clock.remaining(PieceColor::Black, PieceColor::Black, 11_000),
+            50_000
+        );
+        assert!(clock.punch(PieceColor::Black, 11_000));
+        assert_eq!(clock.black_ms, 51_000);
+        assert_eq!(
+            clock.remaining(PieceColor::Black, PieceColor::White, 20_000),
+            51_000
+        );
+        let charged = clock.at(PieceColor::White, 25_000);
+        assert_eq!(charged.white_ms, 47_000);
+        assert_eq!(charged.black_ms, 51_000);
+        assert_eq!(
+            charged.remaining(PieceColor::White, PieceColor::White, 26_000),
+            46_000
+        );
+        let mut resumed = clock;
+        resumed.resume(30_000);
+        assert_eq!(
+            resumed.remaining(PieceColor::White, PieceColor::White, 40_000),
+            51_000
+        );
+        assert!(!clock.punch(PieceColor::White, 80_000));
+        assert_eq!(clock.white_ms, 0);
+
+        let mut huge = Clock::new(TimeControl {
+            initial_ms: u64::MAX - 1,
+            increment_ms: u64::MAX,
+        });
+        assert!(huge.punch(PieceColor::White, 1));
+        assert_eq!(huge.white_ms, u64::MAX);
+    }
+
+    #[test]
+    fn time_control_bounds_work() {
+        let valid = TimeControl {
+            initial_ms: MAX_INITIAL_MS,
+            increment_ms: MAX_INCREMENT_MS,
+        };
+        assert!(valid.is_valid());
+        assert!(!TimeControl {
+            initial_ms: 0,
+            ..valid
+        }
+        .is_valid());
+        assert!(!TimeControl {
+            initial_ms: MAX_INITIAL_MS + 1,
+            ..valid
+        }
+        .is_valid());
+        assert!(!TimeControl {
+            increment_ms: MAX_INCREMENT_MS + 1,
+            ..valid
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn move_deadline_works() {
+        assert_eq!(move_deadline(1_000, 0), 1_000);
+        assert_eq!(move_deadline(1_000, 3), 1_000 + 3 * 86_400_000);
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use axum::http::HeaderValue;
+use clap::Parser;
+use serde::Deserialize;
+
+use crate::{
+    clock::{TimeControl, MAX_INCREMENT_MS, MAX_INITIAL_MS},
+    limits::{parse_origins, parse_proxies},
+};
+
+/**
+ * Read when it exists and no other file was given
+ */
+const DEFAULT_CONFIG_PATH: &str = "chess.toml";
+
+/**
+ * Command line flags, each of which can also be set through its environment variable.
+ * Flags and environment variables win over the configuration file.
+ */
+#[derive(Debug, Default, Parser)]
+#[command(version, about = "Chess game server")]
+pub struct Args {
+    #[arg(
+        long,
+        env = "CONFIG_PATH",
+        help = "TOML configuration file [default: chess.toml when present]"
+    )]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "BIND_ADDRESS", help = "Address and port to listen on")]
+    pub bind: Option<SocketAddr>,
+    #[arg(long, env = "PORT", help = "Port to listen on, keeping the address")]
+    pub port: Option<u16>,
+    #[arg(
+        long,
+        env = "ALLOWED_ORIGINS",
+        help = "Comma separated origins browsers may use the API from"
+    )]
+    pub allowed_origins: Option<String>,
+    #[arg(
+        long,
+        env = "TRUSTED_PROXIES",
+        help = "Comma separated proxy addresses whose X-Forwarded-For is believed"
+    )]
+    pub trusted_proxies: Option<String>,
+    #[arg(long, env = "DATABASE_PATH", help = "SQLite database file")]
+    pub database_path: Option<PathBuf>,
+    #[arg(
+        long,
+        env = "SESSION_SECRET",
+        hide_env_values = true,
+        help = "Secret used to sign session tokens"
+    )]
+    pub session_secret: Option<String>,
+    #[arg(
+        long,
+        env = "MAX_GAMES",
+        help = "Most games that may be in progress at once"
+    )]
+    pub max_games: Option<usize>,
+    #[arg(long, env = "ENGINE_WORKERS", help = "Threads for CPU heavy work")]
+    pub engine_workers: Option<usize>,
+    #[arg(long, env = "LOG_LEVEL", help = "error, warn, info, debug or trace")]
+    pub log_level: Option<LogLevel>,
+    #[arg(
+        long,
+        env = "PRODUCTION",
+        help = "Refuse to start without a session secret"
+    )]
+    pub production: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(format!("unknown log level {s}")),
+        }
+    }
+}
+
+/**
+ * Limits on the work the server does for clients
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /**
+     * CPU heavy jobs such as password hashing that run at once, off the async workers
+     */
+    pub workers: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self { workers: 4 }
+    }
+}
+
+/**
+ * Server settings, from the configuration file with the flags and environment on top
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub allowed_origins: Vec<String>,
+    /**
+     * Reverse proxies in front of the server, rate limits are per client behind them
+     */
+    pub trusted_proxies: Vec<String>,
+    pub database_path: PathBuf,
+    /**
+     * Sessions are signed with a random secret and lost on restart when unset
+     */
+    pub session_secret: Option<String>,
+    /**
+     * Clock of lobby games that ask for neither a clock nor days per move, untimed when unset
+     */
+    pub default_time_control: Option<TimeControl>,
+    pub engine: EngineConfig,
+    pub max_games: usize,
+    pub log_level: LogLevel,
+    /**
+     * A deploy that would log everyone out by signing with a random secret fails to start instead
+     */
+    pub production: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 8000),
+            allowed_origins: vec!["http://localhost:5173".to_string()],
+            trusted_proxies: Vec::new(),
+            database_path: PathBuf::from("chess.db"),
+            session_secret: None,
+            default_time_control: None,
+            engine: EngineConfig::default(),
+            max_games: 1_000,
+            log_level: LogLevel::default(),
+            production: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, err) => write!(f, "cannot read {}: {err}", path.display()),
+            Self::Parse(path, err) => write!(f, "{} is not valid: {err}", path.display()),
+            Self::Invalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /**
+     * Reads the configuration file, applies `args` over it and checks the result
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load(args: Args) -> Result<Self, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+        config.apply(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.into(), err))?;
+        toml::from_str(&text).map_err(|err| ConfigError::Parse(path.into(), err))
+    }
+
+    fn apply(&mut self, args: Args) {
+        if let Some(bind) = args.bind {
+            self.bind = bind;
+        }
+        if let Some(port) = args.port {
+            self.bind.set_port(port);
+        }
+        if let Some(origins) = args.allowed_origins {
+            self.allowed_origins = origins.split(',').map(str::to_owned).collect();
+        }
+        if let Some(proxies) = args.trusted_proxies {
+            self.trusted_proxies = proxies.split(',').map(str::to_owned).collect();
+        }
+        if let Some(database_path) = args.database_path {
+            self.database_path = database_path;
+        }
+        if args.session_secret.is_some() {
+            self.session_secret = args.session_secret;
+        }
+        if let Some(max_games) = args.max_games {
+            self.max_games = max_games;
+        }
+        if let Some(workers) = args.engine_workers {
+            self.engine.workers = workers;
+        }
+        if let Some(log_level) = args.log_level {
+            self.log_level = log_level;
+        }
+        if args.production {
+            self.production = true;
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        parse_origins(&self.allowed_origins.join(",")).map_err(ConfigError::Invalid)?;
+        parse_proxies(&self.trusted_proxies.join(",")).map_err(ConfigError::Invalid)?;
+        if self.production && self.session_secret.is_none() {
+            return Err(ConfigError::Invalid(
+                "session_secret must be set in production".to_string(),
+            ));
+        }
+        if self.max_games == 0 {
+            return Err(ConfigError::Invalid(
+                "max_games must be at least 1".to_string(),
+            ));
+        }
+        if self.engine.workers == 0 {
+            return Err(ConfigError::Invalid(
+                "engine.workers must be at least 1".to_string(),
+            ));
+        }
+        if self
+            .default_time_control
+            .is_some_and(|time_control| !time_control.is_valid())
+        {
+            return Err(ConfigError::Invalid(format!(
+                "default_time_control needs an initial_ms from 1 to {MAX_INITIAL_MS} \
+                 and an increment_ms up to {MAX_INCREMENT_MS}"
+            )));
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn allowed_origins(&self) -> Vec<HeaderValue> {
+        parse_origins(&self.allowed_origins.join(",")).expect("origins were validated on load")
+    }
+
+    #[must_use]
+    pub fn trusted_proxies(&self) -> Vec<IpAddr> {
+        parse_proxies(&self.trusted_proxies.join(",")).expect("proxies were validated on load")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    #[test]
+    fn file_is_read_with_defaults() {
+        let config = parse(
+            r#"
+            bind = "127.0.0.1:9000"
+            max_games = 50
+            log_level = "debug"
+
+            [default_time_control]
+            initial_ms = 300000
+            increment_ms = 2000
+
+            [engine]
+            workers = 2
+            "#,
+        )
+        .expect("config is valid");
+        assert_eq!(
+            config.bind,
+            "127.0.0.1:9000".parse().expect("valid address")
+        );
+        assert_eq!(config.max_games, 50);
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.engine.workers, 2);
+        assert_eq!(
+            config.default_time_control,
+            Some(TimeControl {
+                initial_ms: 300_000,
+                increment_ms: 2_000
+            })
+        );
+        assert_eq!(config.database_path, PathBuf::from("chess.db"));
+
+        assert!(parse("unknown = 1").is_err());
+        assert!(parse("log_level = \"loud\"").is_err());
+    }
+
+    #[test]
+    fn flags_win_over_the_file() {
+        let mut config = Config::default();
+        config.apply(Args {
+            port: Some(9001),
+            allowed_origins: Some("https://chess.example".to_string()),
+            max_games: Some(10),
+            log_level: Some(LogLevel::Warn),
+            trusted_proxies: Some("127.0.0.1,::1".to_string()),
+            ..Args::default()
+        });
+        assert_eq!(config.bind.port(), 9001);
+        assert_eq!(config.allowed_origins, vec!["https://chess.example"]);
+        assert_eq!(config.max_games, 10);
+        assert_eq!(config.log_level, LogLevel::Warn);
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.trusted_proxies(),
+            vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let invalid = [
+            Config {
+                max_games: 0,
+                ..Config::default()
+            },
+            Config {
+                allowed_origins: vec!["chess.example".to_string()],
+                ..Config::default()
+            },
+            Config {
+                engine: EngineConfig { workers: 0 },
+                ..Config::default()
+            },
+            Config {
+                default_time_control: Some(TimeControl {
+                    initial_ms: 0,
+                    increment_ms: 1_000,
+                }),
+                ..Config::default()
+            },
+            Config {
+                production: true,
+                ..Config::default()
+            },
+            Config {
+                trusted_proxies: vec!["nginx".to_string()],
+                ..Config::default()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{config:?}");
+        }
+        assert!(Config::default().validate().is_ok());
+        assert!(Config {
+            production: true,
+            session_secret: Some("secret".to_string()),
+            ..Config::default()
+        }
+        .validate()
+        .is_ok());
+    }
+}
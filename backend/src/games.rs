@@ -0,0 +1,1509 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use chess::{
+    board_builder::BoardBuilder,
+    fen::to_fen,
+    make_chess_move::MoveError,
+    notation::{move_to_san, move_to_uci},
+    piece::PieceColor,
+    variant::GameVariant,
+    Chess,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, info_span, warn, Span};
+
+use crate::{
+    accounts::{Seat, UserId},
+    chat::{clean_text, ChatChannel, ChatError, ChatMessage},
+    clock::{move_deadline, Clock, TimeControl},
+    metrics::METRICS,
+    protocol::ServerMessage,
+    ratings::white_score,
+    storage::{now, Checkpoint, GameId, GameRecord, GameStore, NewGame, StorageError, Timestamp},
+};
+
+/**
+ * Number of messages a slow websocket client can fall behind before it misses updates
+ */
+const UPDATES_CAPACITY: usize = 64;
+
+/**
+ * Why a player's action on a game was not carried out
+ */
+#[derive(Debug)]
+pub enum GameError {
+    Move(MoveError),
+    InvalidColor,
+    OutOfTime,
+    NotYourSeat,
+    SeatedGame,
+    NothingToTakeBack,
+    NoTakebackRequest,
+    Chat(ChatError),
+    Storage(StorageError),
+}
+
+/**
+ * How much a takeback undoes
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TakebackScope {
+    /**
+     * The requester's last move, and the opponent's reply if there was one
+     */
+    #[default]
+    LastMove,
+    /**
+     * The last move of each side
+     */
+    FullMove,
+}
+
+/**
+ * A takeback waiting for the opponent's answer
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TakebackRequest {
+    pub color: PieceColor,
+    pub plies: usize,
+}
+
+impl GameError {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Move(err) => err.as_str(),
+            Self::InvalidColor => "invalid_color",
+            Self::OutOfTime => "out_of_time",
+            Self::NotYourSeat => "not_your_seat",
+            Self::SeatedGame => "seated_game",
+            Self::NothingToTakeBack => "nothing_to_take_back",
+            Self::NoTakebackRequest => "no_takeback_request",
+            Self::Chat(err) => err.as_str(),
+            Self::Storage(_) => "storage_error",
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Move(err) => err.fmt(f),
+            Self::InvalidColor => write!(f, "color must be White or Black"),
+            Self::OutOfTime => write!(f, "time ran out before the move was made"),
+            Self::NotYourSeat => write!(f, "that side is played by another user"),
+            Self::SeatedGame => write!(f, "games between seated players cannot be reset"),
+            Self::NothingToTakeBack => write!(f, "there is no move to take back"),
+            Self::NoTakebackRequest => {
+                write!(f, "the opponent has not asked for a takeback")
+            }
+            Self::Chat(err) => err.fmt(f),
+            Self::Storage(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl From<StorageError> for GameError {
+    fn from(err: StorageError) -> Self {
+        Self::Storage(err)
+    }
+}
+
+pub struct Game {
+    pub id: GameId,
+    pub chess: Chess,
+    /**
+     * None when anyone may play that side, as on the shared board
+     */
+    pub white: Option<Seat>,
+    pub black: Option<Seat>,
+    /**
+     * Color of the player whose draw offer is waiting for an answer
+     */
+    pub draw_offer: Option<PieceColor>,
+    pub takeback_request: Option<TakebackRequest>,
+    /**
+     * FEN of the set up position the game started from, a reset goes back to it
+     */
+    start_fen: Option<String>,
+    /**
+     * None for untimed games
+     */
+    pub time_control: Option<TimeControl>,
+    pub clock: Option<Clock>,
+    /**
+     * The clock as it was before each move, so a takeback can restore it
+     */
+    clock_history: Vec<Clock>,
+    /**
+     * Set for correspondence games, which have no clock
+     */
+    pub days_per_move: Option<u32>,
+    /**
+     * When the side to move of a correspondence game loses on time
+     */
+    pub deadline: Option<Timestamp>,
+    store: Arc<dyn GameStore>,
+    /**
+     * Games of the registry that have not finished, this one included while it is in progress
+     */
+    in_progress: Arc<AtomicUsize>,
+    updates: broadcast::Sender<String>,
+    /**
+     * Messages only the seated players get, such as their private chat
+     */
+    player_updates: broadcast::Sender<String>,
+    /**
+     * Every event about this game is logged inside this span
+     */
+    span: Span,
+}
+
+impl Game {
+    fn new(id: GameId, chess: Chess, store: Arc<dyn GameStore>) -> Self {
+        let (updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        let (player_updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        let in_progress = Arc::new(AtomicUsize::new(usize::from(
+            !chess.gamestate.is_finished(),
+        )));
+        Self {
+            id,
+            chess,
+            white: None,
+            black: None,
+            draw_offer: None,
+            takeback_request: None,
+            start_fen: None,
+            time_control: None,
+            clock: None,
+            clock_history: Vec::new(),
+            days_per_move: None,
+            deadline: None,
+            store,
+            in_progress,
+            updates,
+            player_updates,
+            span: info_span!(parent: None, "game", game_id = id),
+        }
+    }
+
+    /**
+     * Rebuilds a stored game by replaying its moves from the starting position.
+     * A checkpoint saved at shutdown brings back the clock as it was then, so the
+     * time the server was down is not charged to anyone.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn restore(record: &GameRecord, store: Arc<dyn GameStore>) -> Result<Self, StorageError> {
+        let mut game = Self::new(record.id, replay(record)?, store);
+        game.seat(record.white.clone(), record.black.clone());
+        game.start_fen.clone_from(&record.start_fen);
+        game.time_control = record.time_control;
+        game.clock = record.time_control.map(|time_control| {
+            let mut clock = Clock::new(time_control);
+            for (ply, stored_move) in record.played_moves().enumerate() {
+                let color = if ply % 2 == 0 {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                game.clock_history.push(clock);
+                clock.punch(color, stored_move.played_at);
+            }
+            clock
+        });
+        game.days_per_move = record.days_per_move;
+        let last_moved_at = record
+            .played_moves()
+            .last()
+            .map_or(record.created_at, |stored_move| stored_move.played_at);
+        game.deadline = record
+            .days_per_move
+            .map(|days| move_deadline(last_moved_at, days));
+
+        if let Some(checkpoint) = record
+            .checkpoint
+            .as_ref()
+            .filter(|checkpoint| checkpoint.saved_at >= record.updated_at)
+        {
+            if let (Some(clock), Some(saved)) = (&mut game.clock, checkpoint.clock) {
+                clock.white_ms = saved.white_ms;
+                clock.black_ms = saved.black_ms;
+                clock.resume(now());
+            }
+            game.draw_offer = checkpoint.draw_offer;
+            game.takeback_request = checkpoint.takeback_request;
+        }
+        Ok(game)
+    }
+
+    /**
+     * Saves what only lives in memory of a game in progress, before the server shuts down
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn checkpoint(&self, saved_at: Timestamp) -> Result<(), StorageError> {
+        if self.chess.gamestate.is_finished() {
+            return Ok(());
+        }
+        let to_move = self.chess.side_to_move();
+        self.store.save_checkpoint(
+            self.id,
+            &Checkpoint {
+                saved_at,
+                clock: self.clock.map(|clock| clock.at(to_move, saved_at)),
+                draw_offer: self.draw_offer,
+                takeback_request: self.takeback_request,
+            },
+        )
+    }
+
+    fn seat(&mut self, white: Option<Seat>, black: Option<Seat>) {
+        self.chess.seat_players(
+            white.as_ref().map(|seat| seat.user_id),
+            black.as_ref().map(|seat| seat.user_id),
+        );
+        self.white = white;
+        self.black = black;
+    }
+
+    const fn seat_of(&self, color: PieceColor) -> Option<&Seat> {
+        match color {
+            PieceColor::White => self.white.as_ref(),
+            PieceColor::Black => self.black.as_ref(),
+            PieceColor::None => None,
+        }
+    }
+
+    /**
+     * Whether `user` may act for `color`, anyone may play a side nobody is seated at
+     */
+    #[must_use]
+    pub fn may_play(&self, color: PieceColor, user: Option<UserId>) -> bool {
+        self.seat_of(color)
+            .is_none_or(|seat| user == Some(seat.user_id))
+    }
+
+    /**
+     * Runs `f` against the board and stores every move it made, and the result if the game ended
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn update<T>(&mut self, f: impl FnOnce(&mut Chess) -> T) -> Result<T, StorageError> {
+        self.update_at(now(), f)
+    }
+
+    fn update_at<T>(
+        &mut self,
+        played_at: Timestamp,
+        f: impl FnOnce(&mut Chess) -> T,
+    ) -> Result<T, StorageError> {
+        let moves_before = self.chess.list_of_moves.len();
+        let state_before = self.chess.gamestate;
+        let value = f(&mut self.chess);
+        self.count_in_progress(state_before.is_finished());
+
+        for chess_move in self.chess.list_of_moves.iter().skip(moves_before) {
+            self.store
+                .record_move(self.id, &move_to_uci(chess_move), played_at)?;
+        }
+        if self.chess.gamestate != state_before {
+            self.store
+                .record_result(self.id, self.chess.gamestate, played_at)?;
+            self.record_ratings(played_at)?;
+        }
+        Ok(value)
+    }
+
+    /**
+     * Keeps the registry's count of games in progress right when the game finished or restarted
+     */
+    fn count_in_progress(&self, was_finished: bool) {
+        match (was_finished, self.chess.gamestate.is_finished()) {
+            (false, true) => {
+                self.in_progress.fetch_sub(1, Ordering::Relaxed);
+            }
+            (true, false) => {
+                self.in_progress.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * Counts the game in a registry's games in progress instead of its own
+     */
+    fn join_count(&mut self, in_progress: &Arc<AtomicUsize>) {
+        if !self.chess.gamestate.is_finished() {
+            in_progress.fetch_add(1, Ordering::Relaxed);
+        }
+        self.in_progress = in_progress.clone();
+    }
+
+    /**
+     * Rates both players once a game between two seated players has finished
+     */
+    fn record_ratings(&self, finished_at: Timestamp) -> Result<(), StorageError> {
+        let (Some(white), Some(black)) = (&self.white, &self.black) else {
+            return Ok(());
+        };
+        let Some(white_score) = white_score(self.chess.gamestate) else {
+            return Ok(());
+        };
+        if white.user_id == black.user_id {
+            return Ok(());
+        }
+        self.store
+            .rate_players(white.user_id, black.user_id, white_score, finished_at)?;
+        Ok(())
+    }
+
+    /**
+     * Plays a move given in UCI notation and tells everyone watching.
+     * A player whose time has run out loses instead.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn play(&mut self, uci: &str, user: Option<UserId>) -> Result<(), GameError> {
+        let _entered = self.span.clone().entered();
+        let played = self.try_play(uci, user);
+        match &played {
+            Ok(()) => {
+                METRICS.move_played();
+                info!(uci, "move played");
+            }
+            Err(err) => {
+                METRICS.move_rejected(err.as_str());
+                info!(uci, reason = err.as_str(), "move rejected");
+            }
+        }
+        played
+    }
+
+    fn try_play(&mut self, uci: &str, user: Option<UserId>) -> Result<(), GameError> {
+        let color = self.chess.side_to_move();
+        if !self.may_play(color, user) {
+            return Err(GameError::NotYourSeat);
+        }
+        let played_at = now();
+        if self.expire(played_at)? {
+            return Err(GameError::OutOfTime);
+        }
+
+        let before = self.chess.clone();
+        self.update_at(played_at, |chess| {
+            let started = Instant::now();
+            let made = chess.make_move_from_uci(uci);
+            METRICS.move_validated(started.elapsed());
+            made
+        })?
+        .map_err(GameError::Move)?;
+        self.draw_offer = None;
+        self.takeback_request = None;
+        if let Some(clock) = &mut self.clock {
+            self.clock_history.push(*clock);
+            clock.punch(color, played_at);
+        }
+        self.deadline = self
+            .days_per_move
+            .map(|days| move_deadline(played_at, days));
+
+        let Some(chess_move) = self.chess.list_of_moves.last() else {
+            return Ok(());
+        };
+        let captured = (self.chess.pieces_eaten.len() > before.pieces_eaten.len())
+            .then(|| self.chess.pieces_eaten.last(color.opposite()))
+            .flatten();
+        self.broadcast(&ServerMessage::MoveMade {
+            game_id: self.id,
+            uci: move_to_uci(chess_move),
+            san: move_to_san(&before, chess_move),
+            fen: to_fen(&self.chess),
+            state: self.chess.gamestate,
+            clock: self.clock,
+            deadline: self.deadline,
+            captured,
+        });
+        self.broadcast_game_over();
+        Ok(())
+    }
+
+    /**
+     * Ends the game when the side to move has run out of time at `at`, on the clock or past
+     * the correspondence deadline. It is a draw when the opponent has too little material
+     * to ever win. Returns whether the game was ended.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn expire(&mut self, at: Timestamp) -> Result<bool, StorageError> {
+        let color = self.chess.side_to_move();
+        let out_of_time = self
+            .clock
+            .is_some_and(|clock| clock.remaining(color, color, at) == 0)
+            || self.deadline.is_some_and(|deadline| at >= deadline);
+        if !out_of_time || self.chess.gamestate.is_finished() {
+            return Ok(false);
+        }
+        self.update_at(at, |chess| chess.flag(color))?;
+        self.broadcast_game_over();
+        Ok(true)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn resign(&mut self, color: PieceColor, user: Option<UserId>) -> Result<(), GameError> {
+        self.ensure_playable(color, user)?;
+        self.update(|chess| chess.resign(color))?;
+        self.broadcast_game_over();
+        Ok(())
+    }
+
+    /**
+     * Offers a draw, which is agreed when the opponent has already offered one
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn offer_draw(&mut self, color: PieceColor, user: Option<UserId>) -> Result<(), GameError> {
+        self.ensure_playable(color, user)?;
+        if self
+            .draw_offer
+            .is_some_and(|offered_by| offered_by != color)
+        {
+            self.update(Chess::agree_draw)?;
+            self.draw_offer = None;
+            self.broadcast_game_over();
+        } else {
+            self.draw_offer = Some(color);
+            self.broadcast(&ServerMessage::DrawOffered {
+                game_id: self.id,
+                color,
+            });
+        }
+        Ok(())
+    }
+
+    /**
+     * Whether `user` sits at either side of this game
+     */
+    #[must_use]
+    pub fn is_player(&self, user: Option<UserId>) -> bool {
+        user.is_some_and(|user| {
+            [&self.white, &self.black]
+                .into_iter()
+                .flatten()
+                .any(|seat| seat.user_id == user)
+        })
+    }
+
+    /**
+     * Stores a chat message and sends it to everyone on its channel.
+     * Anyone may talk in the room, the players channel is for seated players only.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn chat(
+        &self,
+        author: Option<&Seat>,
+        channel: ChatChannel,
+        text: &str,
+    ) -> Result<(), GameError> {
+        let text = clean_text(text).map_err(GameError::Chat)?;
+        if channel == ChatChannel::Players && !self.is_player(author.map(|seat| seat.user_id)) {
+            return Err(GameError::Chat(ChatError::PlayersOnly));
+        }
+        let message = ChatMessage {
+            channel,
+            author: author.map(|seat| seat.username.clone()),
+            text,
+            sent_at: now(),
+        };
+        self.store.record_chat(self.id, &message)?;
+        let frame = ServerMessage::Chat {
+            game_id: self.id,
+            message,
+        }
+        .to_json();
+        let updates = match channel {
+            ChatChannel::Room => &self.updates,
+            ChatChannel::Players => &self.player_updates,
+        };
+        // No receivers just means nobody is watching right now
+        let _ = updates.send(frame);
+        Ok(())
+    }
+
+    /**
+     * Asks the opponent of `color` to let them take back moves
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn request_takeback(
+        &mut self,
+        color: PieceColor,
+        scope: TakebackScope,
+        user: Option<UserId>,
+    ) -> Result<(), GameError> {
+        self.ensure_playable(color, user)?;
+        let plies = match scope {
+            TakebackScope::LastMove if self.chess.side_to_move() == color => 2,
+            TakebackScope::LastMove => 1,
+            TakebackScope::FullMove => 2,
+        };
+        if self.chess.list_of_moves.len() < plies {
+            return Err(GameError::NothingToTakeBack);
+        }
+        self.takeback_request = Some(TakebackRequest { color, plies });
+        self.broadcast(&ServerMessage::TakebackRequested {
+            game_id: self.id,
+            color,
+            plies,
+        });
+        Ok(())
+    }
+
+    /**
+     * Accepts or declines the takeback the opponent of `color` asked for
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn answer_takeback(
+        &mut self,
+        color: PieceColor,
+        accept: bool,
+        user: Option<UserId>,
+    ) -> Result<(), GameError> {
+        self.ensure_playable(color, user)?;
+        let request = self
+            .takeback_request
+            .filter(|request| request.color != color)
+            .ok_or(GameError::NoTakebackRequest)?;
+        self.takeback_request = None;
+        if accept {
+            self.take_back(request.plies, now())?;
+        } else {
+            self.broadcast(&ServerMessage::TakebackDeclined {
+                game_id: self.id,
+                color,
+            });
+        }
+        Ok(())
+    }
+
+    /**
+     * Rolls the game back by replaying all but the last `plies` moves,
+     * and gives the clocks back the time they had before those moves
+     */
+    fn take_back(&mut self, plies: usize, at: Timestamp) -> Result<(), GameError> {
+        let keep = self.chess.list_of_moves.len() - plies;
+        let mut chess = Chess::with_variant(self.chess.variant);
+        for chess_move in &self.chess.list_of_moves[..keep] {
+            chess
+                .make_move_from_uci(&move_to_uci(chess_move))
+                .map_err(GameError::Move)?;
+        }
+        chess.players.0.user_id = self.chess.players.0.user_id;
+        chess.players.1.user_id = self.chess.players.1.user_id;
+        #[allow(clippy::cast_possible_truncation)]
+        self.store.take_back(self.id, plies as u32, at)?;
+
+        let was_finished = self.chess.gamestate.is_finished();
+        self.chess = chess;
+        self.count_in_progress(was_finished);
+        self.draw_offer = None;
+        if let Some(mut clock) = self.clock_history.get(keep).copied() {
+            clock.resume(at);
+            self.clock = Some(clock);
+            self.clock_history.truncate(keep);
+        }
+        self.deadline = self.days_per_move.map(|days| move_deadline(at, days));
+        self.span.in_scope(|| info!(plies, "moves taken back"));
+        self.broadcast(&ServerMessage::TakenBack {
+            game_id: self.id,
+            plies,
+            fen: to_fen(&self.chess),
+            clock: self.clock,
+            deadline: self.deadline,
+        });
+        Ok(())
+    }
+
+    /**
+     * Only games nobody is seated at can be reset. The clock starts over from the
+     * time control and a correspondence deadline counts from the reset.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn reset(&mut self) -> Result<(), GameError> {
+        if self.white.is_some() || self.black.is_some() {
+            return Err(GameError::SeatedGame);
+        }
+        let reset_at = now();
+        let was_finished = self.chess.gamestate.is_finished();
+        self.chess = start_position(self.chess.variant, self.start_fen.as_deref())?;
+        self.count_in_progress(was_finished);
+        self.draw_offer = None;
+        self.takeback_request = None;
+        self.clock = self.time_control.map(Clock::new);
+        self.clock_history.clear();
+        self.deadline = self.days_per_move.map(|days| move_deadline(reset_at, days));
+        self.store.reset_game(self.id, reset_at)?;
+        self.span.in_scope(|| info!("game reset"));
+        self.broadcast(&self.snapshot());
+        Ok(())
+    }
+
+    fn ensure_playable(&self, color: PieceColor, user: Option<UserId>) -> Result<(), GameError> {
+        if self.chess.gamestate.is_finished() {
+            return Err(GameError::Move(MoveError::GameOver));
+        }
+        if color == PieceColor::None {
+            return Err(GameError::InvalidColor);
+        }
+        if !self.may_play(color, user) {
+            return Err(GameError::NotYourSeat);
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> ServerMessage {
+        ServerMessage::State {
+            game_id: self.id,
+            chess: Box::new(self.chess.clone()),
+            clock: self.clock,
+            deadline: self.deadline,
+        }
+    }
+
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.updates.subscribe()
+    }
+
+    /**
+     * Feed of player only messages, None unless `user` is seated at this game
+     */
+    #[must_use]
+    pub fn subscribe_as_player(&self, user: Option<UserId>) -> Option<broadcast::Receiver<String>> {
+        self.is_player(user)
+            .then(|| self.player_updates.subscribe())
+    }
+
+    /**
+     * Sends `message` to every client watching this game
+     */
+    pub fn broadcast(&self, message: &ServerMessage) {
+        // No receivers just means nobody is watching right now
+        let _ = self.updates.send(message.to_json());
+    }
+
+    /**
+     * Whether the game is over and no client is left watching it
+     */
+    fn is_abandoned(&self) -> bool {
+        self.chess.gamestate.is_finished()
+            && self.updates.receiver_count() == 0
+            && self.player_updates.receiver_count() == 0
+    }
+
+    fn broadcast_game_over(&self) {
+        if self.chess.gamestate.is_finished() {
+            self.span
+                .in_scope(|| info!(result = self.chess.gamestate.as_str(), "game over"));
+            self.broadcast(&ServerMessage::GameOver {
+                game_id: self.id,
+                result: self.chess.gamestate,
+            });
+        }
+    }
+}
+
+/**
+ * The variant's starting position, or the set up position a game was created from
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn start_position(
+    variant: GameVariant,
+    start_fen: Option<&str>,
+) -> Result<Chess, StorageError> {
+    let Some(fen) = start_fen else {
+        return Ok(Chess::with_variant(variant));
+    };
+    BoardBuilder::from_fen(fen)
+        .and_then(BoardBuilder::build)
+        .map_err(|err| StorageError::Corrupted(format!("start position {fen}: {err}")))
+}
+
+/**
+ * Replays the stored moves of a game from its starting position. A game that ended
+ * off the board, by resignation, on time or by agreement, gets its stored result back.
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn replay(record: &GameRecord) -> Result<Chess, StorageError> {
+    let mut chess = start_position(record.variant, record.start_fen.as_deref())?;
+    for stored_move in record.played_moves() {
+        chess.make_move_from_uci(&stored_move.uci).map_err(|err| {
+            StorageError::Corrupted(format!(
+                "game {} move {} ({}): {err}",
+                record.id, stored_move.ply, stored_move.uci
+            ))
+        })?;
+    }
+    if record.state.is_finished() {
+        chess.gamestate = record.state;
+        chess.players.0.victory = record.state.winner() == PieceColor::White;
+        chess.players.1.victory = record.state.winner() == PieceColor::Black;
+    }
+    Ok(chess)
+}
+
+const fn is_shared(white: Option<&Seat>, black: Option<&Seat>) -> bool {
+    white.is_none() && black.is_none()
+}
+
+pub struct GameRegistry {
+    store: Arc<dyn GameStore>,
+    games: HashMap<GameId, Arc<Mutex<Game>>>,
+    shared: Option<GameId>,
+    max_games: usize,
+    /**
+     * Kept up to date by the games themselves, so counting them takes no game lock
+     */
+    in_progress: Arc<AtomicUsize>,
+}
+
+impl GameRegistry {
+    /**
+     * Loads every game that was still in progress when the server last stopped.
+     * Games whose moves no longer replay are skipped.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn restore(store: Arc<dyn GameStore>) -> Result<Self, StorageError> {
+        let mut games = HashMap::new();
+        let mut shared = None;
+        let in_progress = Arc::new(AtomicUsize::new(0));
+        for record in store.in_progress_games()? {
+            let id = record.id;
+            match Game::restore(&record, store.clone()) {
+                Ok(mut game) => {
+                    game.join_count(&in_progress);
+                    if is_shared(record.white.as_ref(), record.black.as_ref()) {
+                        shared = shared.max(Some(id));
+                    }
+                    games.insert(id, Arc::new(Mutex::new(game)));
+                }
+                Err(err) => warn!(game_id = id, %err, "skipping a game that does not replay"),
+            }
+        }
+        Ok(Self {
+            store,
+            games,
+            shared,
+            max_games: usize::MAX,
+            in_progress,
+        })
+    }
+
+    /**
+     * Caps the number of games in progress that `is_full` allows
+     */
+    #[must_use]
+    pub const fn with_max_games(mut self, max_games: usize) -> Self {
+        self.max_games = max_games;
+        self
+    }
+
+    /**
+     * Number of games that have not finished yet
+     */
+    #[must_use]
+    pub fn in_progress(&self) -> usize {
+        self.in_progress.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Whether as many games are in progress as the server may run
+     */
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.in_progress() >= self.max_games
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn create_game(&mut self, new_game: &NewGame) -> Result<Arc<Mutex<Game>>, StorageError> {
+        let chess = start_position(new_game.variant, new_game.start_fen.as_deref())?;
+        let id = self.store.create_game(new_game, now())?;
+        let mut game = Game::new(id, chess, self.store.clone());
+        game.seat(new_game.white.clone(), new_game.black.clone());
+        game.start_fen.clone_from(&new_game.start_fen);
+        game.time_control = new_game.time_control;
+        game.clock = new_game.time_control.map(Clock::new);
+        game.days_per_move = new_game.days_per_move;
+        game.deadline = new_game
+            .days_per_move
+            .map(|days| move_deadline(now(), days));
+        game.join_count(&self.in_progress);
+        let game = Arc::new(Mutex::new(game));
+        self.games.insert(id, game.clone());
+        if is_shared(new_game.white.as_ref(), new_game.black.as_ref()) {
+            self.shared = Some(id);
+        }
+        Ok(game)
+    }
+
+    #[must_use]
+    pub fn get(&self, id: GameId) -> Option<Arc<Mutex<Game>>> {
+        self.games.get(&id).cloned()
+    }
+
+    /**
+     * The newest game without seated players, which is the shared board clients join
+     */
+    #[must_use]
+    pub fn shared(&self) -> Option<Arc<Mutex<Game>>> {
+        self.shared.and_then(|id| self.games.get(&id).cloned())
+    }
+
+    /**
+     * Every game kept in memory, finished ones included
+     */
+    #[must_use]
+    pub fn games(&self) -> Vec<Arc<Mutex<Game>>> {
+        self.games.values().cloned().collect()
+    }
+
+    /**
+     * Drops finished games nobody watches any more, their records stay in the store.
+     * The shared board is kept so it can be reset, and games busy with a request are
+     * left for the next call.
+     */
+    pub fn evict_finished(&mut self) -> usize {
+        let before = self.games.len();
+        let shared = self.shared;
+        self.games.retain(|&id, game| {
+            Some(id) == shared || game.try_lock().map_or(true, |game| !game.is_abandoned())
+        });
+        before - self.games.len()
+    }
+
+    #[must_use]
+    pub fn store(&self) -> Arc<dyn GameStore> {
+        self.store.clone()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStore;
+    use chess::{
+        chessboard::{file::File, rank::Rank},
+        game_state::GameState,
+        piece::Piece,
+        variant::GameVariant,
+    };
+
+    #[tokio::test]
+    async fn moves_are_stored_and_restored() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame::default())
+            .expect("game is created");
+        let id = {
+            let mut game = game.lock().await;
+            game.update(|chess| {
+                chess.make_move_from_str("f2", "f3", None);
+                chess.make_move_from_str("e7", "e5", None);
+                chess.make_move_from_str("a2", "a5", None);
+            })
+            .expect("moves are stored");
+            game.id
+        };
+
+        let restored = GameRegistry::restore(store.clone()).expect("restore works");
+        assert_eq!(restored.len(), 1);
+        let game = restored.get(id).expect("game is restored");
+        let game = game.lock().await;
+        assert_eq!(game.chess.list_of_moves.len(), 2);
+        assert_eq!(game.chess.turn_number, 2);
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn chess960_games_are_restored() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                variant: GameVariant::Chess960(0),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let id = game.lock().await.id;
+        // Clear c1, d1 and e1, then castle queenside with the king taking its f1 rook
+        for uci in [
+            "d1e3", "a7a6", "e1d3", "a6a5", "b2b3", "a5a4", "c1b2", "h7h6", "g1f1",
+        ] {
+            game.lock()
+                .await
+                .update(|chess| chess.make_move_from_uci(uci))
+                .expect("move is stored")
+                .expect("legal move");
+        }
+
+        let restored = GameRegistry::restore(store).expect("restore works");
+        let game = restored.get(id).expect("game is restored");
+        let game = game.lock().await;
+        assert_eq!(game.chess.variant, GameVariant::Chess960(0));
+        assert_eq!(
+            game.chess.get_square(File::C, Rank::First).piece,
+            Piece::King(PieceColor::White)
+        );
+        assert_eq!(
+            game.chess.get_square(File::D, Rank::First).piece,
+            Piece::Rook(PieceColor::White)
+        );
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn crazyhouse_drops_are_restored() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                variant: GameVariant::Crazyhouse,
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let id = game.lock().await.id;
+        for uci in ["e2e4", "d7d5", "e4d5", "d8d5", "P@e4"] {
+            game.lock()
+                .await
+                .update(|chess| chess.make_move_from_uci(uci))
+                .expect("move is stored")
+                .expect("legal move");
+        }
+
+        let restored = GameRegistry::restore(store).expect("restore works");
+        let game = restored.get(id).expect("game is restored");
+        let game = game.lock().await;
+        assert_eq!(
+            game.chess.get_square(File::E, Rank::Fourth).piece,
+            Piece::Pawn(PieceColor::White)
+        );
+        assert!(game.chess.players.0.pocket.is_empty());
+        assert_eq!(
+            game.chess
+                .players
+                .1
+                .pocket
+                .count(Piece::Pawn(PieceColor::Black)),
+            1
+        );
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn set_up_positions_are_restored_and_reset() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let start_fen = "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1";
+        let game = registry
+            .create_game(&NewGame {
+                start_fen: Some(start_fen.to_string()),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let id = game.lock().await.id;
+        game.lock()
+            .await
+            .play("e1c1", None)
+            .expect("castling is legal");
+
+        let restored = GameRegistry::restore(store).expect("restore works");
+        let game = restored.get(id).expect("game is restored");
+        let mut game = game.lock().await;
+        assert_eq!(to_fen(&game.chess), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+        game.reset().expect("shared games reset");
+        assert_eq!(to_fen(&game.chess), start_fen);
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn finished_games_are_not_restored() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone())
+            .expect("restore works")
+            .with_max_games(1);
+        assert!(!registry.is_full());
+        let game = registry
+            .create_game(&NewGame::default())
+            .expect("game is created");
+        assert!(registry.is_full());
+        game.lock()
+            .await
+            .update(|chess| {
+                for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+                    chess.make_move_from_uci(uci).expect("legal move");
+                }
+            })
+            .expect("moves are stored");
+        assert_eq!(game.lock().await.chess.gamestate, GameState::BlackVictory);
+        assert!(!registry.is_full());
+
+        let restored = GameRegistry::restore(store).expect("restore works");
+        assert_eq!(restored.len(), 0);
+
+        game.lock().await.reset().expect("shared game resets");
+        assert_eq!(registry.in_progress(), 1);
+        assert!(registry.is_full());
+    }
+
+    #[tokio::test]
+    async fn draw_is_agreed_when_both_players_offer() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame::default())
+            .expect("game is created");
+        let mut game = game.lock().await;
+        let mut updates = game.subscribe();
+
+        game.offer_draw(PieceColor::White, None)
+            .expect("offer is made");
+        game.offer_draw(PieceColor::White, None)
+            .expect("offer is repeated");
+        assert_eq!(game.chess.gamestate, GameState::InProgress);
+        assert!(matches!(
+            game.offer_draw(PieceColor::None, None),
+            Err(GameError::InvalidColor)
+        ));
+
+        game.offer_draw(PieceColor::Black, None)
+            .expect("offer is accepted");
+        assert_eq!(game.chess.gamestate, GameState::Draw);
+        assert!(matches!(
+            game.play("e2e4", None),
+            Err(GameError::Move(MoveError::GameOver))
+        ));
+        let id = game.id;
+        drop(game);
+
+        let mut last_update = String::new();
+        while let Ok(update) = updates.try_recv() {
+            last_update = update;
+        }
+        assert!(last_update.contains(r#""type":"game_over""#));
+        let record = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        assert_eq!(record.state, GameState::Draw);
+    }
+
+    #[tokio::test]
+    async fn moves_are_broadcast_without_the_board() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                time_control: Some(TimeControl {
+                    initial_ms: 60_000,
+                    increment_ms: 0,
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        let mut updates = game.subscribe();
+        for uci in ["e2e4", "d7d5", "e4d5"] {
+            game.play(uci, None).expect("legal move");
+        }
+        drop(game);
+
+        let mut last_update = String::new();
+        while let Ok(update) = updates.try_recv() {
+            last_update = update;
+        }
+        let update: serde_json::Value = serde_json::from_str(&last_update).expect("valid json");
+        assert_eq!(update["type"], "move_made");
+        assert_eq!(update["uci"], "e4d5");
+        assert_eq!(update["san"], "exd5");
+        assert_eq!(
+            update["fen"],
+            "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2"
+        );
+        assert_eq!(update["captured"], serde_json::json!({ "Pawn": "Black" }));
+        assert!(update["clock"]["white_ms"].is_u64());
+        assert!(update.get("chess").is_none());
+    }
+
+    #[tokio::test]
+    async fn checkpoints_survive_a_restart() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                time_control: Some(TimeControl {
+                    initial_ms: 60_000,
+                    increment_ms: 0,
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        game.play("e2e4", None).expect("legal move");
+        game.offer_draw(PieceColor::White, None)
+            .expect("draw can be offered");
+        // Black thinks for 10 seconds before the server goes down
+        game.checkpoint(now() + 10_000)
+            .expect("checkpoint is stored");
+        let id = game.id;
+        drop(game);
+
+        let registry = GameRegistry::restore(store).expect("restore works");
+        let game = registry.get(id).expect("game is restored");
+        let game = game.lock().await;
+        assert_eq!(game.draw_offer, Some(PieceColor::White));
+        let clock = game.clock.expect("game is timed");
+        let black_ms = clock.remaining(PieceColor::Black, PieceColor::Black, now());
+        assert!((49_000..=50_000).contains(&black_ms), "{black_ms}");
+        assert_eq!(clock.white_ms, 60_000);
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn flagged_player_loses() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                time_control: Some(TimeControl {
+                    initial_ms: 0,
+                    increment_ms: 0,
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        assert!(matches!(game.play("e2e4", None), Err(GameError::OutOfTime)));
+        assert_eq!(game.chess.gamestate, GameState::BlackVictory);
+        drop(game);
+
+        let game = registry
+            .create_game(&NewGame {
+                time_control: Some(TimeControl {
+                    initial_ms: 0,
+                    increment_ms: 0,
+                }),
+                start_fen: Some("4k3/8/8/8/8/8/8/3QK3 w - - 0 1".to_string()),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        assert!(matches!(game.play("d1d7", None), Err(GameError::OutOfTime)));
+        assert_eq!(game.chess.gamestate, GameState::InsufficientMaterial);
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn finished_games_are_evicted_once_nobody_watches() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store).expect("restore works");
+        let shared = registry
+            .create_game(&NewGame::default())
+            .expect("shared game is created");
+        let seat = |user_id, username: &str| {
+            Some(Seat {
+                user_id,
+                username: username.to_string(),
+            })
+        };
+        let game = registry
+            .create_game(&NewGame {
+                white: seat(1, "alice"),
+                black: seat(2, "bob"),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let id = game.lock().await.id;
+        let watcher = game.lock().await.subscribe();
+        shared
+            .lock()
+            .await
+            .resign(PieceColor::White, None)
+            .expect("shared game is resigned");
+        game.lock()
+            .await
+            .resign(PieceColor::White, Some(1))
+            .expect("white resigns");
+
+        assert_eq!(registry.evict_finished(), 0);
+        drop(watcher);
+        assert_eq!(registry.evict_finished(), 1);
+        assert!(registry.get(id).is_none());
+        assert!(registry.shared().is_some());
+    }
+
+    #[tokio::test]
+    async fn finished_games_are_rated() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                white: Some(Seat {
+                    user_id: 1,
+                    username: "alice".to_string(),
+                }),
+                black: Some(Seat {
+                    user_id: 2,
+                    username: "bob".to_string(),
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        game.lock()
+            .await
+            .resign(PieceColor::Black, Some(2))
+            .expect("black resigns");
+
+        let alice = store.rating(1).expect("query works").expect("rated");
+        let bob = store.rating(2).expect("query works").expect("rated");
+        assert!(alice.glicko > bob.glicko);
+        assert!(alice.elo > bob.elo);
+        assert_eq!((alice.games, bob.games), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn only_seated_users_play_their_side() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                white: Some(Seat {
+                    user_id: 1,
+                    username: "alice".to_string(),
+                }),
+                black: Some(Seat {
+                    user_id: 2,
+                    username: "bob".to_string(),
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        assert_eq!(game.chess.players.1.user_id, Some(2));
+        assert!(matches!(
+            game.play("e2e4", None),
+            Err(GameError::NotYourSeat)
+        ));
+        assert!(matches!(
+            game.play("e2e4", Some(2)),
+            Err(GameError::NotYourSeat)
+        ));
+        game.play("e2e4", Some(1)).expect("alice plays white");
+        assert!(matches!(
+            game.resign(PieceColor::Black, Some(1)),
+            Err(GameError::NotYourSeat)
+        ));
+        assert!(matches!(game.reset(), Err(GameError::SeatedGame)));
+        let id = game.id;
+        drop(game);
+
+        let restored = GameRegistry::restore(store).expect("restore works");
+        let game = restored.get(id).expect("game is restored");
+        let game = game.lock().await;
+        assert_eq!(game.black.as_ref().map(|seat| seat.user_id), Some(2));
+        assert!(game.may_play(PieceColor::Black, Some(2)));
+        assert!(!game.may_play(PieceColor::Black, Some(1)));
+        drop(game);
+    }
+
+    #[tokio::test]
+    async fn correspondence_games_expire() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                days_per_move: Some(2),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        game.play("e2e4", None).expect("legal move");
+        let deadline = game.deadline.expect("correspondence games have a deadline");
+        assert!(deadline >= now() + 2 * 86_400_000 - 1_000);
+        assert!(!game.expire(deadline - 1).expect("expiry works"));
+        let id = game.id;
+        drop(game);
+
+        let restored = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = restored.get(id).expect("game is restored");
+        let mut game = game.lock().await;
+        assert_eq!(game.deadline, Some(deadline));
+        assert!(game.expire(deadline).expect("expiry works"));
+        assert_eq!(game.chess.gamestate, GameState::WhiteVictory);
+        drop(game);
+        let record = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        assert_eq!(record.state, GameState::WhiteVictory);
+    }
+
+    #[tokio::test]
+    async fn reset_restarts_the_clock_and_deadline() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store).expect("restore works");
+        let time_control = TimeControl {
+            initial_ms: 60_000,
+            increment_ms: 1_000,
+        };
+        let timed = registry
+            .create_game(&NewGame {
+                time_control: Some(time_control),
+                ..NewGame::default()
+            })
+            .expect("timed game is created");
+        let mut timed = timed.lock().await;
+        timed.play("e2e4", None).expect("legal move");
+        timed.play("e7e5", None).expect("legal move");
+        assert_ne!(timed.clock, Some(Clock::new(time_control)));
+        timed.reset().expect("shared games reset");
+        assert_eq!(timed.clock, Some(Clock::new(time_control)));
+        drop(timed);
+
+        let correspondence = registry
+            .create_game(&NewGame {
+                days_per_move: Some(2),
+                ..NewGame::default()
+            })
+            .expect("correspondence game is created");
+        let mut correspondence = correspondence.lock().await;
+        correspondence.deadline = Some(0);
+        correspondence.reset().expect("shared games reset");
+        let deadline = correspondence.deadline.expect("deadline is set again");
+        assert!(deadline >= now() + 2 * 86_400_000 - 1_000);
+        assert!(!correspondence.expire(deadline - 1).expect("expiry works"));
+        drop(correspondence);
+    }
+
+    #[tokio::test]
+    async fn takebacks_roll_the_game_back() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let game = registry
+            .create_game(&NewGame {
+                time_control: Some(TimeControl {
+                    initial_ms: 60_000,
+                    increment_ms: 0,
+                }),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let mut game = game.lock().await;
+        assert!(matches!(
+            game.request_takeback(PieceColor::White, TakebackScope::LastMove, None),
+            Err(GameError::NothingToTakeBack)
+        ));
+        for uci in ["e2e4", "e7e5", "g1f3"] {
+            game.play(uci, None).expect("legal move");
+        }
+        let clock_after_e4 = game.clock_history[1];
+
+        game.request_takeback(PieceColor::White, TakebackScope::LastMove, None)
+            .expect("white moved last");
+        assert!(matches!(
+            game.answer_takeback(PieceColor::White, true, None),
+            Err(GameError::NoTakebackRequest)
+        ));
+        game.answer_takeback(PieceColor::Black, false, None)
+            .expect("black declines");
+        assert!(game.takeback_request.is_none());
+
+        game.request_takeback(PieceColor::Black, TakebackScope::LastMove, None)
+            .expect("black moved before white");
+        game.answer_takeback(PieceColor::White, true, None)
+            .expect("white accepts");
+        assert_eq!(game.chess.list_of_moves.len(), 1);
+        assert_eq!(game.chess.side_to_move(), PieceColor::Black);
+        let clock = game.clock.expect("game is timed");
+        assert_eq!(clock.white_ms, clock_after_e4.white_ms);
+        assert_eq!(clock.black_ms, clock_after_e4.black_ms);
+        game.play("c7c5", None).expect("black plays another move");
+        let id = game.id;
+        drop(game);
+
+        let record = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        let moves: Vec<&str> = record.played_moves().map(|m| m.uci.as_str()).collect();
+        assert_eq!(moves, vec!["e2e4", "c7c5"]);
+        let taken_back: Vec<&str> = record
+            .moves
+            .iter()
+            .filter(|m| m.taken_back_at.is_some())
+            .map(|m| m.uci.as_str())
+            .collect();
+        assert_eq!(taken_back, vec!["e7e5", "g1f3"]);
+        assert_eq!(
+            replay(&record).expect("replay works").list_of_moves.len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn chat_is_stored_and_players_only_stays_private() {
+        let store: Arc<dyn GameStore> = Arc::new(MemoryStore::new());
+        let mut registry = GameRegistry::restore(store.clone()).expect("restore works");
+        let alice = Seat {
+            user_id: 1,
+            username: "alice".to_string(),
+        };
+        let game = registry
+            .create_game(&NewGame {
+                white: Some(alice.clone()),
+                ..NewGame::default()
+            })
+            .expect("game is created");
+        let game = game.lock().await;
+        let mut room = game.subscribe();
+        let mut players = game.subscribe_as_player(Some(1)).expect("alice is seated");
+        assert!(game.subscribe_as_player(Some(2)).is_none());
+        assert!(game.subscribe_as_player(None).is_none());
+
+        game.chat(None, ChatChannel::Room, " hello ")
+            .expect("spectators may talk");
+        game.chat(Some(&alice), ChatChannel::Players, "gl")
+            .expect("players may talk privately");
+        assert!(matches!(
+            game.chat(None, ChatChannel::Players, "let me in"),
+            Err(GameError::Chat(ChatError::PlayersOnly))
+        ));
+        assert!(matches!(
+            game.chat(Some(&alice), ChatChannel::Room, ""),
+            Err(GameError::Chat(ChatError::Empty))
+        ));
+        let id = game.id;
+        drop(game);
+
+        let room_message = room.try_recv().expect("room got a message");
+        assert!(room_message.contains(r#""text":"hello""#));
+        assert!(room.try_recv().is_err());
+        let player_message = players.try_recv().expect("players got a message");
+        assert!(player_message.contains(r#""channel":"players""#));
+
+        let stored = store.chat_messages(id).expect("query works");
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].author.as_deref(), Some("alice"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, Extension, Json};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::{storage::now, workers::WorkerPool, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct GameCounts {
+    total: usize,
+    in_progress: usize,
+}
+
+impl GameCounts {
+    fn of(state: &State) -> Self {
+        Self {
+            total: state.games.len(),
+            in_progress: state.games.in_progress(),
+        }
+    }
+}
+
+/**
+ * Liveness, answered as long as the server can take its state lock
+ */
+pub async fn get_healthz(Extension(state): Extension<Arc<Mutex<State>>>) -> Json<Value> {
+    let state = state.lock().await;
+    let uptime_ms = now().saturating_sub(state.started_at);
+    let games = GameCounts::of(&state);
+    drop(state);
+    Json(json!({
+        "status": "ok",
+        "uptime_ms": uptime_ms,
+        "games": games,
+    }))
+}
+
+/**
+ * Readiness, which fails with 503 while the database cannot be reached or the server shuts down
+ */
+pub async fn get_readyz(
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Extension(workers): Extension<Arc<WorkerPool>>,
+) -> (StatusCode, Json<Value>) {
+    let state = state.lock().await;
+    let uptime_ms = now().saturating_sub(state.started_at);
+    let games = GameCounts::of(&state);
+    let shutting_down = *state.shutdown.borrow();
+    let store = state.games.store();
+    drop(state);
+
+    let storage = store.ping();
+    let ready = storage.is_ok() && !shutting_down;
+    let storage = match storage {
+        Ok(()) => json!({ "status": "ok" }),
+        Err(err) => json!({ "status": "error", "error": err.to_string() }),
+    };
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "uptime_ms": uptime_ms,
+            "shutting_down": shutting_down,
+            "games": games,
+            "storage": storage,
+            "workers": workers.status(),
+        })),
+    )
+}
+
+/**
+ * What the running server is doing, for operators
+ */
+pub async fn get_status(Extension(state): Extension<Arc<Mutex<State>>>) -> Json<Value> {
+    let state = state.lock().await;
+    let games = GameCounts::of(&state);
+    let status = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "started_at": state.started_at,
+        "uptime_ms": now().saturating_sub(state.started_at),
+        "shutting_down": *state.shutdown.borrow(),
+        "connected_clients": state.clients_count,
+        "games": games,
+        "lobby": {
+            "challenges": state.lobby.challenges_len(),
+            "queue": state.lobby.queue_len(),
+        },
+    });
+    drop(state);
+    Json(status)
+}
@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+
+use crate::{
+    accounts::UserId,
+    storage::{now, Timestamp},
+};
+
+/**
+ * Largest websocket frame or message a client may send, client messages are small JSON objects
+ */
+pub const MAX_FRAME_SIZE: usize = 16 * 1024;
+/**
+ * Beyond this many keys a rate limiter forgets the buckets that have filled up again
+ */
+const MAX_TRACKED_KEYS: usize = 10_000;
+/**
+ * Open websocket connections allowed from one IP address
+ */
+const MAX_CONNECTIONS_PER_IP: usize = 16;
+
+pub const REQUESTS_PER_IP: Rate = Rate {
+    burst: 120,
+    per_second: 20,
+};
+pub const CONNECTS_PER_IP: Rate = Rate {
+    burst: 10,
+    per_second: 1,
+};
+pub const MESSAGES_PER_CONNECTION: Rate = Rate {
+    burst: 20,
+    per_second: 5,
+};
+pub const MESSAGES_PER_SESSION: Rate = Rate {
+    burst: 40,
+    per_second: 10,
+};
+
+/**
+ * How many tokens a reset costs, since it disrupts everyone watching the shared board
+ */
+pub const RESET_COST: u64 = 10;
+
+/**
+ * Up to `burst` tokens, refilled at `per_second`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub burst: u64,
+    pub per_second: u64,
+}
+
+/**
+ * Tokens are kept in thousandths so they refill every millisecond
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    rate: Rate,
+    millitokens: u64,
+    updated_at: Timestamp,
+}
+
+impl TokenBucket {
+    /**
+     * A full bucket
+     */
+    #[must_use]
+    pub const fn new(rate: Rate, now: Timestamp) -> Self {
+        Self {
+            rate,
+            millitokens: rate.burst * 1000,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Timestamp) {
+        let elapsed = now.saturating_sub(self.updated_at);
+        self.millitokens = self
+            .millitokens
+            .saturating_add(elapsed.saturating_mul(self.rate.per_second))
+            .min(self.rate.burst * 1000);
+        self.updated_at = self.updated_at.max(now);
+    }
+
+    /**
+     * Takes `cost` tokens, unless there are not that many left
+     */
+    pub fn take(&mut self, cost: u64, now: Timestamp) -> bool {
+        self.refill(now);
+        match self.millitokens.checked_sub(cost * 1000) {
+            Some(left) => {
+                self.millitokens = left;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_full(&mut self, now: Timestamp) -> bool {
+        self.refill(now);
+        self.millitokens == self.rate.burst * 1000
+    }
+}
+
+/**
+ * One token bucket per key, such as an IP address or a user
+ */
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    rate: Rate,
+    buckets: Mutex<HashMap<K, TokenBucket>>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    #[must_use]
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            rate,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn take(&self, key: K, cost: u64, now: Timestamp) -> bool {
+        let mut buckets = self.buckets.lock().expect("rate limiter lock is poisoned");
+        if buckets.len() >= MAX_TRACKED_KEYS {
+            // Full buckets behave exactly like missing ones
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(self.rate, now))
+            .take(cost, now)
+    }
+}
+
+/**
+ * Limits shared by every request and websocket connection
+ */
+#[derive(Debug)]
+pub struct Limits {
+    pub requests: RateLimiter<IpAddr>,
+    pub connects: RateLimiter<IpAddr>,
+    pub session_messages: RateLimiter<UserId>,
+    open_connections: Mutex<HashMap<IpAddr, usize>>,
+    /**
+     * Origins other than the server's own that browsers may use the API from
+     */
+    pub allowed_origins: Vec<HeaderValue>,
+    /**
+     * Reverse proxies whose `X-Real-IP` and `X-Forwarded-For` headers name the client
+     */
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl Limits {
+    #[must_use]
+    pub fn new(allowed_origins: Vec<HeaderValue>) -> Self {
+        Self {
+            requests: RateLimiter::new(REQUESTS_PER_IP),
+            connects: RateLimiter::new(CONNECTS_PER_IP),
+            session_messages: RateLimiter::new(MESSAGES_PER_SESSION),
+            open_connections: Mutex::new(HashMap::new()),
+            allowed_origins,
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /**
+     * The address limits are kept for. Behind a trusted proxy that is the client the proxy
+     * names, the nearest address in `X-Forwarded-For` that is not a proxy itself.
+     */
+    #[must_use]
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+        let header_ips = |name: &str| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+                .collect::<Vec<_>>()
+        };
+        header_ips("x-forwarded-for")
+            .into_iter()
+            .rev()
+            .find(|ip| !self.trusted_proxies.contains(ip))
+            .or_else(|| header_ips("x-real-ip").into_iter().next())
+            .unwrap_or(peer)
+    }
+
+    /**
+     * Holds one of the connections an IP address may have open, None when it has none left
+     */
+    #[must_use]
+    pub fn open_connection(self: &Arc<Self>, ip: IpAddr) -> Option<ConnectionSlot> {
+        let mut open = self
+            .open_connections
+            .lock()
+            .expect("connection count lock is poisoned");
+        let count = open.entry(ip).or_default();
+        if *count >= MAX_CONNECTIONS_PER_IP {
+            return None;
+        }
+        *count += 1;
+        drop(open);
+        Some(ConnectionSlot {
+            limits: self.clone(),
+            ip,
+        })
+    }
+
+    /**
+     * Whether a browser on `origin` may open a websocket to the server at `host`.
+     * Browsers do not apply CORS to websockets, so the upgrade checks the origin itself.
+     */
+    #[must_use]
+    pub fn origin_allowed(&self, origin: &HeaderValue, host: Option<&HeaderValue>) -> bool {
+        let same_origin = host
+            .and_then(|host| host.to_str().ok())
+            .is_some_and(|host| {
+                origin.to_str().ok().is_some_and(|origin| {
+                    origin
+                        .split_once("://")
+                        .is_some_and(|(_, origin_host)| origin_host == host)
+                })
+            });
+        same_origin || self.allowed_origins.contains(origin)
+    }
+}
+
+/**
+ * An open websocket connection, given back when dropped
+ */
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    limits: Arc<Limits>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut open = self
+            .limits
+            .open_connections
+            .lock()
+            .expect("connection count lock is poisoned");
+        if let Some(count) = open.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/**
+ * Parses a comma separated list of origins such as `https://chess.example,http://localhost:5173`
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn parse_origins(origins: &str) -> Result<Vec<HeaderValue>, String> {
+    origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(format!(
+                    "origin {origin} must start with http:// or https://"
+                ));
+            }
+            HeaderValue::from_str(origin.trim_end_matches('/'))
+                .map_err(|_| format!("origin {origin} is not a valid header value"))
+        })
+        .collect()
+}
+
+/**
+ * Parses a comma separated list of IP addresses such as `127.0.0.1,::1`
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn parse_proxies(proxies: &str) -> Result<Vec<IpAddr>, String> {
+    proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy
+                .parse()
+                .map_err(|_| format!("trusted proxy {proxy} is not an IP address"))
+        })
+        .collect()
+}
+
+/**
+ * Answer for clients over their limit
+ */
+#[must_use]
+pub fn too_many_requests(error: &str) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, "1")],
+        Json(json!({ "error": error, "reason": "rate_limited" })),
+    )
+        .into_response()
+}
+
+/**
+ * Middleware limiting the requests of each client IP address
+ */
+pub async fn limit_requests(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(limits): Extension<Arc<Limits>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = limits.client_ip(addr.ip(), request.headers());
+    if limits.requests.take(ip, 1, now()) {
+        next.run(request).await
+    } else {
+        too_many_requests("too many requests, slow down")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: Rate = Rate {
+        burst: 3,
+        per_second: 2,
+    };
+
+    #[test]
+    fn token_bucket_works() {
+        let mut bucket = TokenBucket::new(RATE, 0);
+        assert!(bucket.take(1, 0));
+        assert!(bucket.take(2, 0));
+        assert!(!bucket.take(1, 0));
+        assert!(!bucket.take(1, 499));
+        assert!(bucket.take(1, 500));
+        assert!(!bucket.take(2, 1_000));
+        assert!(bucket.take(1, 1_000));
+
+        // Refilling stops at the burst size
+        assert!(bucket.take(3, 60_000));
+        assert!(!bucket.take(1, 60_000));
+        assert!(!bucket.take(4, 120_000));
+    }
+
+    #[test]
+    fn rate_limiter_keeps_a_bucket_per_key() {
+        let limiter = RateLimiter::new(RATE);
+        assert!(limiter.take(1, 3, 0));
+        assert!(!limiter.take(1, 1, 0));
+        assert!(limiter.take(2, 3, 0));
+    }
+
+    #[test]
+    fn connections_per_ip_are_limited() {
+        let limits = Arc::new(Limits::new(Vec::new()));
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let slots: Vec<_> = (0..MAX_CONNECTIONS_PER_IP)
+            .map(|_| limits.open_connection(ip).expect("below the limit"))
+            .collect();
+        assert!(limits.open_connection(ip).is_none());
+        assert!(limits
+            .open_connection(IpAddr::from([10, 0, 0, 1]))
+            .is_some());
+        drop(slots);
+        assert!(limits.open_connection(ip).is_some());
+    }
+
+    #[test]
+    fn clients_behind_trusted_proxies_are_told_apart() {
+        let proxy = IpAddr::from([127, 0, 0, 1]);
+        let limits = Limits::new(Vec::new())
+            .with_trusted_proxies(parse_proxies("127.0.0.1, ::1").expect("proxies are valid"));
+        assert!(parse_proxies("localhost").is_err());
+
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            pairs
+                .iter()
+                .map(|&(name, value)| {
+                    (
+                        header::HeaderName::from_static(name),
+                        HeaderValue::from_static(value),
+                    )
+                })
+                .collect::<HeaderMap>()
+        };
+        let forwarded = headers(&[
+            ("x-forwarded-for", "6.6.6.6, 203.0.113.7, ::1"),
+            ("x-real-ip", "198.51.100.2"),
+        ]);
+        assert_eq!(
+            limits.client_ip(proxy, &forwarded),
+            IpAddr::from([203, 0, 113, 7])
+        );
+        assert_eq!(
+            limits.client_ip(proxy, &headers(&[("x-real-ip", "198.51.100.2")])),
+            IpAddr::from([198, 51, 100, 2])
+        );
+        assert_eq!(limits.client_ip(proxy, &HeaderMap::new()), proxy);
+
+        // Anyone else could make up the headers
+        let direct = IpAddr::from([192, 0, 2, 1]);
+        assert_eq!(limits.client_ip(direct, &forwarded), direct);
+
+        // Two clients behind the proxy get their own buckets
+        let ips = ["203.0.113.7", "203.0.113.8"]
+            .map(|ip| limits.client_ip(proxy, &headers(&[("x-real-ip", ip)])));
+        for _ in 0..CONNECTS_PER_IP.burst {
+            assert!(limits.connects.take(ips[0], 1, 0));
+        }
+        assert!(!limits.connects.take(ips[0], 1, 0));
+        assert!(limits.connects.take(ips[1], 1, 0));
+    }
+
+    #[test]
+    fn origins_are_checked() {
+        let origins = parse_origins("https://chess.example/, http://localhost:5173")
+            .expect("origins are valid");
+        assert!(parse_origins("chess.example").is_err());
+        let limits = Limits::new(origins);
+        let host = HeaderValue::from_static("play.chess.example");
+        assert!(limits.origin_allowed(&HeaderValue::from_static("https://chess.example"), None));
+        assert!(limits.origin_allowed(
+            &HeaderValue::from_static("https://play.chess.example"),
+            Some(&host)
+        ));
+        assert!(!limits.origin_allowed(
+            &HeaderValue::from_static("https://evil.example"),
+            Some(&host)
+        ));
+    }
+}
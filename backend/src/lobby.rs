@@ -0,0 +1,475 @@
+use std::{collections::BTreeMap, fmt};
+
+use chess::variant::{GameVariant, CHESS960_POSITIONS};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::Seat,
+    clock::{TimeControl, MAX_DAYS_PER_MOVE, MAX_INCREMENT_MS, MAX_INITIAL_MS},
+    storage::{NewGame, Timestamp},
+};
+
+pub type ChallengeId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorPreference {
+    White,
+    Black,
+    Random,
+}
+
+/**
+ * An open offer to play, waiting for someone to accept it
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Challenge {
+    pub id: ChallengeId,
+    pub player: Seat,
+    pub time_control: Option<TimeControl>,
+    pub days_per_move: Option<u32>,
+    pub variant: GameVariant,
+    pub color: ColorPreference,
+    pub created_at: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Seeker {
+    player: Seat,
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+    variant: GameVariant,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LobbyError {
+    ChallengeNotFound(ChallengeId),
+    OwnChallenge,
+    NotYourChallenge,
+    InvalidTimeControl,
+    InvalidVariant,
+    /**
+     * The server already runs as many games as it is configured for
+     */
+    ServerFull,
+}
+
+impl LobbyError {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ChallengeNotFound(_) => "challenge_not_found",
+            Self::OwnChallenge => "own_challenge",
+            Self::NotYourChallenge => "not_your_challenge",
+            Self::InvalidTimeControl => "invalid_time_control",
+            Self::InvalidVariant => "invalid_variant",
+            Self::ServerFull => "server_full",
+        }
+    }
+}
+
+impl fmt::Display for LobbyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChallengeNotFound(id) => write!(f, "challenge {id} not found"),
+            Self::OwnChallenge => write!(f, "you cannot accept your own challenge"),
+            Self::NotYourChallenge => {
+                write!(f, "only the player who posted a challenge can cancel it")
+            }
+            Self::InvalidTimeControl => write!(
+                f,
+                "give either a clock of up to {} minutes with up to {} seconds of increment, \
+                 or 1 to {MAX_DAYS_PER_MOVE} days per move, not both",
+                MAX_INITIAL_MS / 60_000,
+                MAX_INCREMENT_MS / 1_000
+            ),
+            Self::InvalidVariant => write!(
+                f,
+                "Chess960 positions are numbered 0 to {}",
+                CHESS960_POSITIONS - 1
+            ),
+            Self::ServerFull => write!(f, "the server is full, try again later"),
+        }
+    }
+}
+
+impl std::error::Error for LobbyError {}
+
+/**
+ * Open challenges and the matchmaking queue.
+ * Both hand out the settings of a new game once two players are paired.
+ */
+#[derive(Debug, Default)]
+pub struct Lobby {
+    next_id: ChallengeId,
+    challenges: BTreeMap<ChallengeId, Challenge>,
+    queue: Vec<Seeker>,
+    /**
+     * Clock given to games that ask for neither a clock nor days per move
+     */
+    default_time_control: Option<TimeControl>,
+}
+
+impl Lobby {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Gives games that ask for neither a clock nor days per move `default_time_control`
+     */
+    #[must_use]
+    pub const fn with_default_time_control(
+        mut self,
+        default_time_control: Option<TimeControl>,
+    ) -> Self {
+        self.default_time_control = default_time_control;
+        self
+    }
+
+    const fn time_control_or_default(
+        &self,
+        time_control: Option<TimeControl>,
+        days_per_move: Option<u32>,
+    ) -> Option<TimeControl> {
+        match (time_control, days_per_move) {
+            (None, None) => self.default_time_control,
+            _ => time_control,
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn post_challenge(
+        &mut self,
+        player: &Seat,
+        time_control: Option<TimeControl>,
+        days_per_move: Option<u32>,
+        variant: GameVariant,
+        color: ColorPreference,
+        created_at: Timestamp,
+    ) -> Result<ChallengeId, LobbyError> {
+        validate_time_control(time_control, days_per_move)?;
+        validate_variant(variant)?;
+        let time_control = self.time_control_or_default(time_control, days_per_move);
+        self.next_id += 1;
+        let id = self.next_id;
+        self.challenges.insert(
+            id,
+            Challenge {
+                id,
+                player: player.clone(),
+                time_control,
+                days_per_move,
+                variant,
+                color,
+                created_at,
+            },
+        );
+        Ok(id)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn cancel_challenge(&mut self, id: ChallengeId, player: &Seat) -> Result<(), LobbyError> {
+        let challenge = self
+            .challenges
+            .get(&id)
+            .ok_or(LobbyError::ChallengeNotFound(id))?;
+        if challenge.player.user_id != player.user_id {
+            return Err(LobbyError::NotYourChallenge);
+        }
+        self.challenges.remove(&id);
+        Ok(())
+    }
+
+    /**
+     * Pairs the poster of the challenge with `player`. The challenge stays open until
+     * `close_challenge`, so it is not lost when the game cannot be created.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn accept_challenge(&self, id: ChallengeId, player: &Seat) -> Result<NewGame, LobbyError> {
+        let challenge = self
+            .challenges
+            .get(&id)
+            .ok_or(LobbyError::ChallengeNotFound(id))?;
+        if challenge.player.user_id == player.user_id {
+            return Err(LobbyError::OwnChallenge);
+        }
+        let poster_is_white = match challenge.color {
+            ColorPreference::White => true,
+            ColorPreference::Black => false,
+            ColorPreference::Random => rand::random(),
+        };
+        Ok(pair(
+            challenge.player.clone(),
+            player.clone(),
+            poster_is_white,
+            challenge.time_control,
+            challenge.days_per_move,
+            challenge.variant,
+        ))
+    }
+
+    /**
+     * Takes an accepted challenge off the lobby once its game has started
+     */
+    pub fn close_challenge(&mut self, id: ChallengeId) {
+        self.challenges.remove(&id);
+    }
+
+    #[must_use]
+    pub fn challenges(&self) -> Vec<Challenge> {
+        self.challenges.values().cloned().collect()
+    }
+
+    #[must_use]
+    pub fn challenges_len(&self) -> usize {
+        self.challenges.len()
+    }
+
+    /**
+     * Pairs `player` with the longest waiting player who wants the same time control and variant,
+     * or puts them in the queue when nobody does
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn seek(
+        &mut self,
+        player: &Seat,
+        time_control: Option<TimeControl>,
+        days_per_move: Option<u32>,
+        variant: GameVariant,
+    ) -> Result<Option<NewGame>, LobbyError> {
+        validate_time_control(time_control, days_per_move)?;
+        validate_variant(variant)?;
+        let time_control = self.time_control_or_default(time_control, days_per_move);
+        self.leave_queue(player);
+        let opponent = self
+            .queue
+            .iter()
+            .position(|seeker| {
+                seeker.time_control == time_control
+                    && seeker.days_per_move == days_per_move
+                    && seeker.variant == variant
+            })
+            .map(|index| self.queue.remove(index));
+        if let Some(opponent) = opponent {
+            return Ok(Some(pair(
+                opponent.player,
+                player.clone(),
+                rand::random(),
+                time_control,
+                days_per_move,
+                variant,
+            )));
+        }
+        self.queue.push(Seeker {
+            player: player.clone(),
+            time_control,
+            days_per_move,
+            variant,
+        });
+        Ok(None)
+    }
+
+    /**
+     * Returns false if the player was not waiting
+     */
+    pub fn leave_queue(&mut self, player: &Seat) -> bool {
+        let waiting = self.queue.len();
+        self.queue
+            .retain(|seeker| seeker.player.user_id != player.user_id);
+        self.queue.len() != waiting
+    }
+
+    #[must_use]
+    pub const fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/**
+ * A game has either a clock within bounds or a correspondence deadline, or neither
+ */
+const fn validate_time_control(
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+) -> Result<(), LobbyError> {
+    match (time_control, days_per_move) {
+        (Some(_), Some(_)) => Err(LobbyError::InvalidTimeControl),
+        (Some(time_control), _) if !time_control.is_valid() => Err(LobbyError::InvalidTimeControl),
+        (_, Some(days)) if days == 0 || days > MAX_DAYS_PER_MOVE => {
+            Err(LobbyError::InvalidTimeControl)
+        }
+        _ => Ok(()),
+    }
+}
+
+const fn validate_variant(variant: GameVariant) -> Result<(), LobbyError> {
+    if variant.is_valid() {
+        Ok(())
+    } else {
+        Err(LobbyError::InvalidVariant)
+    }
+}
+
+fn pair(
+    first: Seat,
+    second: Seat,
+    first_is_white: bool,
+    time_control: Option<TimeControl>,
+    days_per_move: Option<u32>,
+    variant: GameVariant,
+) -> NewGame {
+    let (white, black) = if first_is_white {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    NewGame {
+        white: Some(white),
+        black: Some(black),
+        time_control,
+        days_per_move,
+        variant,
+        start_fen: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seat(user_id: u64, username: &str) -> Seat {
+        Seat {
+            user_id,
+            username: username.to_string(),
+        }
+    }
+
+    const BLITZ: Option<TimeControl> = Some(TimeControl {
+        initial_ms: 300_000,
+        increment_ms: 3_000,
+    });
+    const STANDARD: GameVariant = GameVariant::Standard;
+    const CHESS960: GameVariant = GameVariant::Chess960(42);
+
+    #[test]
+    fn challenges_work() {
+        let (alice, bob, carol) = (seat(1, "alice"), seat(2, "bob"), seat(3, "carol"));
+        let mut lobby = Lobby::new();
+        let id = lobby
+            .post_challenge(&alice, BLITZ, None, CHESS960, ColorPreference::Black, 0)
+            .expect("time control is valid");
+        assert_eq!(lobby.challenges().len(), 1);
+        assert_eq!(
+            lobby.accept_challenge(id, &alice),
+            Err(LobbyError::OwnChallenge)
+        );
+        assert_eq!(
+            lobby.cancel_challenge(id, &bob),
+            Err(LobbyError::NotYourChallenge)
+        );
+
+        let game = lobby.accept_challenge(id, &bob).expect("challenge is open");
+        assert_eq!(game.white, Some(bob));
+        assert_eq!(game.black, Some(alice));
+        assert_eq!(game.time_control, BLITZ);
+        assert_eq!(game.variant, CHESS960);
+        assert_eq!(lobby.challenges().len(), 1);
+        lobby.close_challenge(id);
+        assert!(lobby.challenges().is_empty());
+        assert_eq!(
+            lobby.accept_challenge(id, &carol),
+            Err(LobbyError::ChallengeNotFound(id))
+        );
+    }
+
+    #[test]
+    fn queue_pairs_same_time_control() {
+        let (alice, bob, carol) = (seat(1, "alice"), seat(2, "bob"), seat(3, "carol"));
+        let mut lobby = Lobby::new();
+        assert_eq!(lobby.seek(&alice, BLITZ, None, STANDARD), Ok(None));
+        assert_eq!(lobby.seek(&bob, None, Some(3), STANDARD), Ok(None));
+        assert_eq!(lobby.seek(&alice, BLITZ, None, STANDARD), Ok(None));
+        assert_eq!(lobby.queue_len(), 2);
+        assert_eq!(
+            lobby.seek(&carol, BLITZ, Some(3), STANDARD),
+            Err(LobbyError::InvalidTimeControl)
+        );
+        assert_eq!(
+            lobby.seek(&carol, None, Some(0), STANDARD),
+            Err(LobbyError::InvalidTimeControl)
+        );
+        for time_control in [
+            TimeControl {
+                initial_ms: 0,
+                increment_ms: 0,
+            },
+            TimeControl {
+                initial_ms: 60_000,
+                increment_ms: u64::MAX,
+            },
+        ] {
+            assert_eq!(
+                lobby.seek(&carol, Some(time_control), None, STANDARD),
+                Err(LobbyError::InvalidTimeControl)
+            );
+        }
+
+        let game = lobby
+            .seek(&carol, BLITZ, None, STANDARD)
+            .expect("time control is valid")
+            .expect("alice is waiting");
+        let mut players = [
+            game.white.expect("white").username,
+            game.black.expect("black").username,
+        ];
+        players.sort();
+        assert_eq!(players, ["alice", "carol"]);
+        assert_eq!(lobby.queue_len(), 1);
+
+        let game = lobby
+            .seek(&alice, None, Some(3), STANDARD)
+            .expect("time control is valid")
+            .expect("bob is waiting");
+        assert_eq!(game.days_per_move, Some(3));
+        assert_eq!(lobby.queue_len(), 0);
+
+        assert!(lobby
+            .seek(&bob, None, None, STANDARD)
+            .expect("untimed")
+            .is_none());
+        assert_eq!(lobby.seek(&carol, None, None, CHESS960), Ok(None));
+        assert_eq!(lobby.queue_len(), 2);
+        assert_eq!(
+            lobby.seek(&alice, None, None, GameVariant::Chess960(960)),
+            Err(LobbyError::InvalidVariant)
+        );
+        let game = lobby
+            .seek(&alice, None, None, CHESS960)
+            .expect("variant is valid")
+            .expect("carol is waiting");
+        assert_eq!(game.variant, CHESS960);
+        assert!(lobby.leave_queue(&bob));
+        assert!(!lobby.leave_queue(&bob));
+    }
+
+    #[test]
+    fn default_time_control_is_applied() {
+        let (alice, bob) = (seat(1, "alice"), seat(2, "bob"));
+        let mut lobby = Lobby::new().with_default_time_control(BLITZ);
+        let id = lobby
+            .post_challenge(&alice, None, None, STANDARD, ColorPreference::Random, 0)
+            .expect("time control is valid");
+        assert_eq!(lobby.challenges()[0].time_control, BLITZ);
+        lobby.cancel_challenge(id, &alice).expect("alice posted it");
+
+        assert_eq!(lobby.seek(&alice, None, Some(3), STANDARD), Ok(None));
+        let game = lobby
+            .seek(&bob, None, Some(3), STANDARD)
+            .expect("time control is valid")
+            .expect("alice is waiting");
+        assert_eq!(game.time_control, None);
+        assert_eq!(game.days_per_move, Some(3));
+    }
+}
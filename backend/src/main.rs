@@ -1,215 +1,239 @@
 #![allow(clippy::redundant_pub_crate)]
+mod accounts;
+mod api;
+mod chat;
+mod clock;
+mod config;
+mod games;
+mod health;
+mod limits;
+mod lobby;
+mod metrics;
+mod protocol;
+mod ratings;
+mod storage;
+mod websocket;
+mod workers;
+
+use accounts::Sessions;
 use axum::{
-    extract::{
-        ws::{Message, WebSocket},
-        WebSocketUpgrade,
-    },
-    response::IntoResponse,
+    http::{header, Method},
+    middleware,
     routing::get,
     Extension, Router,
 };
-use chess::{
-    chessboard::{file::File, rank::Rank},
-    Chess,
+use clap::Parser;
+use config::{Args, Config};
+use games::{Game, GameRegistry};
+use limits::Limits;
+use lobby::Lobby;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use storage::{now, sqlite::SqliteStore, GameStore, NewGame, Timestamp};
+use tokio::sync::{broadcast, watch, Mutex};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
 };
-use futures::{SinkExt, StreamExt};
-use serde::Deserialize;
-use std::sync::Arc;
-use tokio::sync::mpsc;
-use tokio::sync::{watch, Mutex};
-use tower_http::cors::{Any, CorsLayer};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use workers::WorkerPool;
+
+/**
+ * Number of lobby messages a websocket client can fall behind before it misses some
+ */
+const LOBBY_UPDATES_CAPACITY: usize = 64;
+/**
+ * How often games are checked for a side that ran out of time
+ */
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
 
 struct State {
+    started_at: Timestamp,
     clients_count: usize,
-    rx: watch::Receiver<Message>,
-    chess: Arc<Mutex<Chess>>,
+    games: GameRegistry,
+    /**
+     * The shared board every client joins by default
+     */
+    game: Arc<Mutex<Game>>,
+    lobby: Lobby,
+    lobby_updates: broadcast::Sender<String>,
+    /**
+     * Set to true once the server starts shutting down, which closes every websocket
+     */
+    shutdown: watch::Sender<bool>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-struct MoveRequest {
-    #[allow(clippy::type_complexity)]
-    list_of_moves: Vec<((String, usize), (String, usize), (usize, usize))>,
-    new_move: (String, String, (usize, usize)),
-}
-
-#[derive(Debug, Deserialize, Clone)]
-struct ResetRequest {
-    action: String,
+fn main() {
+    let config = match Config::load(Args::parse()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Invalid configuration: {err}");
+            std::process::exit(1);
+        }
+    };
+    // One JSON object per line, with the connection and game spans of each event
+    tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_env_filter(EnvFilter::new(config.log_level.as_str()))
+        .init();
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start the runtime")
+        .block_on(serve(config));
 }
 
-#[tokio::main]
-async fn main() {
-    let (_tx, rx) = watch::channel(Message::Text("{}".to_string()));
-    let chess = Arc::new(Mutex::new(Chess::new_starting_position()));
+async fn serve(config: Config) {
+    let store: Arc<dyn GameStore> =
+        Arc::new(SqliteStore::open(&config.database_path).expect("Failed to open database"));
+    let mut games = GameRegistry::restore(store)
+        .expect("Failed to restore games")
+        .with_max_games(config.max_games);
+    info!(
+        games = games.len(),
+        database = %config.database_path.display(),
+        "restored games"
+    );
+
+    let game = games.shared().unwrap_or_else(|| {
+        games
+            .create_game(&NewGame::default())
+            .expect("Failed to create game")
+    });
     let state = Arc::new(Mutex::new(State {
+        started_at: now(),
         clients_count: 0,
-        rx,
-        chess,
+        games,
+        game,
+        lobby: Lobby::new().with_default_time_control(config.default_time_control),
+        lobby_updates: broadcast::channel(LOBBY_UPDATES_CAPACITY).0,
+        shutdown: watch::channel(false).0,
     }));
 
+    tokio::spawn(expire_games(state.clone()));
+
+    let sessions = Arc::new(config.session_secret.as_ref().map_or_else(
+        || {
+            warn!("no session secret is set, sessions will not survive a restart");
+            Sessions::with_random_secret()
+        },
+        |secret| Sessions::new(secret.clone().into_bytes()),
+    ));
+
+    let allowed_origins = config.allowed_origins();
     let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_origin(AllowOrigin::list(allowed_origins.clone()))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
+    let limits =
+        Arc::new(Limits::new(allowed_origins).with_trusted_proxies(config.trusted_proxies()));
+    let workers = Arc::new(WorkerPool::new(config.engine.workers));
 
     let router = Router::new()
-        .route("/websocket", get(websocket_handler))
-        .route("/status", get(get_status))
+        .route("/websocket", get(websocket::websocket_handler))
+        .route("/healthz", get(health::get_healthz))
+        .route("/readyz", get(health::get_readyz))
+        .route("/status", get(health::get_status))
+        .route("/metrics", get(metrics::get_metrics))
+        .merge(api::routes())
+        .layer(middleware::from_fn(limits::limit_requests))
+        .layer(TraceLayer::new_for_http())
         .layer(cors)
-        .layer(Extension(state));
+        .layer(Extension(state.clone()))
+        .layer(Extension(sessions))
+        .layer(Extension(limits))
+        .layer(Extension(workers));
 
-    // Get port from environment variable or use default 8000
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8000".to_string())
-        .parse::<u16>()
-        .unwrap_or(8000);
-
-    let addr = format!("0.0.0.0:{port}");
-    println!("Server listening on {addr}");
-
-    let listener = tokio::net::TcpListener::bind(&addr)
+    info!(address = %config.bind, "server listening");
+    let listener = tokio::net::TcpListener::bind(config.bind)
         .await
         .expect("Failed to bind to address");
 
-    axum::serve(listener, router)
-        .await
-        .expect("Failed to start server");
-}
+    let shutdown = state.lock().await.shutdown.clone();
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(shutdown))
+    .await
+    .expect("Failed to start server");
 
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    Extension(state): Extension<Arc<Mutex<State>>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| websocket(socket, state))
+    checkpoint_games(&state).await;
 }
 
-#[allow(clippy::too_many_lines)]
-async fn websocket(stream: WebSocket, state: Arc<Mutex<State>>) {
-    let (mut sender, mut receiver) = stream.split();
-    let mut rx = {
-        let mut state = state.lock().await;
-        state.clients_count += 1;
-        state.rx.clone()
+/**
+ * Waits for SIGINT or SIGTERM, then tells every websocket to close.
+ * The server stops accepting connections once this returns.
+ */
+async fn shutdown_signal(shutdown: watch::Sender<bool>) {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
     };
-
-    let chess = state.lock().await.chess.clone();
-    // Create a channel for communication between tasks
-    let (tx, mut rx_channel) = mpsc::channel(100);
-
-    let initial_chess_state = {
-        let chess_game = chess.lock().await;
-        serde_json::json!({
-            "type": "initial_state",
-            "chess": *chess_game
-        })
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
     };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    if (sender
-        .send(Message::Text(
-            serde_json::to_string(&initial_chess_state).expect("Failed initializing chess state"),
-        ))
-        .await)
-        .is_err()
-    {
-        return; // Client disconnected
+    tokio::select! {
+        () = interrupt => {},
+        () = terminate => {},
     }
+    info!("shutting down");
+    shutdown.send_replace(true);
+}
 
-    let mut send_task = tokio::spawn(async move {
-        loop {
-            tokio::select! {
-                _ = rx.changed() => {
-                    let msg = rx.borrow().clone();
-                    if sender.send(msg).await.is_err() {
-                        break;
-                    }
-                }
-                Some(msg) = rx_channel.recv() => {
-                    if sender.send(msg).await.is_err() {
-                        break;
-                    }
-                }
-            }
+/**
+ * Saves the clocks and pending offers of every game, which are restored on the next start
+ */
+async fn checkpoint_games(state: &Arc<Mutex<State>>) {
+    let games = state.lock().await.games.games();
+    let saved_at = now();
+    let mut saved = 0;
+    for game in games {
+        let game = game.lock().await;
+        let checkpointed = game.checkpoint(saved_at);
+        let id = game.id;
+        drop(game);
+        match checkpointed {
+            Ok(()) => saved += 1,
+            Err(err) => error!(game_id = id, %err, "failed to checkpoint game"),
         }
-    });
+    }
+    info!(games = saved, "checkpointed games");
+}
 
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Text(text))) = receiver.next().await {
-            if let Ok(move_request) = serde_json::from_str::<MoveRequest>(&text) {
-                let mut chess_game = chess.lock().await;
-                // Apply previous moves
-                for move_tuple in &move_request.list_of_moves {
-                    let start_sq = chess_game.get_square(
-                        File::try_from(move_tuple.0 .0.as_str()).expect("invalid file"),
-                        Rank::try_from(move_tuple.0 .1).expect("invalid rank"),
-                    );
-                    let end_sq = chess_game.get_square(
-                        File::try_from(move_tuple.1 .0.as_str()).expect("invalid file"),
-                        Rank::try_from(move_tuple.1 .1).expect("invalid rank"),
-                    );
-                    #[allow(clippy::cast_possible_truncation)]
-                    #[allow(clippy::cast_possible_wrap)]
-                    let promoted_piece = (move_tuple.2 .0 as i32, move_tuple.2 .1 as i32);
-                    chess_game.make_move(&start_sq, &end_sq, promoted_piece);
-                }
-
-                let promoted_piece = match move_request.new_move.2 {
-                    (1, 0 | 1) => Some("QUEEN"),
-                    (2, 0 | 1) => Some("ROOK"),
-                    (3, 0 | 1) => Some("KNIGHT"),
-                    (4, 0 | 1) => Some("BISHOP"),
-                    _ => None,
-                };
-
-                // Make the new move
-                chess_game.make_move_from_str(
-                    &move_request.new_move.0,
-                    &move_request.new_move.1,
-                    promoted_piece,
-                );
-
-                // Send updated chess state to all clients
-                let response = serde_json::json!({
-                    "type": "update",
-                    "chess": *chess_game
-                });
-                #[allow(clippy::unwrap_used)]
-                let response_json = serde_json::to_string(&response).unwrap();
-                if tx.send(Message::Text(response_json)).await.is_err() {
-                    break;
-                }
-                // Explicitly drop chess_game here
-                drop(chess_game);
-            } else if let Ok(reset_request) = serde_json::from_str::<ResetRequest>(&text) {
-                if reset_request.action == "reset" {
-                    let mut chess_game = chess.lock().await;
-                    *chess_game = Chess::new_starting_position();
-
-                    // Send updated chess state to all clients
-                    let response = serde_json::json!({
-                        "type": "reset",
-                        "chess": *chess_game
-                    });
-                    #[allow(clippy::unwrap_used)]
-                    let response_json = serde_json::to_string(&response).unwrap();
-                    if tx.send(Message::Text(response_json)).await.is_err() {
-                        break;
-                    }
-
-                    // Explicitly drop chess_game here
-                    drop(chess_game);
-                }
+/**
+ * Ends games whose side to move ran out of time without trying to move again,
+ * such as correspondence players who stopped answering. Finished games nobody
+ * watches are dropped first, so only games that may still expire are locked.
+ */
+async fn expire_games(state: Arc<Mutex<State>>) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let games = {
+            let mut state = state.lock().await;
+            let evicted = state.games.evict_finished();
+            if evicted > 0 {
+                info!(games = evicted, "evicted finished games");
+            }
+            state.games.games()
+        };
+        for game in games {
+            let expired = game.lock().await.expire(now());
+            if let Err(err) = expired {
+                let game_id = game.lock().await.id;
+                error!(game_id, %err, "failed to end game on time");
             }
         }
-    });
-
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
-    };
-
-    state.lock().await.clients_count -= 1;
-}
-
-async fn get_status() -> impl IntoResponse {
-    Chess::new_starting_position().to_json()
+    }
 }
@@ -0,0 +1,222 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{http::header, response::IntoResponse, Extension};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::State;
+
+/**
+ * Upper bounds of the move validation latency buckets, in microseconds
+ */
+const VALIDATION_BUCKETS_US: [u64; 10] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000,
+];
+
+/**
+ * Counters every part of the server adds to, rendered in the Prometheus text format
+ */
+pub static METRICS: Metrics = Metrics::new();
+
+#[derive(Debug)]
+pub struct Metrics {
+    moves: AtomicU64,
+    rejected_moves: Mutex<BTreeMap<&'static str, u64>>,
+    validation_buckets: [AtomicU64; VALIDATION_BUCKETS_US.len()],
+    validation_count: AtomicU64,
+    validation_sum_us: AtomicU64,
+    websocket_send_failures: AtomicU64,
+}
+
+impl Metrics {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            moves: AtomicU64::new(0),
+            rejected_moves: Mutex::new(BTreeMap::new()),
+            validation_buckets: [const { AtomicU64::new(0) }; VALIDATION_BUCKETS_US.len()],
+            validation_count: AtomicU64::new(0),
+            validation_sum_us: AtomicU64::new(0),
+            websocket_send_failures: AtomicU64::new(0),
+        }
+    }
+
+    pub fn move_played(&self) {
+        self.moves.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn move_rejected(&self, reason: &'static str) {
+        *self
+            .rejected_moves
+            .lock()
+            .expect("metrics lock is poisoned")
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    /**
+     * Records how long checking and making one move took
+     */
+    pub fn move_validated(&self, took: Duration) {
+        let micros = u64::try_from(took.as_micros()).unwrap_or(u64::MAX);
+        for (bucket, &bound) in self.validation_buckets.iter().zip(&VALIDATION_BUCKETS_US) {
+            if micros <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.validation_count.fetch_add(1, Ordering::Relaxed);
+        self.validation_sum_us.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn websocket_send_failed(&self) {
+        self.websocket_send_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /**
+     * The Prometheus text exposition of every metric, with the gauges read from the live state
+     */
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn render(&self, connected_clients: usize, active_games: usize) -> String {
+        let mut out = String::new();
+        gauge(
+            &mut out,
+            "chess_connected_clients",
+            "Open websocket connections",
+            connected_clients,
+        );
+        gauge(
+            &mut out,
+            "chess_active_games",
+            "Games in progress",
+            active_games,
+        );
+        counter_header(
+            &mut out,
+            "chess_moves_total",
+            "Moves played, rate() of it gives moves per second",
+        );
+        let _ = writeln!(
+            out,
+            "chess_moves_total {}",
+            self.moves.load(Ordering::Relaxed)
+        );
+
+        counter_header(
+            &mut out,
+            "chess_rejected_moves_total",
+            "Moves refused, by reason",
+        );
+        let rejected = self
+            .rejected_moves
+            .lock()
+            .expect("metrics lock is poisoned")
+            .clone();
+        for (reason, count) in rejected {
+            let _ = writeln!(
+                out,
+                "chess_rejected_moves_total{{reason=\"{reason}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP chess_move_validation_seconds Time taken to check and make a move\n\
+             # TYPE chess_move_validation_seconds histogram"
+        );
+        for (bucket, bound) in self.validation_buckets.iter().zip(VALIDATION_BUCKETS_US) {
+            let _ = writeln!(
+                out,
+                "chess_move_validation_seconds_bucket{{le=\"{}\"}} {}",
+                bound as f64 / 1_000_000.0,
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.validation_count.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "chess_move_validation_seconds_bucket{{le=\"+Inf\"}} {count}\n\
+             chess_move_validation_seconds_sum {}\n\
+             chess_move_validation_seconds_count {count}",
+            self.validation_sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+
+        counter_header(
+            &mut out,
+            "chess_websocket_send_failures_total",
+            "Websocket frames that could not be sent",
+        );
+        let _ = writeln!(
+            out,
+            "chess_websocket_send_failures_total {}",
+            self.websocket_send_failures.load(Ordering::Relaxed)
+        );
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+    );
+}
+
+fn counter_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+}
+
+pub async fn get_metrics(Extension(state): Extension<Arc<AsyncMutex<State>>>) -> impl IntoResponse {
+    let state = state.lock().await;
+    let connected_clients = state.clients_count;
+    let active_games = state.games.in_progress();
+    drop(state);
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.render(connected_clients, active_games),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_are_rendered() {
+        let metrics = Metrics::new();
+        metrics.move_played();
+        metrics.move_played();
+        metrics.move_rejected("illegal_move");
+        metrics.move_rejected("not_your_seat");
+        metrics.move_rejected("illegal_move");
+        metrics.move_validated(Duration::from_micros(300));
+        metrics.move_validated(Duration::from_secs(1));
+        metrics.websocket_send_failed();
+
+        let text = metrics.render(3, 1);
+        for line in [
+            "chess_connected_clients 3",
+            "chess_active_games 1",
+            "chess_moves_total 2",
+            "chess_rejected_moves_total{reason=\"illegal_move\"} 2",
+            "chess_rejected_moves_total{reason=\"not_your_seat\"} 1",
+            "chess_move_validation_seconds_bucket{le=\"0.00025\"} 0",
+            "chess_move_validation_seconds_bucket{le=\"0.0005\"} 1",
+            "chess_move_validation_seconds_bucket{le=\"0.1\"} 1",
+            "chess_move_validation_seconds_bucket{le=\"+Inf\"} 2",
+            "chess_move_validation_seconds_sum 1.0003",
+            "chess_move_validation_seconds_count 2",
+            "chess_websocket_send_failures_total 1",
+            "# TYPE chess_move_validation_seconds histogram",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line} in\n{text}");
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use chess::{
+    game_state::GameState,
+    piece::{Piece, PieceColor},
+    variant::GameVariant,
+    Chess,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::Seat,
+    chat::{ChatChannel, ChatMessage},
+    clock::{Clock, TimeControl},
+    games::TakebackScope,
+    lobby::Challenge,
+    storage::{GameId, Timestamp},
+};
+
+/**
+ * Bumped whenever a message changes shape, clients sending another version are refused
+ */
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/**
+ * Every frame sent by a client, for example {"version": 1, "type": "move", "uci": "e2e4"}
+ */
+#[derive(Debug, Deserialize)]
+pub struct ClientFrame {
+    pub version: u32,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /**
+     * Starts watching a game, the shared board when no id is given
+     */
+    Join {
+        game_id: Option<GameId>,
+    },
+    Move {
+        uci: String,
+    },
+    Resign {
+        color: PieceColor,
+    },
+    OfferDraw {
+        color: PieceColor,
+    },
+    RequestTakeback {
+        color: PieceColor,
+        #[serde(default)]
+        scope: TakebackScope,
+    },
+    AnswerTakeback {
+        color: PieceColor,
+        accept: bool,
+    },
+    Chat {
+        text: String,
+        #[serde(default)]
+        channel: ChatChannel,
+    },
+    Ping,
+    /**
+     * Puts the watched game back to the starting position
+     */
+    Reset,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /**
+     * Full snapshot of a game, sent when a client joins or has missed updates
+     */
+    State {
+        game_id: GameId,
+        chess: Box<Chess>,
+        clock: Option<Clock>,
+        /**
+         * When the side to move of a correspondence game loses on time
+         */
+        deadline: Option<Timestamp>,
+    },
+    /**
+     * Just the move and the position it led to
+     */
+    MoveMade {
+        game_id: GameId,
+        uci: String,
+        san: String,
+        fen: String,
+        state: GameState,
+        clock: Option<Clock>,
+        deadline: Option<Timestamp>,
+        captured: Option<Piece>,
+    },
+    DrawOffered {
+        game_id: GameId,
+        color: PieceColor,
+    },
+    TakebackRequested {
+        game_id: GameId,
+        color: PieceColor,
+        plies: usize,
+    },
+    TakebackDeclined {
+        game_id: GameId,
+        color: PieceColor,
+    },
+    /**
+     * The last `plies` moves were undone, leaving the position in `fen`
+     */
+    TakenBack {
+        game_id: GameId,
+        plies: usize,
+        fen: String,
+        clock: Option<Clock>,
+        deadline: Option<Timestamp>,
+    },
+    GameOver {
+        game_id: GameId,
+        result: GameState,
+    },
+    Chat {
+        game_id: GameId,
+        #[serde(flatten)]
+        message: ChatMessage,
+    },
+    /**
+     * Open challenges, sent to everyone whenever the list changes
+     */
+    Challenges {
+        challenges: Vec<Challenge>,
+    },
+    /**
+     * Two players were paired from the lobby or the matchmaking queue
+     */
+    GameStarted {
+        game_id: GameId,
+        white: Seat,
+        black: Seat,
+        time_control: Option<TimeControl>,
+        days_per_move: Option<u32>,
+        variant: GameVariant,
+    },
+    Pong,
+    /**
+     * The server is shutting down and closes the connection, clients should reconnect shortly
+     */
+    ServerRestarting,
+    Error {
+        code: &'static str,
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct ServerFrame<'a> {
+    version: u32,
+    #[serde(flatten)]
+    message: &'a ServerMessage,
+}
+
+impl ServerMessage {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&ServerFrame {
+            version: PROTOCOL_VERSION,
+            message: self,
+        })
+        .expect("Server messages are always serializable")
+    }
+}
+
+/**
+ * Parses a client frame, or gives the error frame to answer it with
+ */
+#[allow(clippy::missing_errors_doc, clippy::result_large_err)]
+pub fn parse_client_message(text: &str) -> Result<ClientMessage, ServerMessage> {
+    let frame: ClientFrame = serde_json::from_str(text)
+        .map_err(|err| ServerMessage::error("invalid_message", err.to_string()))?;
+    if frame.version != PROTOCOL_VERSION {
+        return Err(ServerMessage::error(
+            "unsupported_version",
+            format!(
+                "protocol version {} is not supported, expected {PROTOCOL_VERSION}",
+                frame.version
+            ),
+        ));
+    }
+    Ok(frame.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_messages_parse() {
+        assert_eq!(
+            parse_client_message(r#"{"version": 1, "type": "move", "uci": "e2e4"}"#)
+                .expect("valid message"),
+            ClientMessage::Move {
+                uci: "e2e4".to_string()
+            }
+        );
+        assert_eq!(
+            parse_client_message(r#"{"version": 1, "type": "join"}"#).expect("valid message"),
+            ClientMessage::Join { game_id: None }
+        );
+        assert_eq!(
+            parse_client_message(r#"{"version": 1, "type": "resign", "color": "White"}"#)
+                .expect("valid message"),
+            ClientMessage::Resign {
+                color: PieceColor::White
+            }
+        );
+        assert_eq!(
+            parse_client_message(r#"{"version": 1, "type": "ping"}"#).expect("valid message"),
+            ClientMessage::Ping
+        );
+        assert_eq!(
+            parse_client_message(r#"{"version": 1, "type": "request_takeback", "color": "Black"}"#)
+                .expect("valid message"),
+            ClientMessage::RequestTakeback {
+                color: PieceColor::Black,
+                scope: TakebackScope::LastMove
+            }
+        );
+    }
+
+    #[test]
+    fn bad_client_messages_are_refused() {
+        let code = |text| match parse_client_message(text) {
+            Err(ServerMessage::Error { code, .. }) => code,
+            other => panic!("expected an error frame, got {other:?}"),
+        };
+        assert_eq!(code("not json"), "invalid_message");
+        assert_eq!(code(r#"{"version": 1, "type": "fly"}"#), "invalid_message");
+        assert_eq!(code(r#"{"version": 1, "type": "move"}"#), "invalid_message");
+        assert_eq!(code(r#"{"type": "ping"}"#), "invalid_message");
+        assert_eq!(
+            code(r#"{"version": 2, "type": "ping"}"#),
+            "unsupported_version"
+        );
+    }
+
+    #[test]
+    fn server_messages_carry_version_and_type() {
+        let json: serde_json::Value = serde_json::from_str(
+            &ServerMessage::GameOver {
+                game_id: 3,
+                result: GameState::Draw,
+            }
+            .to_json(),
+        )
+        .expect("valid json");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": PROTOCOL_VERSION,
+                "type": "game_over",
+                "game_id": 3,
+                "result": "Draw"
+            })
+        );
+    }
+}
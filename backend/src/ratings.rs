@@ -0,0 +1,243 @@
+use std::f64::consts::PI;
+
+use chess::game_state::GameState;
+use serde::{Deserialize, Serialize};
+
+/**
+ * Converts between the Glicko rating scale and the internal Glicko-2 scale
+ */
+const GLICKO2_SCALE: f64 = 173.7178;
+const DEFAULT_RATING: f64 = 1500.0;
+const DEFAULT_DEVIATION: f64 = 350.0;
+const DEFAULT_VOLATILITY: f64 = 0.06;
+/**
+ * How much the volatility may change, smaller values keep it steadier
+ */
+const TAU: f64 = 0.5;
+const CONVERGENCE_TOLERANCE: f64 = 0.000_001;
+const ELO_K_FACTOR: f64 = 32.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Rating {
+    /**
+     * Glicko-2 rating on the familiar Glicko scale, where new players start at 1500
+     */
+    pub glicko: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+    pub elo: f64,
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            glicko: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+            elo: DEFAULT_RATING,
+            games: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerRating {
+    pub player: String,
+    #[serde(flatten)]
+    pub rating: Rating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RatingSystem {
+    #[default]
+    Glicko2,
+    Elo,
+}
+
+/**
+ * Points white scored in a finished game, None while it is still going
+ */
+#[must_use]
+pub const fn white_score(state: GameState) -> Option<f64> {
+    match state {
+        GameState::WhiteVictory | GameState::WhiteKingOfTheHill | GameState::WhiteThreeCheck => {
+            Some(1.0)
+        }
+        GameState::BlackVictory | GameState::BlackKingOfTheHill | GameState::BlackThreeCheck => {
+            Some(0.0)
+        }
+        GameState::InsufficientMaterial | GameState::Stalemate | GameState::Draw => Some(0.5),
+        GameState::InProgress => None,
+    }
+}
+
+/**
+ * New ratings of both players after a game between them
+ */
+#[must_use]
+pub fn rate_game(white: Rating, black: Rating, white_score: f64) -> (Rating, Rating) {
+    let mut new_white = glicko2(white, &[(black, white_score)]);
+    let mut new_black = glicko2(black, &[(white, 1.0 - white_score)]);
+    new_white.elo = elo(white.elo, black.elo, white_score);
+    new_black.elo = elo(black.elo, white.elo, 1.0 - white_score);
+    (new_white, new_black)
+}
+
+#[must_use]
+pub fn elo(rating: f64, opponent: f64, score: f64) -> f64 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0));
+    ELO_K_FACTOR.mul_add(score - expected, rating)
+}
+
+/**
+ * Glicko-2 update for one rating period, `results` holds each opponent and the score against them.
+ * See <http://www.glicko.net/glicko/glicko2.pdf>
+ */
+#[must_use]
+pub fn glicko2(player: Rating, results: &[(Rating, f64)]) -> Rating {
+    let mu = (player.glicko - DEFAULT_RATING) / GLICKO2_SCALE;
+    let phi = player.deviation / GLICKO2_SCALE;
+    #[allow(clippy::cast_possible_truncation)]
+    let games = player.games + results.len() as u32;
+
+    if results.is_empty() {
+        let deviation = phi.hypot(player.volatility) * GLICKO2_SCALE;
+        return Rating {
+            deviation: deviation.min(DEFAULT_DEVIATION),
+            ..player
+        };
+    }
+
+    let outcomes: Vec<(f64, f64, f64)> = results
+        .iter()
+        .map(|(opponent, score)| {
+            let opponent_mu = (opponent.glicko - DEFAULT_RATING) / GLICKO2_SCALE;
+            let g = g(opponent.deviation / GLICKO2_SCALE);
+            let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+            (g, expected, *score)
+        })
+        .collect();
+    let variance = 1.0
+        / outcomes
+            .iter()
+            .map(|(g, expected, _)| g * g * expected * (1.0 - expected))
+            .sum::<f64>();
+    let improvement: f64 = outcomes
+        .iter()
+        .map(|(g, expected, score)| g * (score - expected))
+        .sum();
+    let delta = variance * improvement;
+
+    let volatility = new_volatility(phi, player.volatility, variance, delta);
+    let pre_period_phi = phi.hypot(volatility);
+    let new_phi = 1.0 / (1.0 / (pre_period_phi * pre_period_phi) + 1.0 / variance).sqrt();
+    let new_mu = (new_phi * new_phi).mul_add(improvement, mu);
+
+    Rating {
+        glicko: GLICKO2_SCALE.mul_add(new_mu, DEFAULT_RATING),
+        deviation: GLICKO2_SCALE * new_phi,
+        volatility,
+        elo: player.elo,
+        games,
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (3.0 * phi * phi / (PI * PI) + 1.0).sqrt()
+}
+
+/**
+ * Finds the new volatility with the Illinois algorithm from step 5 of the paper
+ */
+#[allow(
+    clippy::suboptimal_flops,
+    clippy::suspicious_operation_groupings,
+    clippy::while_float
+)]
+fn new_volatility(phi: f64, volatility: f64, variance: f64, delta: f64) -> f64 {
+    let a = (volatility * volatility).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denominator = phi.mul_add(phi, variance) + ex;
+        ex * (delta * delta - phi * phi - variance - ex) / (2.0 * denominator * denominator)
+            - (x - a) / (TAU * TAU)
+    };
+
+    let mut upper = a;
+    let mut lower = if delta * delta > phi.mul_add(phi, variance) {
+        (delta * delta - phi * phi - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_upper = f(upper);
+    let mut f_lower = f(lower);
+    while (lower - upper).abs() > CONVERGENCE_TOLERANCE {
+        let next = (upper - lower) * f_upper / (f_lower - f_upper) + upper;
+        let f_next = f(next);
+        if f_next * f_lower <= 0.0 {
+            upper = lower;
+            f_upper = f_lower;
+        } else {
+            f_upper /= 2.0;
+        }
+        lower = next;
+        f_lower = f_next;
+    }
+    (upper / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(glicko: f64, deviation: f64) -> Rating {
+        Rating {
+            glicko,
+            deviation,
+            ..Rating::default()
+        }
+    }
+
+    #[test]
+    fn glicko2_matches_paper_example() {
+        let player = rating(1500.0, 200.0);
+        let updated = glicko2(
+            player,
+            &[
+                (rating(1400.0, 30.0), 1.0),
+                (rating(1550.0, 100.0), 0.0),
+                (rating(1700.0, 300.0), 0.0),
+            ],
+        );
+        assert!((updated.glicko - 1464.06).abs() < 0.01);
+        assert!((updated.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001);
+        assert_eq!(updated.games, 3);
+    }
+
+    #[test]
+    fn elo_works() {
+        assert!((elo(1500.0, 1500.0, 1.0) - 1516.0).abs() < f64::EPSILON);
+        assert!((elo(1500.0, 1500.0, 0.5) - 1500.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn rate_game_works() {
+        let (white, black) = rate_game(Rating::default(), Rating::default(), 1.0);
+        assert!(white.glicko > DEFAULT_RATING);
+        assert!(black.glicko < DEFAULT_RATING);
+        assert!(white.deviation < DEFAULT_DEVIATION);
+        assert!(white.elo > DEFAULT_RATING && black.elo < DEFAULT_RATING);
+        assert_eq!((white.games, black.games), (1, 1));
+        assert_eq!(white_score(GameState::Stalemate), Some(0.5));
+        assert_eq!(white_score(GameState::BlackKingOfTheHill), Some(0.0));
+        assert_eq!(white_score(GameState::InProgress), None);
+    }
+}
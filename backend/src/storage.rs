@@ -0,0 +1,562 @@
+#[cfg(test)]
+pub mod memory;
+pub mod sqlite;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chess::game_state::GameState;
+use chess::piece::PieceColor;
+use chess::variant::GameVariant;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::{Seat, User, UserId},
+    chat::ChatMessage,
+    clock::{Clock, TimeControl},
+    games::TakebackRequest,
+    ratings::{PlayerRating, Rating, RatingSystem},
+};
+
+pub type GameId = u64;
+
+/**
+ * Milliseconds since the unix epoch
+ */
+pub type Timestamp = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoredMove {
+    pub ply: u32,
+    pub uci: String,
+    pub played_at: Timestamp,
+    /**
+     * When the game was reset after this move, such moves are kept but no longer replayed
+     */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<Timestamp>,
+    /**
+     * When the move was taken back, it is kept for the record but no longer replayed
+     */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taken_back_at: Option<Timestamp>,
+}
+
+impl StoredMove {
+    /**
+     * Whether the move is part of the game as it stands
+     */
+    #[must_use]
+    pub const fn is_played(&self) -> bool {
+        self.reset_at.is_none() && self.taken_back_at.is_none()
+    }
+}
+
+/**
+ * Settings a game is created with
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewGame {
+    pub white: Option<Seat>,
+    pub black: Option<Seat>,
+    pub time_control: Option<TimeControl>,
+    /**
+     * Set for correspondence games, where each move must be made within this many days
+     */
+    pub days_per_move: Option<u32>,
+    pub variant: GameVariant,
+    /**
+     * FEN of a set up position to play from instead of the variant's starting position
+     */
+    pub start_fen: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameRecord {
+    pub id: GameId,
+    pub white: Option<Seat>,
+    pub black: Option<Seat>,
+    pub time_control: Option<TimeControl>,
+    pub days_per_move: Option<u32>,
+    pub variant: GameVariant,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_fen: Option<String>,
+    pub state: GameState,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+    pub finished_at: Option<Timestamp>,
+    /**
+     * Every move ever made in the game, oldest first, including those taken back or reset
+     */
+    pub moves: Vec<StoredMove>,
+    #[serde(skip)]
+    pub checkpoint: Option<Checkpoint>,
+}
+
+impl GameRecord {
+    /**
+     * The moves that lead to the current position
+     */
+    pub fn played_moves(&self) -> impl Iterator<Item = &StoredMove> {
+        self.moves
+            .iter()
+            .filter(|stored_move| stored_move.is_played())
+    }
+}
+
+/**
+ * What a game only kept in memory, saved when the server shuts down.
+ * It is stale once the game was updated after `saved_at`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub saved_at: Timestamp,
+    /**
+     * The clock with the thinking time of the side to move charged up to `saved_at`
+     */
+    pub clock: Option<Clock>,
+    pub draw_offer: Option<PieceColor>,
+    pub takeback_request: Option<TakebackRequest>,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    GameNotFound(GameId),
+    UsernameTaken,
+    Corrupted(String),
+    Sqlite(rusqlite::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GameNotFound(id) => write!(f, "game {id} not found"),
+            Self::UsernameTaken => write!(f, "that username is already taken"),
+            Self::Corrupted(reason) => write!(f, "stored data is corrupted: {reason}"),
+            Self::Sqlite(err) => write!(f, "sqlite error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Sqlite(err)
+    }
+}
+
+/**
+ * Persistent record of every game played on the server.
+ * Moves are stored in UCI notation and replayed to rebuild a game.
+ */
+pub trait GameStore: Send + Sync {
+    fn create_game(&self, game: &NewGame, created_at: Timestamp) -> Result<GameId, StorageError>;
+
+    fn record_move(
+        &self,
+        game_id: GameId,
+        uci: &str,
+        played_at: Timestamp,
+    ) -> Result<(), StorageError>;
+
+    fn record_result(
+        &self,
+        game_id: GameId,
+        state: GameState,
+        finished_at: Timestamp,
+    ) -> Result<(), StorageError>;
+
+    /**
+     * Starts a game over from the starting position, its moves are kept but marked as reset
+     */
+    fn reset_game(&self, game_id: GameId, reset_at: Timestamp) -> Result<(), StorageError>;
+
+    /**
+     * Marks the last `plies` moves of a game as taken back, they stay in the record
+     */
+    fn take_back(
+        &self,
+        game_id: GameId,
+        plies: u32,
+        taken_back_at: Timestamp,
+    ) -> Result<(), StorageError>;
+
+    /**
+     * Replaces the checkpoint of a game, without counting as an update
+     */
+    fn save_checkpoint(&self, game_id: GameId, checkpoint: &Checkpoint)
+        -> Result<(), StorageError>;
+
+    fn load_game(&self, game_id: GameId) -> Result<Option<GameRecord>, StorageError>;
+
+    fn in_progress_games(&self) -> Result<Vec<GameRecord>, StorageError>;
+
+    /**
+     * The most recently created games, newest first
+     */
+    fn list_games(&self, limit: usize) -> Result<Vec<GameRecord>, StorageError>;
+
+    fn rating(&self, user_id: UserId) -> Result<Option<Rating>, StorageError>;
+
+    /**
+     * Rates a game `white` finished with `white_score` against `black`. Both ratings are read,
+     * rated and written back at once, so games finishing together do not overwrite each other.
+     */
+    fn rate_players(
+        &self,
+        white: UserId,
+        black: UserId,
+        white_score: f64,
+        rated_at: Timestamp,
+    ) -> Result<(Rating, Rating), StorageError>;
+
+    /**
+     * Highest rated players first
+     */
+    fn leaderboard(
+        &self,
+        system: RatingSystem,
+        limit: usize,
+    ) -> Result<Vec<PlayerRating>, StorageError>;
+
+    fn record_chat(&self, game_id: GameId, message: &ChatMessage) -> Result<(), StorageError>;
+
+    /**
+     * Chat of a game in both channels, oldest first
+     */
+    fn chat_messages(&self, game_id: GameId) -> Result<Vec<ChatMessage>, StorageError>;
+
+    /**
+     * Fails with `UsernameTaken` when the name is in use, ignoring case
+     */
+    fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        created_at: Timestamp,
+    ) -> Result<User, StorageError>;
+
+    /**
+     * The user with this name, ignoring case, and their password hash
+     */
+    fn find_user(&self, username: &str) -> Result<Option<(User, String)>, StorageError>;
+
+    /**
+     * Fails when the store cannot be reached, used by the readiness check
+     */
+    fn ping(&self) -> Result<(), StorageError>;
+}
+
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as Timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{memory::MemoryStore, sqlite::SqliteStore, *};
+    use crate::chat::ChatChannel;
+
+    fn store_roundtrip(store: &dyn GameStore) {
+        let alice = store
+            .create_user("alice", "hash", 5)
+            .expect("user is created");
+        let time_control = TimeControl {
+            initial_ms: 300_000,
+            increment_ms: 2_000,
+        };
+        let id = store
+            .create_game(
+                &NewGame {
+                    white: Some(Seat {
+                        user_id: alice.id,
+                        username: alice.username,
+                    }),
+                    time_control: Some(time_control),
+                    days_per_move: Some(3),
+                    variant: GameVariant::Chess960(42),
+                    ..NewGame::default()
+                },
+                10,
+            )
+            .expect("game is created");
+        store.record_move(id, "e2e4", 20).expect("move is stored");
+        store.record_move(id, "e7e5", 30).expect("move is stored");
+
+        let games = store.in_progress_games().expect("query works");
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.id, id);
+        assert_eq!(
+            game.white.as_ref().map(|seat| seat.username.as_str()),
+            Some("alice")
+        );
+        assert_eq!(game.time_control, Some(time_control));
+        assert_eq!(game.days_per_move, Some(3));
+        assert_eq!(game.variant, GameVariant::Chess960(42));
+        assert_eq!(game.start_fen, None);
+        assert_eq!(game.black, None);
+        assert_eq!(game.state, GameState::InProgress);
+        assert_eq!(game.updated_at, 30);
+        let moves: Vec<&str> = game.moves.iter().map(|m| m.uci.as_str()).collect();
+        assert_eq!(moves, vec!["e2e4", "e7e5"]);
+        assert_eq!(game.moves[1].ply, 2);
+
+        store.take_back(id, 1, 35).expect("takeback works");
+        let game = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        let moves: Vec<&str> = game.played_moves().map(|m| m.uci.as_str()).collect();
+        assert_eq!(moves, vec!["e2e4"]);
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.moves[1].taken_back_at, Some(35));
+        assert_eq!(game.updated_at, 35);
+
+        store.reset_game(id, 40).expect("reset works");
+        let games = store.in_progress_games().expect("query works");
+        assert_eq!(games[0].played_moves().count(), 0);
+        assert_eq!(games[0].moves.len(), 2);
+        assert_eq!(games[0].moves[0].reset_at, Some(40));
+        assert_eq!(games[0].moves[1].reset_at, None);
+        assert_eq!(games[0].updated_at, 40);
+        store.record_move(id, "d2d4", 45).expect("move is stored");
+        let game = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        let played: Vec<(u32, &str)> = game
+            .played_moves()
+            .map(|m| (m.ply, m.uci.as_str()))
+            .collect();
+        assert_eq!(played, vec![(1, "d2d4")]);
+        assert_eq!(game.moves.len(), 3);
+
+        store
+            .record_result(id, GameState::WhiteVictory, 50)
+            .expect("result is stored");
+        assert!(store.in_progress_games().expect("query works").is_empty());
+        let game = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        assert_eq!(game.state, GameState::WhiteVictory);
+
+        assert_eq!(game.finished_at, Some(50));
+        assert!(store.load_game(id + 1).expect("load works").is_none());
+
+        let second_id = store
+            .create_game(&NewGame::default(), 70)
+            .expect("game is created");
+        let listed: Vec<GameId> = store
+            .list_games(10)
+            .expect("query works")
+            .iter()
+            .map(|game| game.id)
+            .collect();
+        assert_eq!(listed, vec![second_id, id]);
+        assert_eq!(store.list_games(1).expect("query works").len(), 1);
+
+        assert!(matches!(
+            store.record_move(second_id + 1, "e2e4", 60),
+            Err(StorageError::GameNotFound(_))
+        ));
+    }
+
+    fn start_position_roundtrip(store: &dyn GameStore) {
+        let start_fen = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        let id = store
+            .create_game(
+                &NewGame {
+                    start_fen: Some(start_fen.to_string()),
+                    ..NewGame::default()
+                },
+                55,
+            )
+            .expect("game is created");
+        let game = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        assert_eq!(game.start_fen.as_deref(), Some(start_fen));
+    }
+
+    fn chat_roundtrip(store: &dyn GameStore) {
+        let id = store
+            .create_game(&NewGame::default(), 10)
+            .expect("game is created");
+        let message = ChatMessage {
+            channel: ChatChannel::Players,
+            author: Some("alice".to_string()),
+            text: "good luck".to_string(),
+            sent_at: 20,
+        };
+        let spectator = ChatMessage {
+            channel: ChatChannel::Room,
+            author: None,
+            text: "hello".to_string(),
+            sent_at: 30,
+        };
+        store.record_chat(id, &message).expect("chat is stored");
+        store.record_chat(id, &spectator).expect("chat is stored");
+        assert_eq!(
+            store.chat_messages(id).expect("query works"),
+            vec![message, spectator.clone()]
+        );
+        assert!(store.chat_messages(id + 1).expect("query works").is_empty());
+        assert!(matches!(
+            store.record_chat(id + 1, &spectator),
+            Err(StorageError::GameNotFound(_))
+        ));
+    }
+
+    fn checkpoint_roundtrip(store: &dyn GameStore) {
+        let time_control = TimeControl {
+            initial_ms: 60_000,
+            increment_ms: 0,
+        };
+        let id = store
+            .create_game(
+                &NewGame {
+                    time_control: Some(time_control),
+                    ..NewGame::default()
+                },
+                10,
+            )
+            .expect("game is created");
+        store.record_move(id, "e2e4", 20).expect("move is stored");
+        let checkpoint = Checkpoint {
+            saved_at: 30,
+            clock: Some(Clock::new(time_control)),
+            draw_offer: Some(PieceColor::White),
+            takeback_request: Some(TakebackRequest {
+                color: PieceColor::White,
+                plies: 1,
+            }),
+        };
+        store
+            .save_checkpoint(id, &checkpoint)
+            .expect("checkpoint is stored");
+        let game = store
+            .load_game(id)
+            .expect("load works")
+            .expect("game exists");
+        assert_eq!(game.checkpoint, Some(checkpoint));
+        assert_eq!(game.updated_at, 20);
+        assert!(matches!(
+            store.save_checkpoint(id + 1, &game.checkpoint.expect("checkpoint is there")),
+            Err(StorageError::GameNotFound(_))
+        ));
+    }
+
+    fn ratings_roundtrip(store: &dyn GameStore) {
+        let alice = store
+            .create_user("alice", "hash", 1)
+            .expect("user is created");
+        let bob = store
+            .create_user("bob", "hash", 2)
+            .expect("user is created");
+        let carol = store
+            .create_user("carol", "hash", 3)
+            .expect("user is created");
+        assert_eq!(store.rating(alice.id).expect("query works"), None);
+
+        let (alice_rating, bob_rating) = store
+            .rate_players(alice.id, bob.id, 1.0, 10)
+            .expect("ratings are stored");
+        assert_eq!(
+            (alice_rating, bob_rating),
+            crate::ratings::rate_game(Rating::default(), Rating::default(), 1.0)
+        );
+        assert_eq!(
+            store.rating(alice.id).expect("query works"),
+            Some(alice_rating)
+        );
+
+        // The second game starts from the ratings the first one left
+        let (carol_rating, alice_rating) = store
+            .rate_players(carol.id, alice.id, 0.5, 20)
+            .expect("ratings are stored");
+        assert_eq!(alice_rating.games, 2);
+        assert_eq!(carol_rating.games, 1);
+        assert_eq!(
+            store.rating(alice.id).expect("query works"),
+            Some(alice_rating)
+        );
+        assert_eq!(store.rating(bob.id).expect("query works"), Some(bob_rating));
+
+        let players = |system| -> Vec<String> {
+            store
+                .leaderboard(system, 10)
+                .expect("query works")
+                .into_iter()
+                .map(|entry| entry.player)
+                .collect()
+        };
+        assert_eq!(
+            players(RatingSystem::Glicko2),
+            vec!["alice", "carol", "bob"]
+        );
+        assert_eq!(players(RatingSystem::Elo), vec!["alice", "carol", "bob"]);
+        assert_eq!(
+            store
+                .leaderboard(RatingSystem::Elo, 1)
+                .expect("query works")
+                .len(),
+            1
+        );
+    }
+
+    fn users_roundtrip(store: &dyn GameStore) {
+        assert!(store.find_user("alice").expect("query works").is_none());
+        let alice = store
+            .create_user("alice", "hash", 10)
+            .expect("user is created");
+        assert_eq!(alice.username, "alice");
+        assert!(matches!(
+            store.create_user("Alice", "other", 20),
+            Err(StorageError::UsernameTaken)
+        ));
+        let bob = store
+            .create_user("bob", "hash", 30)
+            .expect("user is created");
+        assert_ne!(alice.id, bob.id);
+
+        let (found, password_hash) = store
+            .find_user("ALICE")
+            .expect("query works")
+            .expect("user exists");
+        assert_eq!(found, alice);
+        assert_eq!(password_hash, "hash");
+    }
+
+    #[test]
+    fn memory_store_works() {
+        MemoryStore::new()
+            .ping()
+            .expect("memory store is reachable");
+        store_roundtrip(&MemoryStore::new());
+        start_position_roundtrip(&MemoryStore::new());
+        ratings_roundtrip(&MemoryStore::new());
+        users_roundtrip(&MemoryStore::new());
+        chat_roundtrip(&MemoryStore::new());
+        checkpoint_roundtrip(&MemoryStore::new());
+    }
+
+    #[test]
+    fn sqlite_store_works() {
+        SqliteStore::open_in_memory()
+            .expect("sqlite opens")
+            .ping()
+            .expect("sqlite is reachable");
+        store_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+        start_position_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+        ratings_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+        users_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+        chat_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+        checkpoint_roundtrip(&SqliteStore::open_in_memory().expect("sqlite opens"));
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, PoisonError},
+};
+
+use chess::game_state::GameState;
+
+use crate::{
+    accounts::{User, UserId},
+    chat::ChatMessage,
+    ratings::{rate_game, PlayerRating, Rating, RatingSystem},
+};
+
+use super::{
+    Checkpoint, GameId, GameRecord, GameStore, NewGame, StorageError, StoredMove, Timestamp,
+};
+
+/**
+ * Keeps games in process memory, used in tests and when no database is wanted
+ */
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    games: Mutex<BTreeMap<GameId, GameRecord>>,
+    ratings: Mutex<HashMap<UserId, Rating>>,
+    users: Mutex<Vec<(User, String)>>,
+    chat: Mutex<HashMap<GameId, Vec<ChatMessage>>>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_game<T>(
+        &self,
+        game_id: GameId,
+        f: impl FnOnce(&mut GameRecord) -> T,
+    ) -> Result<T, StorageError> {
+        let mut games = self.games.lock().unwrap_or_else(PoisonError::into_inner);
+        games
+            .get_mut(&game_id)
+            .map(f)
+            .ok_or(StorageError::GameNotFound(game_id))
+    }
+}
+
+impl GameStore for MemoryStore {
+    fn create_game(&self, game: &NewGame, created_at: Timestamp) -> Result<GameId, StorageError> {
+        let mut games = self.games.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = games.keys().next_back().map_or(1, |last| last + 1);
+        games.insert(
+            id,
+            GameRecord {
+                id,
+                white: game.white.clone(),
+                black: game.black.clone(),
+                time_control: game.time_control,
+                days_per_move: game.days_per_move,
+                variant: game.variant,
+                start_fen: game.start_fen.clone(),
+                state: GameState::InProgress,
+                created_at,
+                updated_at: created_at,
+                finished_at: None,
+                moves: Vec::new(),
+                checkpoint: None,
+            },
+        );
+        drop(games);
+        Ok(id)
+    }
+
+    fn record_move(
+        &self,
+        game_id: GameId,
+        uci: &str,
+        played_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        self.with_game(game_id, |game| {
+            #[allow(clippy::cast_possible_truncation)]
+            let ply = game.played_moves().count() as u32 + 1;
+            game.moves.push(StoredMove {
+                ply,
+                uci: uci.to_owned(),
+                played_at,
+                reset_at: None,
+                taken_back_at: None,
+            });
+            game.updated_at = played_at;
+        })
+    }
+
+    fn record_result(
+        &self,
+        game_id: GameId,
+        state: GameState,
+        finished_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        self.with_game(game_id, |game| {
+            game.state = state;
+            game.updated_at = finished_at;
+            game.finished_at = state.is_finished().then_some(finished_at);
+        })
+    }
+
+    fn reset_game(&self, game_id: GameId, reset_at: Timestamp) -> Result<(), StorageError> {
+        self.with_game(game_id, |game| {
+            for stored_move in game.moves.iter_mut().filter(|m| m.is_played()) {
+                stored_move.reset_at = Some(reset_at);
+            }
+            game.state = GameState::InProgress;
+            game.updated_at = reset_at;
+            game.finished_at = None;
+        })
+    }
+
+    fn save_checkpoint(
+        &self,
+        game_id: GameId,
+        checkpoint: &Checkpoint,
+    ) -> Result<(), StorageError> {
+        self.with_game(game_id, |game| game.checkpoint = Some(checkpoint.clone()))
+    }
+
+    fn take_back(
+        &self,
+        game_id: GameId,
+        plies: u32,
+        taken_back_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        self.with_game(game_id, |game| {
+            for stored_move in game
+                .moves
+                .iter_mut()
+                .rev()
+                .filter(|m| m.is_played())
+                .take(plies as usize)
+            {
+                stored_move.taken_back_at = Some(taken_back_at);
+            }
+            game.updated_at = taken_back_at;
+        })
+    }
+
+    fn load_game(&self, game_id: GameId) -> Result<Option<GameRecord>, StorageError> {
+        let games = self.games.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(games.get(&game_id).cloned())
+    }
+
+    fn in_progress_games(&self) -> Result<Vec<GameRecord>, StorageError> {
+        let games = self.games.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(games
+            .values()
+            .filter(|game| !game.state.is_finished())
+            .cloned()
+            .collect())
+    }
+
+    fn list_games(&self, limit: usize) -> Result<Vec<GameRecord>, StorageError> {
+        let games = self.games.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(games.values().rev().take(limit).cloned().collect())
+    }
+
+    fn rating(&self, user_id: UserId) -> Result<Option<Rating>, StorageError> {
+        let ratings = self.ratings.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(ratings.get(&user_id).copied())
+    }
+
+    fn rate_players(
+        &self,
+        white: UserId,
+        black: UserId,
+        white_score: f64,
+        _rated_at: Timestamp,
+    ) -> Result<(Rating, Rating), StorageError> {
+        let mut ratings = self.ratings.lock().unwrap_or_else(PoisonError::into_inner);
+        let rated = rate_game(
+            ratings.get(&white).copied().unwrap_or_default(),
+            ratings.get(&black).copied().unwrap_or_default(),
+            white_score,
+        );
+        ratings.insert(white, rated.0);
+        ratings.insert(black, rated.1);
+        drop(ratings);
+        Ok(rated)
+    }
+
+    fn leaderboard(
+        &self,
+        system: RatingSystem,
+        limit: usize,
+    ) -> Result<Vec<PlayerRating>, StorageError> {
+        let ratings = self.ratings.lock().unwrap_or_else(PoisonError::into_inner);
+        let users = self.users.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut leaderboard: Vec<PlayerRating> = users
+            .iter()
+            .filter_map(|(user, _)| {
+                ratings.get(&user.id).map(|rating| PlayerRating {
+                    player: user.username.clone(),
+                    rating: *rating,
+                })
+            })
+            .collect();
+        drop(users);
+        drop(ratings);
+        leaderboard.sort_by(|a, b| match system {
+            RatingSystem::Glicko2 => b.rating.glicko.total_cmp(&a.rating.glicko),
+            RatingSystem::Elo => b.rating.elo.total_cmp(&a.rating.elo),
+        });
+        leaderboard.truncate(limit);
+        Ok(leaderboard)
+    }
+
+    fn record_chat(&self, game_id: GameId, message: &ChatMessage) -> Result<(), StorageError> {
+        self.with_game(game_id, |_| ())?;
+        let mut chat = self.chat.lock().unwrap_or_else(PoisonError::into_inner);
+        chat.entry(game_id).or_default().push(message.clone());
+        drop(chat);
+        Ok(())
+    }
+
+    fn chat_messages(&self, game_id: GameId) -> Result<Vec<ChatMessage>, StorageError> {
+        let chat = self.chat.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(chat.get(&game_id).cloned().unwrap_or_default())
+    }
+
+    fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        created_at: Timestamp,
+    ) -> Result<User, StorageError> {
+        let mut users = self.users.lock().unwrap_or_else(PoisonError::into_inner);
+        if users
+            .iter()
+            .any(|(user, _)| user.username.eq_ignore_ascii_case(username))
+        {
+            return Err(StorageError::UsernameTaken);
+        }
+        let user = User {
+            id: users.len() as u64 + 1,
+            username: username.to_owned(),
+            created_at,
+        };
+        users.push((user.clone(), password_hash.to_owned()));
+        drop(users);
+        Ok(user)
+    }
+
+    fn find_user(&self, username: &str) -> Result<Option<(User, String)>, StorageError> {
+        let users = self.users.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(users
+            .iter()
+            .find(|(user, _)| user.username.eq_ignore_ascii_case(username))
+            .cloned())
+    }
+
+    fn ping(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,618 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use chess::{game_state::GameState, variant::GameVariant};
+use rusqlite::{params, types::Type, Connection, ErrorCode, OptionalExtension, Row, Transaction};
+
+use super::{
+    Checkpoint, GameId, GameRecord, GameStore, NewGame, StorageError, StoredMove, Timestamp,
+};
+use crate::{
+    accounts::{Seat, User, UserId},
+    chat::{ChatChannel, ChatMessage},
+    clock::TimeControl,
+    ratings::{rate_game, PlayerRating, Rating, RatingSystem},
+};
+
+/**
+ * Schema changes in the order they were introduced.
+ * The index of the last applied migration is kept in `PRAGMA user_version`.
+ */
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE games (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        white TEXT,
+        black TEXT,
+        state TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        finished_at INTEGER
+    );
+    CREATE TABLE moves (
+        game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+        ply INTEGER NOT NULL,
+        uci TEXT NOT NULL,
+        played_at INTEGER NOT NULL,
+        PRIMARY KEY (game_id, ply)
+    );
+    CREATE INDEX games_state ON games(state);
+",
+    "
+    ALTER TABLE games ADD COLUMN initial_ms INTEGER;
+    ALTER TABLE games ADD COLUMN increment_ms INTEGER;
+",
+    "
+    CREATE TABLE ratings (
+        player TEXT PRIMARY KEY,
+        rating REAL NOT NULL,
+        deviation REAL NOT NULL,
+        volatility REAL NOT NULL,
+        elo REAL NOT NULL,
+        games INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX ratings_rating ON ratings(rating);
+    CREATE INDEX ratings_elo ON ratings(elo);
+",
+    "
+    CREATE TABLE users (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    ALTER TABLE games ADD COLUMN white_id INTEGER REFERENCES users(id);
+    ALTER TABLE games ADD COLUMN black_id INTEGER REFERENCES users(id);
+",
+    "
+    ALTER TABLE games ADD COLUMN days_per_move INTEGER;
+",
+    "
+    CREATE TABLE chat_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+        channel TEXT NOT NULL,
+        author TEXT,
+        text TEXT NOT NULL,
+        sent_at INTEGER NOT NULL
+    );
+    CREATE INDEX chat_messages_game ON chat_messages(game_id);
+",
+    "
+    ALTER TABLE games ADD COLUMN checkpoint TEXT;
+",
+    "
+    ALTER TABLE games ADD COLUMN variant TEXT NOT NULL DEFAULT 'standard';
+",
+    "
+    CREATE TABLE move_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        game_id INTEGER NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+        ply INTEGER NOT NULL,
+        uci TEXT NOT NULL,
+        played_at INTEGER NOT NULL,
+        reset_at INTEGER
+    );
+    INSERT INTO move_history (game_id, ply, uci, played_at)
+        SELECT game_id, ply, uci, played_at FROM moves ORDER BY game_id, ply;
+    DROP TABLE moves;
+    ALTER TABLE move_history RENAME TO moves;
+    CREATE INDEX moves_game ON moves(game_id);
+",
+    "
+    ALTER TABLE moves ADD COLUMN taken_back_at INTEGER;
+",
+    "
+    CREATE TABLE user_ratings (
+        user_id INTEGER PRIMARY KEY REFERENCES users(id),
+        rating REAL NOT NULL,
+        deviation REAL NOT NULL,
+        volatility REAL NOT NULL,
+        elo REAL NOT NULL,
+        games INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    INSERT INTO user_ratings (user_id, rating, deviation, volatility, elo, games, updated_at)
+        SELECT users.id, ratings.rating, ratings.deviation, ratings.volatility, ratings.elo,
+               ratings.games, ratings.updated_at
+        FROM ratings JOIN users ON users.username = ratings.player;
+    DROP TABLE ratings;
+    ALTER TABLE user_ratings RENAME TO ratings;
+    CREATE INDEX ratings_rating ON ratings(rating);
+    CREATE INDEX ratings_elo ON ratings(elo);
+",
+    "
+    ALTER TABLE games ADD COLUMN start_fen TEXT;
+",
+];
+
+const GAME_COLUMNS: &str = "id, white, black, state, created_at, updated_at, finished_at, \
+                            initial_ms, increment_ms, white_id, black_id, days_per_move, checkpoint, \
+                            variant, start_fen";
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    #[allow(clippy::missing_errors_doc)]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    #[allow(clippy::missing_errors_doc)]
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self, StorageError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with_transaction<T>(
+        &self,
+        f: impl FnOnce(&Transaction<'_>) -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let value = f(&tx)?;
+        tx.commit()?;
+        drop(conn);
+        Ok(value)
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), StorageError> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let tx = conn.transaction()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/**
+ * Games created before accounts existed only have a player name, they count as unseated
+ */
+fn seat_from_row(
+    row: &Row<'_>,
+    id_index: usize,
+    name_index: usize,
+) -> rusqlite::Result<Option<Seat>> {
+    Ok(match (row.get(id_index)?, row.get(name_index)?) {
+        (Some(user_id), Some(username)) => Some(Seat { user_id, username }),
+        _ => None,
+    })
+}
+
+fn game_from_row(row: &Row<'_>) -> rusqlite::Result<(GameRecord, String)> {
+    let state: String = row.get(3)?;
+    let time_control = match (row.get(7)?, row.get(8)?) {
+        (Some(initial_ms), Some(increment_ms)) => Some(TimeControl {
+            initial_ms,
+            increment_ms,
+        }),
+        _ => None,
+    };
+    let checkpoint = row
+        .get::<_, Option<String>>(12)?
+        .map(|json| serde_json::from_str::<Checkpoint>(&json))
+        .transpose()
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(12, Type::Text, Box::new(err)))?;
+    let variant = row
+        .get::<_, String>(13)?
+        .parse::<GameVariant>()
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(13, Type::Text, err.into()))?;
+    Ok((
+        GameRecord {
+            id: row.get(0)?,
+            white: seat_from_row(row, 9, 1)?,
+            black: seat_from_row(row, 10, 2)?,
+            time_control,
+            days_per_move: row.get(11)?,
+            variant,
+            start_fen: row.get(14)?,
+            state: GameState::InProgress,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+            finished_at: row.get(6)?,
+            moves: Vec::new(),
+            checkpoint,
+        },
+        state,
+    ))
+}
+
+fn load_moves(conn: &Connection, game: &mut GameRecord) -> Result<(), StorageError> {
+    let mut statement = conn.prepare_cached(
+        "SELECT ply, uci, played_at, reset_at, taken_back_at FROM moves
+         WHERE game_id = ?1 ORDER BY id",
+    )?;
+    game.moves = statement
+        .query_map([game.id], |row| {
+            Ok(StoredMove {
+                ply: row.get(0)?,
+                uci: row.get(1)?,
+                played_at: row.get(2)?,
+                reset_at: row.get(3)?,
+                taken_back_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(())
+}
+
+fn finish_record(
+    conn: &Connection,
+    (mut game, state): (GameRecord, String),
+) -> Result<GameRecord, StorageError> {
+    game.state = GameState::from_str(&state).map_err(StorageError::Corrupted)?;
+    load_moves(conn, &mut game)?;
+    Ok(game)
+}
+
+const RATING_COLUMNS: &str = "rating, deviation, volatility, elo, games";
+
+fn rating_from_row(row: &Row<'_>, offset: usize) -> rusqlite::Result<Rating> {
+    Ok(Rating {
+        glicko: row.get(offset)?,
+        deviation: row.get(offset + 1)?,
+        volatility: row.get(offset + 2)?,
+        elo: row.get(offset + 3)?,
+        games: row.get(offset + 4)?,
+    })
+}
+
+fn load_rating(conn: &Connection, user_id: UserId) -> rusqlite::Result<Option<Rating>> {
+    conn.query_row(
+        &format!("SELECT {RATING_COLUMNS} FROM ratings WHERE user_id = ?1"),
+        [user_id],
+        |row| rating_from_row(row, 0),
+    )
+    .optional()
+}
+
+const fn ensure_updated(game_id: GameId, updated_rows: usize) -> Result<(), StorageError> {
+    if updated_rows == 0 {
+        Err(StorageError::GameNotFound(game_id))
+    } else {
+        Ok(())
+    }
+}
+
+impl GameStore for SqliteStore {
+    fn create_game(&self, game: &NewGame, created_at: Timestamp) -> Result<GameId, StorageError> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO games (white, black, state, created_at, updated_at,
+                                initial_ms, increment_ms, white_id, black_id, days_per_move,
+                                variant, start_fen)
+             VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                game.white.as_ref().map(|seat| &seat.username),
+                game.black.as_ref().map(|seat| &seat.username),
+                GameState::InProgress.as_str(),
+                created_at,
+                game.time_control
+                    .map(|time_control| time_control.initial_ms),
+                game.time_control
+                    .map(|time_control| time_control.increment_ms),
+                game.white.as_ref().map(|seat| seat.user_id),
+                game.black.as_ref().map(|seat| seat.user_id),
+                game.days_per_move,
+                game.variant.to_string(),
+                game.start_fen
+            ],
+        )?;
+        #[allow(clippy::cast_sign_loss)]
+        Ok(conn.last_insert_rowid() as GameId)
+    }
+
+    fn record_move(
+        &self,
+        game_id: GameId,
+        uci: &str,
+        played_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        self.with_transaction(|tx| {
+            let updated = tx.execute(
+                "UPDATE games SET updated_at = ?2 WHERE id = ?1",
+                params![game_id, played_at],
+            )?;
+            ensure_updated(game_id, updated)?;
+            tx.execute(
+                "INSERT INTO moves (game_id, ply, uci, played_at)
+                 SELECT ?1, COUNT(*) + 1, ?2, ?3 FROM moves
+                 WHERE game_id = ?1 AND reset_at IS NULL AND taken_back_at IS NULL",
+                params![game_id, uci, played_at],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn record_result(
+        &self,
+        game_id: GameId,
+        state: GameState,
+        finished_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        let updated = self.conn().execute(
+            "UPDATE games SET state = ?2, updated_at = ?3, finished_at = ?4 WHERE id = ?1",
+            params![
+                game_id,
+                state.as_str(),
+                finished_at,
+                state.is_finished().then_some(finished_at)
+            ],
+        )?;
+        ensure_updated(game_id, updated)
+    }
+
+    fn reset_game(&self, game_id: GameId, reset_at: Timestamp) -> Result<(), StorageError> {
+        self.with_transaction(|tx| {
+            let updated = tx.execute(
+                "UPDATE games SET state = ?2, updated_at = ?3, finished_at = NULL WHERE id = ?1",
+                params![game_id, GameState::InProgress.as_str(), reset_at],
+            )?;
+            ensure_updated(game_id, updated)?;
+            tx.execute(
+                "UPDATE moves SET reset_at = ?2
+                 WHERE game_id = ?1 AND reset_at IS NULL AND taken_back_at IS NULL",
+                params![game_id, reset_at],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn take_back(
+        &self,
+        game_id: GameId,
+        plies: u32,
+        taken_back_at: Timestamp,
+    ) -> Result<(), StorageError> {
+        self.with_transaction(|tx| {
+            let updated = tx.execute(
+                "UPDATE games SET updated_at = ?2 WHERE id = ?1",
+                params![game_id, taken_back_at],
+            )?;
+            ensure_updated(game_id, updated)?;
+            tx.execute(
+                "UPDATE moves SET taken_back_at = ?3
+                 WHERE game_id = ?1 AND reset_at IS NULL AND taken_back_at IS NULL
+                 AND ply > (SELECT COUNT(*) FROM moves
+                            WHERE game_id = ?1 AND reset_at IS NULL AND taken_back_at IS NULL) - ?2",
+                params![game_id, plies, taken_back_at],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn save_checkpoint(
+        &self,
+        game_id: GameId,
+        checkpoint: &Checkpoint,
+    ) -> Result<(), StorageError> {
+        let checkpoint =
+            serde_json::to_string(checkpoint).expect("Checkpoints are always serializable");
+        let updated = self.conn().execute(
+            "UPDATE games SET checkpoint = ?2 WHERE id = ?1",
+            params![game_id, checkpoint],
+        )?;
+        ensure_updated(game_id, updated)
+    }
+
+    fn load_game(&self, game_id: GameId) -> Result<Option<GameRecord>, StorageError> {
+        self.with_transaction(|tx| {
+            tx.query_row(
+                &format!("SELECT {GAME_COLUMNS} FROM games WHERE id = ?1"),
+                [game_id],
+                game_from_row,
+            )
+            .optional()?
+            .map(|row| finish_record(tx, row))
+            .transpose()
+        })
+    }
+
+    fn in_progress_games(&self) -> Result<Vec<GameRecord>, StorageError> {
+        self.with_transaction(|tx| {
+            let rows = tx
+                .prepare(&format!(
+                    "SELECT {GAME_COLUMNS} FROM games WHERE state = ?1 ORDER BY id"
+                ))?
+                .query_map([GameState::InProgress.as_str()], game_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.into_iter().map(|row| finish_record(tx, row)).collect()
+        })
+    }
+
+    fn list_games(&self, limit: usize) -> Result<Vec<GameRecord>, StorageError> {
+        self.with_transaction(|tx| {
+            let rows = tx
+                .prepare(&format!(
+                    "SELECT {GAME_COLUMNS} FROM games ORDER BY id DESC LIMIT ?1"
+                ))?
+                .query_map([limit], game_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.into_iter().map(|row| finish_record(tx, row)).collect()
+        })
+    }
+
+    fn rating(&self, user_id: UserId) -> Result<Option<Rating>, StorageError> {
+        Ok(load_rating(&self.conn(), user_id)?)
+    }
+
+    fn rate_players(
+        &self,
+        white: UserId,
+        black: UserId,
+        white_score: f64,
+        rated_at: Timestamp,
+    ) -> Result<(Rating, Rating), StorageError> {
+        self.with_transaction(|tx| {
+            let rated = rate_game(
+                load_rating(tx, white)?.unwrap_or_default(),
+                load_rating(tx, black)?.unwrap_or_default(),
+                white_score,
+            );
+            let mut statement = tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO ratings (user_id, {RATING_COLUMNS}, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            ))?;
+            for (user_id, rating) in [(white, rated.0), (black, rated.1)] {
+                statement.execute(params![
+                    user_id,
+                    rating.glicko,
+                    rating.deviation,
+                    rating.volatility,
+                    rating.elo,
+                    rating.games,
+                    rated_at
+                ])?;
+            }
+            Ok(rated)
+        })
+    }
+
+    fn leaderboard(
+        &self,
+        system: RatingSystem,
+        limit: usize,
+    ) -> Result<Vec<PlayerRating>, StorageError> {
+        let order_by = match system {
+            RatingSystem::Glicko2 => "rating",
+            RatingSystem::Elo => "elo",
+        };
+        let conn = self.conn();
+        let leaderboard = conn
+            .prepare(&format!(
+                "SELECT users.username, {RATING_COLUMNS} FROM ratings
+                 JOIN users ON users.id = ratings.user_id
+                 ORDER BY {order_by} DESC LIMIT ?1"
+            ))?
+            .query_map([limit], |row| {
+                Ok(PlayerRating {
+                    player: row.get(0)?,
+                    rating: rating_from_row(row, 1)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        drop(conn);
+        Ok(leaderboard)
+    }
+
+    fn record_chat(&self, game_id: GameId, message: &ChatMessage) -> Result<(), StorageError> {
+        self.conn()
+            .execute(
+                "INSERT INTO chat_messages (game_id, channel, author, text, sent_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    game_id,
+                    message.channel.as_str(),
+                    message.author,
+                    message.text,
+                    message.sent_at
+                ],
+            )
+            .map_err(|err| match err.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => StorageError::GameNotFound(game_id),
+                _ => err.into(),
+            })?;
+        Ok(())
+    }
+
+    fn chat_messages(&self, game_id: GameId) -> Result<Vec<ChatMessage>, StorageError> {
+        let conn = self.conn();
+        let rows = conn
+            .prepare_cached(
+                "SELECT channel, author, text, sent_at FROM chat_messages
+                 WHERE game_id = ?1 ORDER BY id",
+            )?
+            .query_map([game_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    ChatMessage {
+                        channel: ChatChannel::Room,
+                        author: row.get(1)?,
+                        text: row.get(2)?,
+                        sent_at: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(conn);
+        rows.into_iter()
+            .map(|(channel, message)| {
+                Ok(ChatMessage {
+                    channel: channel.parse().map_err(StorageError::Corrupted)?,
+                    ..message
+                })
+            })
+            .collect()
+    }
+
+    fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+        created_at: Timestamp,
+    ) -> Result<User, StorageError> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)",
+            params![username, password_hash, created_at],
+        )
+        .map_err(|err| match err.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => StorageError::UsernameTaken,
+            _ => err.into(),
+        })?;
+        #[allow(clippy::cast_sign_loss)]
+        let id = conn.last_insert_rowid() as u64;
+        drop(conn);
+        Ok(User {
+            id,
+            username: username.to_owned(),
+            created_at,
+        })
+    }
+
+    fn find_user(&self, username: &str) -> Result<Option<(User, String)>, StorageError> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT id, username, created_at, password_hash FROM users WHERE username = ?1",
+                [username],
+                |row| {
+                    Ok((
+                        User {
+                            id: row.get(0)?,
+                            username: row.get(1)?,
+                            created_at: row.get(2)?,
+                        },
+                        row.get(3)?,
+                    ))
+                },
+            )
+            .optional()?)
+    }
+
+    fn ping(&self) -> Result<(), StorageError> {
+        self.conn().query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
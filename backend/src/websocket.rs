@@ -0,0 +1,410 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        ConnectInfo, WebSocketUpgrade,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        Mutex,
+    },
+    time::Instant,
+};
+use tracing::{debug, error, info, info_span, Instrument};
+
+use crate::{
+    accounts::{AuthUser, Seat},
+    chat::{ChatError, ChatLimiter},
+    games::{Game, GameError},
+    limits::{
+        too_many_requests, ConnectionSlot, Limits, TokenBucket, MAX_FRAME_SIZE,
+        MESSAGES_PER_CONNECTION, RESET_COST,
+    },
+    metrics::METRICS,
+    protocol::{parse_client_message, ClientMessage, ServerMessage},
+    storage::now,
+    State,
+};
+
+/**
+ * How often the server pings a connection to find out whether it is still there
+ */
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/**
+ * Connections that send nothing, not even a pong, for this long are closed
+ */
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/**
+ * Numbers connections in the logs
+ */
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/**
+ * Spectators connect without a token, players pass theirs as the `token` query parameter
+ */
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    user: Option<AuthUser>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<Mutex<State>>>,
+    Extension(limits): Extension<Arc<Limits>>,
+) -> Response {
+    let ip = limits.client_ip(addr.ip(), &headers);
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        if !limits.origin_allowed(origin, headers.get(header::HOST)) {
+            info!(ip = %ip, ?origin, "websocket from a foreign origin refused");
+            return (StatusCode::FORBIDDEN, "origin not allowed").into_response();
+        }
+    }
+    if !limits.connects.take(ip, 1, now()) {
+        info!(ip = %ip, "websocket refused, too many connection attempts");
+        return too_many_requests("too many connection attempts, slow down");
+    }
+    let Some(slot) = limits.open_connection(ip) else {
+        info!(ip = %ip, "websocket refused, too many open connections");
+        return too_many_requests("too many open connections");
+    };
+
+    let user = user.map(|AuthUser(seat)| seat);
+    let span = info_span!(
+        "connection",
+        id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        ip = %ip,
+        user = user.as_ref().map(|seat| seat.username.as_str()),
+    );
+    ws.max_frame_size(MAX_FRAME_SIZE)
+        .max_message_size(MAX_FRAME_SIZE)
+        .on_upgrade(move |socket| websocket(socket, state, user, limits, slot).instrument(span))
+}
+
+/**
+ * The game a connection is watching and its feeds of updates
+ */
+struct Watching {
+    game: Arc<Mutex<Game>>,
+    updates: broadcast::Receiver<String>,
+    /**
+     * Only there when the connected user plays in the game
+     */
+    player_updates: Option<broadcast::Receiver<String>>,
+}
+
+impl Watching {
+    async fn new(game: Arc<Mutex<Game>>, user: Option<&Seat>) -> (Self, ServerMessage) {
+        let (updates, player_updates, snapshot) = {
+            let game = game.lock().await;
+            (
+                game.subscribe(),
+                game.subscribe_as_player(user.map(|seat| seat.user_id)),
+                game.snapshot(),
+            )
+        };
+        (
+            Self {
+                game,
+                updates,
+                player_updates,
+            },
+            snapshot,
+        )
+    }
+
+    /**
+     * Next update from the game, or from its players feed when the client plays in it
+     */
+    async fn next_update(&mut self) -> Result<String, RecvError> {
+        tokio::select! {
+            update = self.updates.recv() => update,
+            update = next_player_update(self.player_updates.as_mut()) => update,
+        }
+    }
+
+    /**
+     * What to send for an update, a fresh snapshot when the client missed some of them.
+     * None once the game's feeds are closed.
+     */
+    async fn catch_up(&self, update: Result<String, RecvError>) -> Option<String> {
+        match update {
+            Ok(message) => Some(message),
+            Err(RecvError::Lagged(missed)) => {
+                info!(missed, "client fell behind, resending the game");
+                Some(self.game.lock().await.snapshot().to_json())
+            }
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
+/**
+ * Next player only update, never resolving for connections that have no such feed
+ */
+async fn next_player_update(
+    updates: Option<&mut broadcast::Receiver<String>>,
+) -> Result<String, RecvError> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/**
+ * What a connection knows about its client
+ */
+struct Connection {
+    user: Option<Seat>,
+    chat_limiter: ChatLimiter,
+    messages: TokenBucket,
+    limits: Arc<Limits>,
+}
+
+impl Connection {
+    /**
+     * Spends `cost` tokens of both this connection and the signed in user, who may have several
+     */
+    fn allow(&mut self, cost: u64) -> bool {
+        let at = now();
+        self.messages.take(cost, at)
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|seat| self.limits.session_messages.take(seat.user_id, cost, at))
+    }
+}
+
+async fn websocket(
+    mut socket: WebSocket,
+    state: Arc<Mutex<State>>,
+    user: Option<Seat>,
+    limits: Arc<Limits>,
+    _slot: ConnectionSlot,
+) {
+    let (game, mut lobby_updates, mut shutdown) = {
+        let mut state = state.lock().await;
+        state.clients_count += 1;
+        (
+            state.game.clone(),
+            state.lobby_updates.subscribe(),
+            state.shutdown.subscribe(),
+        )
+    };
+
+    info!("client connected");
+    let (mut watching, snapshot) = Watching::new(game, user.as_ref()).await;
+    let mut connection = Connection {
+        user,
+        chat_limiter: ChatLimiter::new(),
+        messages: TokenBucket::new(MESSAGES_PER_CONNECTION, now()),
+        limits,
+    };
+    let mut last_heard = Instant::now();
+    let mut pings = tokio::time::interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    if !*shutdown.borrow_and_update() && send(&mut socket, &snapshot.to_json()).await {
+        loop {
+            tokio::select! {
+                incoming = socket.recv() => match incoming.inspect(|_| last_heard = Instant::now()) {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Some(reply) = handle_message(&text, &mut watching, &mut connection, &state).await {
+                            if !send(&mut socket, &reply.to_json()).await {
+                                break;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {
+                        let reply = ServerMessage::error("invalid_message", "binary frames are not supported");
+                        if !send(&mut socket, &reply.to_json()).await {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                },
+                _ = shutdown.changed() => {
+                    if send(&mut socket, &ServerMessage::ServerRestarting.to_json()).await {
+                        let _ = socket.send(Message::Close(None)).await;
+                    }
+                    break;
+                }
+                _ = pings.tick() => {
+                    if last_heard.elapsed() >= IDLE_TIMEOUT {
+                        info!("closing idle connection");
+                        break;
+                    }
+                    if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                        METRICS.websocket_send_failed();
+                        break;
+                    }
+                }
+                update = watching.next_update() => match watching.catch_up(update).await {
+                    Some(message) => {
+                        if !send(&mut socket, &message).await {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+                update = lobby_updates.recv() => match update {
+                    Ok(message) => {
+                        if !send(&mut socket, &message).await {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+    }
+
+    state.lock().await.clients_count -= 1;
+    info!("client disconnected");
+}
+
+/**
+ * Returns false when the client has gone away
+ */
+async fn send(socket: &mut WebSocket, message: &str) -> bool {
+    let sent = socket.send(Message::Text(message.to_owned())).await.is_ok();
+    if !sent {
+        METRICS.websocket_send_failed();
+    }
+    sent
+}
+
+/**
+ * Carries out a client message, returning the reply meant only for this client.
+ * Changes to the game reach every watcher, this client included, through the game's updates.
+ */
+async fn handle_message(
+    text: &str,
+    watching: &mut Watching,
+    connection: &mut Connection,
+    state: &Arc<Mutex<State>>,
+) -> Option<ServerMessage> {
+    let user = connection.user.as_ref().map(|seat| seat.user_id);
+    let message = match parse_client_message(text) {
+        Ok(message) => message,
+        Err(error_frame) => return Some(error_frame),
+    };
+
+    let cost = if matches!(message, ClientMessage::Reset) {
+        RESET_COST
+    } else {
+        1
+    };
+    if !connection.allow(cost) {
+        info!("message rate limited");
+        return Some(ServerMessage::error(
+            "rate_limited",
+            "too many messages, slow down",
+        ));
+    }
+
+    let result = match message {
+        ClientMessage::Join { game_id } => {
+            let game = {
+                let state = state.lock().await;
+                game_id.map_or_else(|| Some(state.game.clone()), |id| state.games.get(id))
+            };
+            let Some(game) = game else {
+                return Some(ServerMessage::error(
+                    "game_not_found",
+                    format!("game {} not found", game_id.unwrap_or_default()),
+                ));
+            };
+            let (joined, snapshot) = Watching::new(game, connection.user.as_ref()).await;
+            let game_id = joined.game.lock().await.id;
+            info!(game_id, "joined game");
+            *watching = joined;
+            return Some(snapshot);
+        }
+        ClientMessage::Ping => return Some(ServerMessage::Pong),
+        ClientMessage::Move { uci } => watching.game.lock().await.play(&uci, user),
+        ClientMessage::Resign { color } => watching.game.lock().await.resign(color, user),
+        ClientMessage::OfferDraw { color } => watching.game.lock().await.offer_draw(color, user),
+        ClientMessage::RequestTakeback { color, scope } => watching
+            .game
+            .lock()
+            .await
+            .request_takeback(color, scope, user),
+        ClientMessage::AnswerTakeback { color, accept } => watching
+            .game
+            .lock()
+            .await
+            .answer_takeback(color, accept, user),
+        ClientMessage::Chat { text, channel } => {
+            if connection.chat_limiter.allow(now()) {
+                watching
+                    .game
+                    .lock()
+                    .await
+                    .chat(connection.user.as_ref(), channel, &text)
+            } else {
+                Err(GameError::Chat(ChatError::RateLimited))
+            }
+        }
+        ClientMessage::Reset => watching.game.lock().await.reset(),
+    };
+
+    result.err().map(|err| {
+        if let GameError::Storage(err) = &err {
+            error!(%err, "storage error");
+        } else {
+            debug!(reason = err.as_str(), "request rejected");
+        }
+        ServerMessage::error(err.as_str(), err.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        games::GameRegistry,
+        storage::{memory::MemoryStore, NewGame},
+    };
+
+    #[tokio::test]
+    async fn lagging_clients_get_a_fresh_snapshot() {
+        let mut games = GameRegistry::restore(Arc::new(MemoryStore::new())).expect("restore works");
+        let game = games
+            .create_game(&NewGame::default())
+            .expect("game is created");
+        let (mut watching, _) = Watching::new(game.clone(), None).await;
+
+        let mut game = game.lock().await;
+        game.update(|chess| chess.make_move_from_uci("e2e4"))
+            .expect("move is stored")
+            .expect("move is legal");
+        for _ in 0..100 {
+            game.broadcast(&ServerMessage::error(
+                "filler",
+                "an update the client misses",
+            ));
+        }
+        drop(game);
+
+        let update = watching.next_update().await;
+        assert!(matches!(update, Err(RecvError::Lagged(_))));
+        let message = watching.catch_up(update).await.expect("game is still open");
+        assert!(message.contains(r#""type":"state""#), "{message}");
+        assert!(message.contains(r#""turn_number":1"#), "{message}");
+        // Then the updates still buffered follow as usual
+        let update = watching.next_update().await;
+        assert!(update.is_ok_and(|message| message.contains("filler")));
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use serde::Serialize;
+use tokio::{
+    sync::Semaphore,
+    task::{self, JoinError},
+};
+
+/**
+ * Runs CPU heavy work such as password hashing off the async workers,
+ * at most `size` jobs at a time
+ */
+#[derive(Debug)]
+pub struct WorkerPool {
+    size: usize,
+    permits: Arc<Semaphore>,
+    busy: AtomicUsize,
+    waiting: AtomicUsize,
+}
+
+/**
+ * How loaded the worker pool is, as shown by the readiness check
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WorkerPoolStatus {
+    pub size: usize,
+    pub busy: usize,
+    pub waiting: usize,
+}
+
+impl WorkerPool {
+    #[must_use]
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            permits: Arc::new(Semaphore::new(size)),
+            busy: AtomicUsize::new(0),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /**
+     * Runs `job` on a blocking thread once one of the workers is free
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn run<T, F>(self: &Arc<Self>, job: F) -> Result<T, JoinError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let permit = {
+            let _waiting = Counted::new(&self.waiting);
+            self.permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("worker pool is never closed")
+        };
+        let pool = self.clone();
+        task::spawn_blocking(move || {
+            let _busy = Counted::new(&pool.busy);
+            let output = job();
+            drop(permit);
+            output
+        })
+        .await
+    }
+
+    #[must_use]
+    pub fn status(&self) -> WorkerPoolStatus {
+        WorkerPoolStatus {
+            size: self.size,
+            busy: self.busy.load(Ordering::Relaxed),
+            waiting: self.waiting.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/**
+ * Counts itself in `count` until dropped, even when the job panics or is cancelled
+ */
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn jobs_are_counted() {
+        let pool = Arc::new(WorkerPool::new(1));
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (finish_tx, finish_rx) = std::sync::mpsc::channel::<()>();
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    started_tx.send(()).expect("test is waiting");
+                    finish_rx.recv().expect("test finishes the job");
+                    1
+                })
+                .await
+            }
+        });
+        task::spawn_blocking(move || started_rx.recv())
+            .await
+            .expect("waiting does not panic")
+            .expect("job starts");
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 2).await }
+        });
+        while pool.status().waiting == 0 {
+            task::yield_now().await;
+        }
+        assert_eq!(
+            pool.status(),
+            WorkerPoolStatus {
+                size: 1,
+                busy: 1,
+                waiting: 1
+            }
+        );
+
+        finish_tx.send(()).expect("job is running");
+        assert_eq!(running.await.expect("no panic").expect("no panic"), 1);
+        assert_eq!(queued.await.expect("no panic").expect("no panic"), 2);
+        assert_eq!(
+            pool.status(),
+            WorkerPoolStatus {
+                size: 1,
+                busy: 0,
+                waiting: 0
+            }
+        );
+    }
+}
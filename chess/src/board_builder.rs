@@ -0,0 +1,448 @@
+use crate::{
+    castling::{Castling, CastlingSide, RightToCastle},
+    check::is_king_in_check_state,
+    chess::{Chess, LatestMove, SquareCoordinates},
+    chessboard::{add_possible_moves_to_squares, file::File, new_board, rank::Rank, ChessBoard},
+    fen::fen_char_to_piece,
+    make_chess_move::{apply_outcome, update_check_status},
+    notation::parse_uci_square,
+    piece::{Piece, PieceColor},
+    variant::GameVariant,
+};
+
+const NO_CASTLING: RightToCastle = RightToCastle {
+    kingside: false,
+    queenside: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    InvalidFen,
+    InvalidSideToMove,
+    InvalidPiece,
+    KingCount,
+    PawnOnBackRank,
+    InvalidCastling,
+    InvalidEnPassant,
+    OpponentInCheck,
+}
+
+impl BoardError {
+    /**
+     * Stable code for the error, sent to clients next to the message
+     */
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidFen => "invalid_fen",
+            Self::InvalidSideToMove => "invalid_side_to_move",
+            Self::InvalidPiece => "invalid_piece",
+            Self::KingCount => "king_count",
+            Self::PawnOnBackRank => "pawn_on_back_rank",
+            Self::InvalidCastling => "invalid_castling",
+            Self::InvalidEnPassant => "invalid_en_passant",
+            Self::OpponentInCheck => "opponent_in_check",
+        }
+    }
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Self::InvalidFen => "the FEN does not describe a board",
+            Self::InvalidSideToMove => "the side to move has to be white or black",
+            Self::InvalidPiece => "pieces have to be white or black",
+            Self::KingCount => "each side needs exactly one king",
+            Self::PawnOnBackRank => "pawns cannot stand on the first or eighth rank",
+            Self::InvalidCastling => "castling needs the king and rook on their starting squares",
+            Self::InvalidEnPassant => "the en passant square does not follow a double pawn step",
+            Self::OpponentInCheck => "the side that just moved cannot be in check",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+/**
+ * Sets up a standard chess position piece by piece, for studies and lessons.
+ * It starts from an empty board with white to move and no castling rights.
+ */
+#[derive(Debug, Clone)]
+pub struct BoardBuilder {
+    board: ChessBoard,
+    side_to_move: PieceColor,
+    castling: Castling,
+    en_passant: Option<SquareCoordinates>,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            board: new_board(),
+            side_to_move: PieceColor::White,
+            castling: Castling {
+                white: NO_CASTLING,
+                black: NO_CASTLING,
+            },
+            en_passant: None,
+        }
+    }
+
+    /**
+     * Sets up the board, side to move, castling rights and en passant square of a
+     * standard FEN. The move counters are left out, a built position starts afresh.
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_fen(fen: &str) -> Result<Self, BoardError> {
+        let mut fields = fen.split_whitespace();
+        let (Some(placement), Some(turn), Some(castling), Some(en_passant)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(BoardError::InvalidFen);
+        };
+
+        let mut builder = Self::new();
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(BoardError::InvalidFen);
+        }
+        for (row, pieces) in ranks.into_iter().enumerate() {
+            let rank = Rank::try_from(7 - row).map_err(|()| BoardError::InvalidFen)?;
+            let mut file = 0;
+            for piece_char in pieces.chars() {
+                if let Some(empty_squares) = piece_char.to_digit(10) {
+                    file += empty_squares;
+                    continue;
+                }
+                let piece = fen_char_to_piece(piece_char).ok_or(BoardError::InvalidFen)?;
+                let square = u8::try_from(file)
+                    .ok()
+                    .and_then(|file| File::try_from(file).ok())
+                    .ok_or(BoardError::InvalidFen)?;
+                builder = builder.place(square, rank, piece);
+                file += 1;
+            }
+            if file != 8 {
+                return Err(BoardError::InvalidFen);
+            }
+        }
+
+        builder = builder.side_to_move(match turn {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(BoardError::InvalidFen),
+        });
+        if castling != "-" {
+            for right in castling.chars() {
+                let rights = match right {
+                    'K' => &mut builder.castling.white.kingside,
+                    'Q' => &mut builder.castling.white.queenside,
+                    'k' => &mut builder.castling.black.kingside,
+                    'q' => &mut builder.castling.black.queenside,
+                    _ => return Err(BoardError::InvalidFen),
+                };
+                *rights = true;
+            }
+        }
+        if en_passant != "-" {
+            let square = parse_uci_square(en_passant).ok_or(BoardError::InvalidFen)?;
+            builder = builder.en_passant(Some(square));
+        }
+        Ok(builder)
+    }
+
+    /**
+     * Puts `piece` on the square, replacing whatever stood there
+     */
+    #[must_use]
+    pub const fn place(mut self, file: File, rank: Rank, piece: Piece) -> Self {
+        self.board[file as usize][rank as usize].piece = piece;
+        self
+    }
+
+    #[must_use]
+    pub const fn remove(self, file: File, rank: Rank) -> Self {
+        self.place(file, rank, Piece::None)
+    }
+
+    #[must_use]
+    pub const fn side_to_move(mut self, color: PieceColor) -> Self {
+        self.side_to_move = color;
+        self
+    }
+
+    #[must_use]
+    pub const fn castling(mut self, castling: Castling) -> Self {
+        self.castling = castling;
+        self
+    }
+
+    /**
+     * The square a pawn passed over with a double step on the previous move
+     */
+    #[must_use]
+    pub const fn en_passant(mut self, square: Option<SquareCoordinates>) -> Self {
+        self.en_passant = square;
+        self
+    }
+
+    /**
+     * Checks the position can be reached in a game and sets it up for play
+     */
+    #[allow(clippy::missing_errors_doc)]
+    pub fn build(self) -> Result<Chess, BoardError> {
+        if self.side_to_move == PieceColor::None {
+            return Err(BoardError::InvalidSideToMove);
+        }
+        self.validate_pieces()?;
+        self.validate_castling()?;
+        let latest_move = self.double_step()?;
+        if is_king_in_check_state(&self.board, self.side_to_move.opposite()) {
+            return Err(BoardError::OpponentInCheck);
+        }
+
+        let mut chess = Chess::with_variant(GameVariant::Standard);
+        chess.board = self.board;
+        chess.turn_number = i32::from(self.side_to_move == PieceColor::Black);
+        chess.players.0.castling = self.castling.white;
+        chess.players.1.castling = self.castling.black;
+        chess.latest_move = latest_move;
+        update_check_status(&mut chess);
+        add_possible_moves_to_squares(&mut chess);
+        apply_outcome(&mut chess, self.side_to_move);
+        Ok(chess)
+    }
+
+    fn validate_pieces(&self) -> Result<(), BoardError> {
+        let pieces = || {
+            self.board
+                .iter()
+                .flatten()
+                .filter(|square| square.piece != Piece::None)
+        };
+        if pieces().any(|square| square.piece.color() == PieceColor::None) {
+            return Err(BoardError::InvalidPiece);
+        }
+        for color in [PieceColor::White, PieceColor::Black] {
+            if pieces()
+                .filter(|square| square.piece == Piece::King(color))
+                .count()
+                != 1
+            {
+                return Err(BoardError::KingCount);
+            }
+        }
+        if pieces().any(|square| {
+            square.piece.is_pawn() && matches!(square.rank, Rank::First | Rank::Eighth)
+        }) {
+            return Err(BoardError::PawnOnBackRank);
+        }
+        Ok(())
+    }
+
+    /**
+     * A right to castle needs the king and that rook still on their starting squares
+     */
+    fn validate_castling(&self) -> Result<(), BoardError> {
+        let variant = GameVariant::Standard;
+        for (color, rights, rank) in [
+            (PieceColor::White, self.castling.white, Rank::First),
+            (PieceColor::Black, self.castling.black, Rank::Eighth),
+        ] {
+            let piece_at = |file: File| self.board[file as usize][rank as usize].piece;
+            for side in [CastlingSide::Kingside, CastlingSide::Queenside] {
+                if rights.allows(side)
+                    && (piece_at(variant.king_file()) != Piece::King(color)
+                        || piece_at(variant.rook_file(side)) != Piece::Rook(color))
+                {
+                    return Err(BoardError::InvalidCastling);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * The double pawn step the en passant square comes from, as the latest move of the game
+     */
+    fn double_step(&self) -> Result<Option<LatestMove>, BoardError> {
+        let Some((file, rank)) = self.en_passant else {
+            return Ok(None);
+        };
+        let (passed_rank, start_rank, end_rank) = match self.side_to_move {
+            PieceColor::White => (Rank::Sixth, Rank::Seventh, Rank::Fifth),
+            _ => (Rank::Third, Rank::Second, Rank::Fourth),
+        };
+        let pawn = Piece::Pawn(self.side_to_move.opposite());
+        let square = |rank: Rank| &self.board[file as usize][rank as usize];
+        if rank != passed_rank
+            || square(passed_rank).has_piece()
+            || square(start_rank).has_piece()
+            || square(end_rank).piece != pawn
+        {
+            return Err(BoardError::InvalidEnPassant);
+        }
+        // Both squares as they were before the step, like `make_chess_move` records them
+        let mut start_sq = square(start_rank).clone();
+        start_sq.piece = pawn;
+        let mut end_sq = square(end_rank).clone();
+        end_sq.piece = Piece::None;
+        Ok(Some((start_sq, end_sq, pawn.color())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fen::{to_fen, STARTING_POSITION_FEN},
+        game_state::GameState,
+        make_chess_move::MoveError,
+    };
+
+    const WHITE: PieceColor = PieceColor::White;
+    const BLACK: PieceColor = PieceColor::Black;
+
+    fn kings() -> BoardBuilder {
+        BoardBuilder::new()
+            .place(File::E, Rank::First, Piece::King(WHITE))
+            .place(File::E, Rank::Eighth, Piece::King(BLACK))
+    }
+
+    #[test]
+    fn positions_are_built() {
+        let chess = kings()
+            .place(File::H, Rank::First, Piece::Rook(WHITE))
+            .place(File::D, Rank::Fourth, Piece::Pawn(WHITE))
+            .place(File::E, Rank::Fourth, Piece::Pawn(BLACK))
+            .castling(Castling {
+                white: RightToCastle {
+                    kingside: true,
+                    queenside: false,
+                },
+                black: NO_CASTLING,
+            })
+            .side_to_move(BLACK)
+            .en_passant(Some((File::D, Rank::Third)))
+            .build()
+            .expect("position is valid");
+        assert_eq!(to_fen(&chess), "4k3/8/8/8/3Pp3/8/8/4K2R b K d3 0 1");
+        assert_eq!(chess.gamestate, GameState::InProgress);
+
+        let mut chess = chess;
+        assert_eq!(chess.make_move_from_uci("e4d3"), Ok(()));
+        assert_eq!(chess.get_square(File::D, Rank::Fourth).piece, Piece::None);
+        assert_eq!(chess.make_move_from_uci("e1g1"), Ok(()));
+        assert_eq!(
+            chess.make_move_from_uci("e8e1"),
+            Err(MoveError::IllegalMove)
+        );
+    }
+
+    #[test]
+    fn impossible_positions_are_rejected() {
+        assert_eq!(
+            BoardBuilder::new()
+                .place(File::E, Rank::First, Piece::King(WHITE))
+                .build()
+                .err(),
+            Some(BoardError::KingCount)
+        );
+        assert_eq!(
+            kings()
+                .place(File::A, Rank::First, Piece::King(WHITE))
+                .build()
+                .err(),
+            Some(BoardError::KingCount)
+        );
+        assert_eq!(
+            kings()
+                .remove(File::E, Rank::First)
+                .place(File::D, Rank::First, Piece::King(WHITE))
+                .build()
+                .map(|chess| to_fen(&chess)),
+            Ok("4k3/8/8/8/8/8/8/3K4 w - - 0 1".to_string())
+        );
+        assert_eq!(
+            kings().side_to_move(PieceColor::None).build().err(),
+            Some(BoardError::InvalidSideToMove)
+        );
+        assert_eq!(
+            kings()
+                .place(File::A, Rank::Second, Piece::Queen(PieceColor::None))
+                .build()
+                .err(),
+            Some(BoardError::InvalidPiece)
+        );
+        assert_eq!(
+            kings()
+                .place(File::A, Rank::Eighth, Piece::Pawn(WHITE))
+                .build()
+                .err(),
+            Some(BoardError::PawnOnBackRank)
+        );
+        assert_eq!(
+            kings().castling(Castling::new()).build().err(),
+            Some(BoardError::InvalidCastling)
+        );
+        assert_eq!(
+            kings()
+                .en_passant(Some((File::D, Rank::Sixth)))
+                .build()
+                .err(),
+            Some(BoardError::InvalidEnPassant)
+        );
+        assert_eq!(
+            kings()
+                .place(File::E, Rank::Second, Piece::Rook(WHITE))
+                .side_to_move(WHITE)
+                .build()
+                .err(),
+            Some(BoardError::OpponentInCheck)
+        );
+    }
+
+    #[test]
+    fn fens_are_read() {
+        for fen in [STARTING_POSITION_FEN, "4k3/8/8/8/3Pp3/8/8/4K2R b K d3 0 1"] {
+            let chess = BoardBuilder::from_fen(fen)
+                .and_then(BoardBuilder::build)
+                .expect("position is valid");
+            assert_eq!(to_fen(&chess), fen);
+        }
+        for fen in [
+            "",
+            "4k3/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w X - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - z9 0 1",
+        ] {
+            assert_eq!(
+                BoardBuilder::from_fen(fen).err(),
+                Some(BoardError::InvalidFen),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn finished_positions_are_over() {
+        let chess = kings()
+            .place(File::A, Rank::Second, Piece::Rook(BLACK))
+            .place(File::B, Rank::First, Piece::Rook(BLACK))
+            .build()
+            .expect("position is valid");
+        assert!(chess.players.0.in_check);
+        assert_eq!(chess.gamestate, GameState::BlackVictory);
+    }
+}
@@ -35,6 +35,36 @@ impl Default for Castling {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CastlingSide {
+    Kingside,
+    Queenside,
+}
+
+impl CastlingSide {
+    /**
+     * Where the king lands, in every variant
+     */
+    #[must_use]
+    pub const fn king_destination(self) -> File {
+        match self {
+            Self::Kingside => File::G,
+            Self::Queenside => File::C,
+        }
+    }
+
+    /**
+     * Where the rook lands, in every variant
+     */
+    #[must_use]
+    pub const fn rook_destination(self) -> File {
+        match self {
+            Self::Kingside => File::F,
+            Self::Queenside => File::D,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RightToCastle {
     pub kingside: bool,
@@ -50,7 +80,15 @@ impl RightToCastle {
         }
     }
 
-    pub fn remove_castling(&mut self) {
+    #[must_use]
+    pub const fn allows(self, side: CastlingSide) -> bool {
+        match side {
+            CastlingSide::Kingside => self.kingside,
+            CastlingSide::Queenside => self.queenside,
+        }
+    }
+
+    pub const fn remove_castling(&mut self) {
         self.kingside = false;
         self.queenside = false;
     }
@@ -1,22 +1,24 @@
 use crate::{
-    chessboard::{get_black_king, get_white_king, ChessBoard},
+    chessboard::{get_adjacent_squares, get_black_king, get_white_king, ChessBoard},
     moves::bishop::BISHOP_MOVES,
     moves::{knight::KNIGHT_MOVES, rook::ROOK_MOVES},
     piece::{Piece, PieceColor},
 };
 
+/**
+ * Whether the king of `king_color` is attacked, a side without a king is never in check
+ */
 #[must_use]
 #[allow(clippy::missing_panics_doc)]
 pub fn is_king_in_check_state(chess_board: &ChessBoard, king_color: PieceColor) -> bool {
     let king_sq = match king_color {
-        PieceColor::White => {
-            get_white_king(chess_board).unwrap_or_else(|| panic!("White king not found"))
-        }
-        PieceColor::Black => {
-            get_black_king(chess_board).unwrap_or_else(|| panic!("Black king not found"))
-        }
+        PieceColor::White => get_white_king(chess_board),
+        PieceColor::Black => get_black_king(chess_board),
         PieceColor::None => panic!("King color is neither white nor black"),
     };
+    let Some(king_sq) = king_sq else {
+        return false;
+    };
 
     let king_file = king_sq.file as i8;
     let king_rank = king_sq.rank as i8;
@@ -33,6 +35,19 @@ pub fn is_king_in_check_state(chess_board: &ChessBoard, king_color: PieceColor)
         || rook_or_queen_check(king_file, king_rank, king_color, chess_board)
 }
 
+/**
+ * Whether the two kings stand next to each other, which no legal standard move allows
+ */
+#[must_use]
+pub fn kings_are_adjacent(chess_board: &ChessBoard) -> bool {
+    match (get_white_king(chess_board), get_black_king(chess_board)) {
+        (Some(white_king), Some(black_king)) => get_adjacent_squares(white_king, chess_board)
+            .iter()
+            .any(|square| square.file == black_king.file && square.rank == black_king.rank),
+        _ => false,
+    }
+}
+
 fn get_opponent_color(color: PieceColor) -> PieceColor {
     match color {
         PieceColor::White => PieceColor::Black,
@@ -188,15 +203,26 @@ fn knight_check(
 mod tests {
     use crate::{
         check::{
-            bishop_or_queen_check, check_by_black_pawn, check_by_white_pawn, rook_or_queen_check,
+            bishop_or_queen_check, check_by_black_pawn, check_by_white_pawn,
+            is_king_in_check_state, rook_or_queen_check,
         },
         chess::Chess,
+        chessboard::new_board,
         piece::{Piece, PieceColor},
     };
 
     const BLACK: PieceColor = PieceColor::Black;
     const WHITE: PieceColor = PieceColor::White;
 
+    #[test]
+    fn kingless_sides_are_never_in_check() {
+        let mut board = new_board();
+        board[0][0].piece = Piece::Queen(BLACK);
+        board[4][7].piece = Piece::King(BLACK);
+        assert!(!is_king_in_check_state(&board, WHITE));
+        assert!(!is_king_in_check_state(&board, BLACK));
+    }
+
     #[test]
     fn test_black_pawn_check() {
         let mut chess = Chess::default();
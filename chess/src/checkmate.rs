@@ -4,7 +4,6 @@ use crate::{
         get_squares_with_black_pieces, get_squares_with_white_pieces, rank::Rank, square::Square,
     },
     game_state::GameState,
-    make_chess_move::king_is_not_in_check_after_move,
     moves::{bishop::BISHOP_MOVES, knight::KNIGHT_MOVES, rook::ROOK_MOVES},
     piece::{Piece, PieceColor},
 };
@@ -18,22 +17,17 @@ type SquareCoordinates = (usize, usize);
 pub type MoveFromCoordinates = (SquareCoordinates, SquareCoordinates, (usize, usize));
 
 pub fn is_checkmate_position(chess: &mut Chess) -> bool {
-    let moves: Vec<MoveFromCoordinates> = if chess.clone().get_white_player().in_check() {
-        possible_legal_moves(chess, WHITE)
-    } else if chess.clone().get_black_player().in_check() {
-        possible_legal_moves(chess, BLACK)
+    let color = if chess.players.0.in_check() {
+        WHITE
+    } else if chess.players.1.in_check() {
+        BLACK
     } else {
         return false;
     };
 
-    let is_checkmate = moves.iter().all(|possible_move| {
-        let start_sq = &chess.board[possible_move.0 .0][possible_move.0 .1];
-        let end_sq = &chess.board[possible_move.1 .0][possible_move.1 .1];
-        !king_is_not_in_check_after_move(&*chess, start_sq, end_sq)
-    });
-
+    let is_checkmate = is_checkmated(chess, color);
     if is_checkmate {
-        chess.gamestate = if chess.clone().get_white_player().in_check() {
+        chess.gamestate = if color == WHITE {
             chess.players.1.victory = true;
             GameState::BlackVictory
         } else {
@@ -44,6 +38,31 @@ pub fn is_checkmate_position(chess: &mut Chess) -> bool {
 
     is_checkmate
 }
+
+/**
+ * Whether `color` is in check and every move it has is forbidden by the rules of the game
+ */
+#[must_use]
+pub fn is_checkmated(chess: &Chess, color: PieceColor) -> bool {
+    let in_check = match color {
+        WHITE => chess.players.0.in_check(),
+        BLACK => chess.players.1.in_check(),
+        PieceColor::None => false,
+    };
+    if !in_check {
+        return false;
+    }
+
+    let rules = chess.variant.rules();
+    possible_legal_moves(chess, color)
+        .iter()
+        .all(|possible_move| {
+            let start_sq = &chess.board[possible_move.0 .0][possible_move.0 .1];
+            let end_sq = &chess.board[possible_move.1 .0][possible_move.1 .1];
+            rules.is_legal_move(chess, start_sq, end_sq).is_err()
+        })
+}
+
 #[must_use]
 #[allow(clippy::missing_panics_doc)]
 pub fn possible_legal_moves(chess: &Chess, color: PieceColor) -> Vec<MoveFromCoordinates> {
@@ -79,16 +98,12 @@ pub fn pawn_possible_moves(sq: &Square) -> Vec<MoveFromCoordinates> {
     let rank = sq.rank as usize;
     let mut possible_moves: Vec<((usize, usize), (usize, usize), (usize, usize))> = Vec::new();
 
-    if rank == 0 || rank == 8 {
-        return possible_moves;
-    }
-
-    match sq.piece.color() {
-        PieceColor::White => {
+    match (sq.piece.color(), rank) {
+        (PieceColor::White, 7) | (PieceColor::Black, 0) | (PieceColor::None, _) => {}
+        (PieceColor::White, _) => {
             possible_moves = white_pawn_possible_moves_helper(file, rank);
         }
-        PieceColor::Black => possible_moves = black_pawn_possible_moves_helper(file, rank),
-        PieceColor::None => {}
+        (PieceColor::Black, _) => possible_moves = black_pawn_possible_moves_helper(file, rank),
     }
     possible_moves
 }
@@ -216,20 +231,10 @@ fn white_pawn_possible_moves_helper(
         }
         return possible_moves;
     }
-    //starting square
-    if rank == 1 {
-        possible_moves.push((
-            (file, rank),
-            (file, Rank::Third.as_usize()),
-            DEFAULT_NO_PROMOTION_TUPLE,
-        ));
-        possible_moves.push((
-            (file, rank),
-            (file, Rank::Fourth.as_usize()),
-            DEFAULT_NO_PROMOTION_TUPLE,
-        ));
-    } else {
-        possible_moves.push(((file, rank), (file, rank + 1), DEFAULT_NO_PROMOTION_TUPLE));
+    possible_moves.push(((file, rank), (file, rank + 1), DEFAULT_NO_PROMOTION_TUPLE));
+    //starting square, or the first rank in Horde
+    if rank <= Rank::Second.as_usize() {
+        possible_moves.push(((file, rank), (file, rank + 2), DEFAULT_NO_PROMOTION_TUPLE));
     }
 
     //captures to left
@@ -2,20 +2,24 @@ use std::str::FromStr;
 
 use crate::{
     chessboard::{
-        self, add_possible_moves_to_squares, file::File, rank::Rank, square::Square,
-        starting_position, ChessBoard,
+        self, add_possible_moves_to_squares, file::File, rank::Rank, square::Square, ChessBoard,
     },
     game_state::GameState,
-    make_chess_move::make_chess_move,
-    piece::{tuple_to_promoted_piece, Piece, PieceColor},
+    make_chess_move::{
+        is_game_active, legal_drops, make_chess_move, try_make_chess_move, try_make_drop, MoveError,
+    },
+    notation::{parse_drop, parse_uci},
+    piece::{promoted_piece_to_i32tuple, tuple_to_promoted_piece, Piece, PieceColor},
     pieces_eaten::PiecesEaten,
     player::Player,
+    pocket::Pocket,
+    variant::GameVariant,
 };
 
 use serde::{Deserialize, Serialize};
 
 pub type LatestMove = (Square, Square, PieceColor);
-type SquareCoordinates = (File, Rank);
+pub type SquareCoordinates = (File, Rank);
 
 /**
 * (0, x) is always None,
@@ -23,11 +27,16 @@ type SquareCoordinates = (File, Rank);
 * (2, x) is always Rook
 * (3, x) is always Knight
 * (4, x) is always Bishop
+* (5, x) is always Pawn, which is only ever dropped
+* (6, x) is always King, which only Antichess promotes to
 * (x, 0) is White
 * (x, 1) is Black
 */
 pub type PromotedPiece = (i32, i32);
 
+/**
+ * A move with the same start and end square drops the piece of its third field, as in Crazyhouse
+ */
 pub type Move = (SquareCoordinates, SquareCoordinates, PromotedPiece);
 pub type ListOfMoves = Vec<Move>;
 
@@ -47,6 +56,21 @@ pub struct Chess {
     pub fifty_move_rule: u8,
     pub list_of_moves: ListOfMoves,
     pub pieces_eaten: PiecesEaten,
+    #[serde(default)]
+    pub variant: GameVariant,
+    /**
+     * Squares of pieces promoted from pawns, which go back to being pawns when captured in Crazyhouse
+     */
+    #[serde(default)]
+    pub promoted_squares: Vec<SquareCoordinates>,
+}
+
+/**
+ * Whether `chess_move` drops a piece from a pocket instead of moving one
+ */
+#[must_use]
+pub fn is_drop(chess_move: &Move) -> bool {
+    chess_move.0 == chess_move.1
 }
 
 impl Chess {
@@ -64,23 +88,24 @@ impl Chess {
             fifty_move_rule: 0,
             list_of_moves: Vec::new(),
             pieces_eaten: PiecesEaten::new(),
+            variant: GameVariant::Standard,
+            promoted_squares: Vec::new(),
         }
     }
 
     #[must_use]
     pub fn new_starting_position() -> Self {
+        Self::with_variant(GameVariant::Standard)
+    }
+
+    /**
+     * The starting position of `variant`, with its rules applied to every move
+     */
+    #[must_use]
+    pub fn with_variant(variant: GameVariant) -> Self {
         let mut chess = Self {
-            board: chessboard::new_board(),
-            turn_number: 0,
-            latest_move: None,
-            players: (
-                Player::new(PieceColor::White),
-                Player::new(PieceColor::Black),
-            ),
-            gamestate: GameState::InProgress,
-            fifty_move_rule: 0,
-            list_of_moves: Vec::new(),
-            pieces_eaten: PiecesEaten::new(),
+            variant,
+            ..Self::new()
         };
         chess.starting_position();
         add_possible_moves_to_squares(&mut chess);
@@ -99,13 +124,17 @@ impl Chess {
     }
 
     pub fn starting_position(&mut self) {
-        self.board = chessboard::new_board();
-        self.board = starting_position();
+        self.variant.rules().starting_position(self);
         self.turn_number = 0;
         self.players.0.victory = false;
         self.players.1.victory = false;
         self.players.0.in_check = false;
         self.players.1.in_check = false;
+        self.players.0.checks = 0;
+        self.players.1.checks = 0;
+        self.players.0.pocket = Pocket::new();
+        self.players.1.pocket = Pocket::new();
+        self.promoted_squares.clear();
         self.gamestate = GameState::InProgress;
     }
 
@@ -165,6 +194,15 @@ impl Chess {
 
     pub fn make_move(&mut self, start_sq: &Square, end_sq: &Square, promoted_piece: PromotedPiece) {
         let promoted_piece = tuple_to_promoted_piece(promoted_piece);
+        if (start_sq.file, start_sq.rank) == (end_sq.file, end_sq.rank) {
+            let square = end_sq.square_name();
+            match promoted_piece.map(|piece| try_make_drop(self, piece, end_sq)) {
+                Some(Ok(())) => {}
+                Some(Err(error)) => tracing::warn!(square, %error, "ignoring an illegal drop"),
+                None => tracing::warn!(square, "ignoring a drop without a piece"),
+            }
+            return;
+        }
         make_chess_move(self, start_sq, end_sq, promoted_piece);
     }
 
@@ -175,7 +213,7 @@ impl Chess {
         possible_promoted_piece: Option<&str>,
     ) {
         if start_sq.is_empty() || end_sq.is_empty() {
-            println!("Startsq or endsq was empty");
+            tracing::warn!(start_sq, end_sq, "ignoring a move without both squares");
             return;
         }
 
@@ -208,6 +246,124 @@ impl Chess {
         make_chess_move(self, &start_sq, &end_sq, promoted_piece);
     }
 
+    #[allow(clippy::missing_errors_doc)]
+    pub fn make_move_from_uci(&mut self, uci: &str) -> Result<(), MoveError> {
+        if let Some((piece, (file, rank))) = parse_drop(uci) {
+            let end_sq = self.get_square(file, rank);
+            return try_make_drop(self, piece, &end_sq);
+        }
+        let ((start_file, start_rank), (end_file, end_rank), promoted_piece) =
+            parse_uci(uci).ok_or(MoveError::InvalidNotation)?;
+        let start_sq = self.get_square(start_file, start_rank);
+        let end_sq = self.get_square(end_file, end_rank);
+        try_make_chess_move(self, &start_sq, &end_sq, promoted_piece)
+    }
+
+    #[must_use]
+    pub const fn side_to_move(&self) -> PieceColor {
+        if self.turn_number % 2 == 0 {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        }
+    }
+
+    /**
+     * Attaches user accounts to the white and black seats
+     */
+    pub const fn seat_players(&mut self, white: Option<u64>, black: Option<u64>) {
+        self.players.0.user_id = white;
+        self.players.1.user_id = black;
+    }
+
+    /**
+     * Every legal move for the side to move including drops, empty when the game is over
+     */
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn legal_moves(&self) -> ListOfMoves {
+        if !is_game_active(self) {
+            return Vec::new();
+        }
+        let side_to_move = self.side_to_move();
+        let royal_king = self.variant.rules().has_royal_king();
+        let drops = legal_drops(self)
+            .into_iter()
+            .map(|(piece, square)| (square, square, promoted_piece_to_i32tuple(Some(piece))));
+        self.board
+            .iter()
+            .flatten()
+            .filter(|square| square.piece.color() == side_to_move)
+            .flat_map(|square| square.possible_moves.iter())
+            .filter(|possible_move| {
+                !royal_king
+                    || !self.board[possible_move.1 .0][possible_move.1 .1]
+                        .piece
+                        .is_king()
+            })
+            .map(|&(start, end, promoted_piece)| {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+                (
+                    coordinates_to_square(start),
+                    coordinates_to_square(end),
+                    (promoted_piece.0 as i32, promoted_piece.1 as i32),
+                )
+            })
+            .chain(drops)
+            .collect()
+    }
+
+    /**
+     * Ends the game in favour of the opponent of `color`, returns false if the game was already over
+     */
+    pub const fn resign(&mut self, color: PieceColor) -> bool {
+        if self.gamestate.is_finished() {
+            return false;
+        }
+        match color {
+            PieceColor::White => {
+                self.players.1.victory = true;
+                self.gamestate = GameState::BlackVictory;
+            }
+            PieceColor::Black => {
+                self.players.0.victory = true;
+                self.gamestate = GameState::WhiteVictory;
+            }
+            PieceColor::None => return false,
+        }
+        true
+    }
+
+    /**
+     * Ends the game as `color` ran out of time. It loses unless its opponent could never
+     * win, then the game is drawn. False if the game is already over.
+     */
+    pub fn flag(&mut self, color: PieceColor) -> bool {
+        if self.gamestate.is_finished() || color == PieceColor::None {
+            return false;
+        }
+        if self
+            .variant
+            .rules()
+            .has_insufficient_material(self, color.opposite())
+        {
+            self.gamestate = GameState::InsufficientMaterial;
+            return true;
+        }
+        self.resign(color)
+    }
+
+    /**
+     * Ends the game as a draw agreed by both players, false if the game is already over
+     */
+    pub const fn agree_draw(&mut self) -> bool {
+        if self.gamestate.is_finished() {
+            return false;
+        }
+        self.gamestate = GameState::Draw;
+        true
+    }
+
     pub fn print_moves(self) {
         self.list_of_moves.into_iter().for_each(|m| {
             println!("{m:?}");
@@ -215,6 +371,14 @@ impl Chess {
     }
 }
 
+#[allow(clippy::cast_possible_truncation)]
+fn coordinates_to_square((file, rank): (usize, usize)) -> SquareCoordinates {
+    (
+        File::try_from(file as u8).expect("Invalid file"),
+        Rank::try_from(rank).expect("Invalid rank"),
+    )
+}
+
 impl Default for Chess {
     fn default() -> Self {
         Self::new()
@@ -223,7 +387,7 @@ impl Default for Chess {
 
 #[cfg(test)]
 mod tests {
-    use crate::piece::Piece;
+    use crate::{board_builder::BoardBuilder, piece::Piece};
 
     use super::*;
     #[test]
@@ -231,6 +395,63 @@ mod tests {
         let _chess: Chess = Chess::new();
     }
 
+    #[test]
+    fn legal_moves_works() {
+        let mut chess = Chess::new_starting_position();
+        assert_eq!(chess.legal_moves().len(), 20);
+        chess.make_move_from_uci("e2e4").expect("legal move");
+        assert!(chess.legal_moves().iter().all(|chess_move| chess
+            .get_square(chess_move.0 .0, chess_move.0 .1)
+            .piece
+            .color()
+            == PieceColor::Black));
+        assert!(chess.resign(PieceColor::Black));
+        assert_eq!(chess.gamestate, GameState::WhiteVictory);
+        assert!(chess.legal_moves().is_empty());
+        assert!(!chess.resign(PieceColor::White));
+    }
+
+    #[test]
+    fn flagging_against_bare_material_draws() {
+        let position = |fen| {
+            BoardBuilder::from_fen(fen)
+                .and_then(BoardBuilder::build)
+                .expect("position is valid")
+        };
+        for (fen, flagged, result) in [
+            (
+                "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+                PieceColor::White,
+                GameState::InsufficientMaterial,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/3QK3 b - - 0 1",
+                PieceColor::Black,
+                GameState::WhiteVictory,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/3NK3 b - - 0 1",
+                PieceColor::Black,
+                GameState::InsufficientMaterial,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/2NNK3 b - - 0 1",
+                PieceColor::Black,
+                GameState::WhiteVictory,
+            ),
+            (
+                "4k3/4p3/8/8/8/8/8/4K3 w - - 0 1",
+                PieceColor::White,
+                GameState::BlackVictory,
+            ),
+        ] {
+            let mut chess = position(fen);
+            assert!(chess.flag(flagged));
+            assert_eq!(chess.gamestate, result, "{fen}");
+            assert!(!chess.flag(flagged));
+        }
+    }
+
     #[test]
     fn make_move_works() {
         let mut chess: Chess = Chess::new();
@@ -10,6 +10,7 @@ use crate::{
         Piece::{self, King},
         PieceColor::{self, Black, White},
     },
+    variant::{BackRank, STANDARD_BACK_RANK},
 };
 
 use self::{
@@ -74,38 +75,19 @@ const fn color_changer(color: SquareColor) -> SquareColor {
 
 #[must_use]
 pub fn starting_position() -> ChessBoard {
-    let mut board = new_board();
-    let pieces = [
-        Piece::Rook,
-        Piece::Knight,
-        Piece::Bishop,
-        Piece::Queen,
-        Piece::King,
-        Piece::Bishop,
-        Piece::Knight,
-        Piece::Rook,
-    ];
+    starting_position_with(&STANDARD_BACK_RANK)
+}
 
-    set_pieces(
-        &mut board,
-        White,
-        0,
-        &pieces
-            .iter()
-            .map(|piece_fn| piece_fn(White))
-            .collect::<Vec<_>>(),
-    );
+/**
+ * Pawns on the second and seventh ranks with `back_rank` behind them, mirrored for black
+ */
+#[must_use]
+pub fn starting_position_with(back_rank: &BackRank) -> ChessBoard {
+    let mut board = new_board();
+    set_pieces(&mut board, White, 0, back_rank);
     set_pieces(&mut board, White, 1, &[Piece::Pawn(White); 8]);
     set_pieces(&mut board, Black, 6, &[Piece::Pawn(Black); 8]);
-    set_pieces(
-        &mut board,
-        Black,
-        7,
-        &pieces
-            .iter()
-            .map(|piece_fn| piece_fn(Black))
-            .collect::<Vec<_>>(),
-    );
+    set_pieces(&mut board, Black, 7, back_rank);
     board
 }
 
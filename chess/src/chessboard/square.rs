@@ -1,13 +1,12 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    check::is_king_in_check_state,
     checkmate::{
         bishop_possible_moves, king_possible_moves, knight_possible_moves, pawn_possible_moves,
         rook_possible_moves, MoveFromCoordinates,
     },
     chess::Chess,
-    moves::move_helpers::helpers::{move_is_black_en_passant, move_is_white_en_passant},
+    moves::king::{castling_moves, move_is_castling},
     piece::{Piece, PieceColor},
 };
 
@@ -151,7 +150,14 @@ impl Square {
                     moves
                 }
             }
-            Piece::King(_) => moves = king_possible_moves(self),
+            Piece::King(_) => {
+                moves = king_possible_moves(self);
+                for castling_move in castling_moves(self, chess) {
+                    if !moves.contains(&castling_move) {
+                        moves.push(castling_move);
+                    }
+                }
+            }
         }
         // chess.print_white_board_to_terminal();
         moves
@@ -166,24 +172,20 @@ impl Square {
     }
 }
 
+/**
+ * Whether the piece on `start_sq` can move to `end_sq` under the rules of the game
+ */
 #[must_use]
 pub fn check_if_move_is_legal(chess: &Chess, start_sq: &Square, end_sq: &Square) -> bool {
-    let mut temp_board = chess.board.clone();
-    if end_sq.has_piece() && end_sq.piece.color() == start_sq.piece.color() {
-        return false;
-    }
-
-    if !start_sq.piece.piece_move(start_sq, end_sq, chess) {
-        return false;
-    };
-
-    if move_is_white_en_passant(start_sq, end_sq, chess)
-        || move_is_black_en_passant(start_sq, end_sq, chess)
+    if !move_is_castling(start_sq, end_sq, chess)
+        && ((end_sq.has_piece() && end_sq.piece.color() == start_sq.piece.color())
+            || !start_sq.piece.piece_move(start_sq, end_sq, chess))
     {
-        temp_board[end_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
+        return false;
     }
-
-    temp_board[end_sq.file as usize][end_sq.rank as usize].piece = start_sq.piece;
-    temp_board[start_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
-    !is_king_in_check_state(&temp_board, start_sq.piece.color())
+    chess
+        .variant
+        .rules()
+        .is_legal_move(chess, start_sq, end_sq)
+        .is_ok()
 }
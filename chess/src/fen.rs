@@ -0,0 +1,237 @@
+use crate::{
+    castling::CastlingSide,
+    chess::Chess,
+    chessboard::rank::Rank,
+    notation::square_to_uci,
+    piece::{Piece, PieceColor},
+    pocket::POCKET_PIECES,
+    variant::GameVariant,
+};
+
+pub const STARTING_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[must_use]
+pub const fn piece_to_fen_char(piece: Piece) -> Option<char> {
+    let piece_char = match piece {
+        Piece::None => return None,
+        Piece::Pawn(_) => 'p',
+        Piece::Knight(_) => 'n',
+        Piece::Bishop(_) => 'b',
+        Piece::Rook(_) => 'r',
+        Piece::Queen(_) => 'q',
+        Piece::King(_) => 'k',
+    };
+    match piece.color() {
+        PieceColor::White => Some(piece_char.to_ascii_uppercase()),
+        PieceColor::Black => Some(piece_char),
+        PieceColor::None => None,
+    }
+}
+
+/**
+ * The piece a FEN letter stands for, uppercase is white and lowercase is black
+ */
+#[must_use]
+pub const fn fen_char_to_piece(piece_char: char) -> Option<Piece> {
+    let color = if piece_char.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    let piece = match piece_char.to_ascii_lowercase() {
+        'p' => Piece::Pawn(color),
+        'n' => Piece::Knight(color),
+        'b' => Piece::Bishop(color),
+        'r' => Piece::Rook(color),
+        'q' => Piece::Queen(color),
+        'k' => Piece::King(color),
+        _ => return None,
+    };
+    Some(piece)
+}
+
+/**
+ * Three-check positions end with the checks each side gave, as `+white+black`.
+ * Crazyhouse boards are followed by the pockets in brackets and mark promoted pieces with `~`.
+ */
+#[must_use]
+pub fn to_fen(chess: &Chess) -> String {
+    let mut board = board_to_fen(chess);
+    if chess.variant == GameVariant::Crazyhouse {
+        board.push_str(&pockets_to_fen(chess));
+    }
+    let mut fields = vec![
+        board,
+        match chess.side_to_move() {
+            PieceColor::Black => "b".to_string(),
+            PieceColor::White | PieceColor::None => "w".to_string(),
+        },
+        castling_to_fen(chess),
+        en_passant_to_fen(chess),
+        chess.fifty_move_rule.to_string(),
+        (chess.turn_number / 2 + 1).to_string(),
+    ];
+    if chess.variant == GameVariant::ThreeCheck {
+        fields.push(format!(
+            "+{}+{}",
+            chess.players.0.checks, chess.players.1.checks
+        ));
+    }
+    fields.join(" ")
+}
+
+fn board_to_fen(chess: &Chess) -> String {
+    let ranks: Vec<String> = (0..8)
+        .rev()
+        .map(|rank| {
+            let mut row = String::new();
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                match piece_to_fen_char(chess.board[file][rank].piece) {
+                    Some(piece_char) => {
+                        if empty_squares > 0 {
+                            row.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        row.push(piece_char);
+                        let square = &chess.board[file][rank];
+                        if chess.promoted_squares.contains(&(square.file, square.rank)) {
+                            row.push('~');
+                        }
+                    }
+                    None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                row.push_str(&empty_squares.to_string());
+            }
+            row
+        })
+        .collect();
+    ranks.join("/")
+}
+
+/**
+ * The pieces in both crazyhouse pockets, white first, in brackets after the board
+ */
+fn pockets_to_fen(chess: &Chess) -> String {
+    let mut pockets = String::from("[");
+    for player in [chess.players.0, chess.players.1] {
+        for piece in POCKET_PIECES {
+            let piece = piece.with_color(player.color);
+            let piece_char = piece_to_fen_char(piece).unwrap_or_default();
+            for _ in 0..player.pocket.count(piece) {
+                pockets.push(piece_char);
+            }
+        }
+    }
+    pockets.push(']');
+    pockets
+}
+
+/**
+ * Castling is only listed while the king and rook are still on their starting squares.
+ * A Chess960 rook with another rook further out on its side is written by its file (X-FEN).
+ */
+fn castling_to_fen(chess: &Chess) -> String {
+    let piece_at = |file: usize, rank: Rank| chess.board[file][rank as usize].piece;
+    let variant = chess.variant;
+    let mut castling = String::new();
+    for (player, rank) in [
+        (&chess.players.0, Rank::First),
+        (&chess.players.1, Rank::Eighth),
+    ] {
+        let rook = Piece::Rook(player.color);
+        if piece_at(variant.king_file() as usize, rank) != Piece::King(player.color) {
+            continue;
+        }
+        for side in [CastlingSide::Kingside, CastlingSide::Queenside] {
+            let rook_file = variant.rook_file(side) as usize;
+            if !player.castling.allows(side) || piece_at(rook_file, rank) != rook {
+                continue;
+            }
+            let mut further_out = match side {
+                CastlingSide::Kingside => rook_file + 1..8,
+                CastlingSide::Queenside => 0..rook_file,
+            };
+            let letter = if further_out.any(|file| piece_at(file, rank) == rook) {
+                char::from(b'a' + u8::try_from(rook_file).expect("Invalid file"))
+            } else if side == CastlingSide::Kingside {
+                'k'
+            } else {
+                'q'
+            };
+            castling.push(if player.color == PieceColor::White {
+                letter.to_ascii_uppercase()
+            } else {
+                letter
+            });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    castling
+}
+
+fn en_passant_to_fen(chess: &Chess) -> String {
+    match &chess.latest_move {
+        Some((start_sq, end_sq, color))
+            if start_sq.piece == Piece::Pawn(*color)
+                && matches!(start_sq.rank, Rank::Second | Rank::Seventh)
+                && (start_sq.rank as u8).abs_diff(end_sq.rank as u8) == 2 =>
+        {
+            let passed_rank = Rank::try_from(u8::midpoint(start_sq.rank as u8, end_sq.rank as u8))
+                .expect("Invalid rank");
+            square_to_uci(end_sq.file, passed_rank)
+        }
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_fen_works() {
+        let chess = Chess::new_starting_position();
+        assert_eq!(to_fen(&chess), STARTING_POSITION_FEN);
+    }
+
+    #[test]
+    fn chess960_fen_works() {
+        let chess = Chess::with_variant(GameVariant::Chess960(0));
+        assert_eq!(
+            to_fen(&chess),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(
+            to_fen(&Chess::with_variant(GameVariant::Chess960(518))),
+            STARTING_POSITION_FEN
+        );
+    }
+
+    #[test]
+    fn fen_after_moves_works() {
+        let mut chess = Chess::new_starting_position();
+        chess.make_move_from_uci("e2e4").expect("legal move");
+        assert_eq!(
+            to_fen(&chess),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        chess.make_move_from_uci("c7c5").expect("legal move");
+        chess.make_move_from_uci("g1f3").expect("legal move");
+        assert_eq!(
+            to_fen(&chess),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        chess.make_move_from_uci("e8e7").ok();
+        chess.make_move_from_uci("d7d6").expect("legal move");
+        chess.make_move_from_uci("h1g1").expect("legal move");
+        assert_eq!(
+            to_fen(&chess),
+            "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKBR1 b Qkq - 1 3"
+        );
+    }
+}
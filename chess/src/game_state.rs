@@ -1,9 +1,14 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
     checkmate::possible_legal_moves,
     chess::Chess,
-    chessboard::{get_squares_with_black_pieces, get_squares_with_white_pieces, square::Square},
+    chessboard::{
+        get_squares_with_black_pieces, get_squares_with_white_pieces,
+        square::{check_if_move_is_legal, Square},
+    },
     piece::{Piece, PieceColor},
 };
 
@@ -11,11 +16,86 @@ use crate::{
 pub enum GameState {
     WhiteVictory,
     BlackVictory,
+    /**
+     * The white king reached the center in King of the Hill
+     */
+    WhiteKingOfTheHill,
+    BlackKingOfTheHill,
+    /**
+     * White gave the third check in Three-check
+     */
+    WhiteThreeCheck,
+    BlackThreeCheck,
     InsufficientMaterial,
     Stalemate,
+    /**
+     * Both players agreed to a draw
+     */
+    Draw,
     InProgress,
 }
 
+impl GameState {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::WhiteVictory => "WhiteVictory",
+            Self::BlackVictory => "BlackVictory",
+            Self::WhiteKingOfTheHill => "WhiteKingOfTheHill",
+            Self::BlackKingOfTheHill => "BlackKingOfTheHill",
+            Self::WhiteThreeCheck => "WhiteThreeCheck",
+            Self::BlackThreeCheck => "BlackThreeCheck",
+            Self::InsufficientMaterial => "InsufficientMaterial",
+            Self::Stalemate => "Stalemate",
+            Self::Draw => "Draw",
+            Self::InProgress => "InProgress",
+        }
+    }
+
+    #[must_use]
+    pub const fn is_finished(self) -> bool {
+        !matches!(self, Self::InProgress)
+    }
+
+    /**
+     * The color that won, None for a draw or a game in progress
+     */
+    #[must_use]
+    pub const fn winner(self) -> PieceColor {
+        match self {
+            Self::WhiteVictory | Self::WhiteKingOfTheHill | Self::WhiteThreeCheck => {
+                PieceColor::White
+            }
+            Self::BlackVictory | Self::BlackKingOfTheHill | Self::BlackThreeCheck => {
+                PieceColor::Black
+            }
+            Self::InsufficientMaterial | Self::Stalemate | Self::Draw | Self::InProgress => {
+                PieceColor::None
+            }
+        }
+    }
+}
+
+impl FromStr for GameState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "WhiteVictory" => Ok(Self::WhiteVictory),
+            "BlackVictory" => Ok(Self::BlackVictory),
+            "WhiteKingOfTheHill" => Ok(Self::WhiteKingOfTheHill),
+            "BlackKingOfTheHill" => Ok(Self::BlackKingOfTheHill),
+            "WhiteThreeCheck" => Ok(Self::WhiteThreeCheck),
+            "BlackThreeCheck" => Ok(Self::BlackThreeCheck),
+            "InsufficientMaterial" => Ok(Self::InsufficientMaterial),
+            "Stalemate" => Ok(Self::Stalemate),
+            "Draw" => Ok(Self::Draw),
+            "InProgress" => Ok(Self::InProgress),
+            _ => Err(format!("Invalid game state: {s}")),
+        }
+    }
+}
+
 #[must_use]
 pub fn insufficient_material(chess: &Chess) -> bool {
     let (white_knights, white_bishops) = count_pieces(get_squares_with_white_pieces(&chess.board));
@@ -31,9 +111,36 @@ pub fn insufficient_material(chess: &Chess) -> bool {
     )
 }
 
+/**
+ * Whether `color` has nothing but its king and at most one knight or bishop,
+ * which can never checkmate
+ */
+#[must_use]
+pub fn insufficient_material_of(chess: &Chess, color: PieceColor) -> bool {
+    let mut minor_pieces = 0;
+    for square in chess.board.iter().flatten() {
+        match square.piece {
+            piece if piece.color() != color => {}
+            Piece::King(_) => {}
+            Piece::Knight(_) | Piece::Bishop(_) => minor_pieces += 1,
+            _ => return false,
+        }
+    }
+    minor_pieces <= 1
+}
+
+/**
+ * Whether `moving_color` has no legal move on the board, whether or not it is in check
+ */
 #[must_use]
 pub fn stalemate(chess: &Chess, moving_color: PieceColor) -> bool {
-    possible_legal_moves(chess, moving_color).is_empty()
+    possible_legal_moves(chess, moving_color)
+        .iter()
+        .all(|possible_move| {
+            let start_sq = &chess.board[possible_move.0 .0][possible_move.0 .1];
+            let end_sq = &chess.board[possible_move.1 .0][possible_move.1 .1];
+            !check_if_move_is_legal(chess, start_sq, end_sq)
+        })
 }
 
 fn count_pieces(squares: Vec<&Square>) -> (u8, u8) {
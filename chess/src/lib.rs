@@ -1,13 +1,19 @@
+pub mod board_builder;
 pub mod castling;
 pub mod check;
 pub mod checkmate;
 pub mod chess;
 pub mod chessboard;
+pub mod fen;
 pub mod game_state;
 pub mod make_chess_move;
 pub mod moves;
+pub mod notation;
+pub mod pgn;
 pub mod piece;
 pub mod pieces_eaten;
 pub mod player;
+pub mod pocket;
+pub mod variant;
 // Re-export the main struct for easier access
 pub use chess::Chess;
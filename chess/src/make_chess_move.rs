@@ -1,86 +1,256 @@
 use crate::{
-    check::is_king_in_check_state,
-    checkmate::{self},
-    chess::Chess,
-    chessboard::{add_possible_moves_to_squares, file::File, rank::Rank, square::Square},
-    game_state::{insufficient_material, stalemate, GameState},
+    castling::CastlingSide,
+    check::{is_king_in_check_state, kings_are_adjacent},
+    chess::{Chess, SquareCoordinates},
+    chessboard::{add_possible_moves_to_squares, rank::Rank, square::Square},
+    game_state::GameState,
     moves::{
-        king::move_is_castling,
+        king::{castle, castling_side, move_is_castling},
         move_helpers::helpers::{move_is_black_en_passant, move_is_white_en_passant},
         pawn::promote,
     },
     piece::{promoted_piece_to_i32tuple, Piece, PieceColor},
 };
 
+/**
+ * Reasons a move can be rejected by `try_make_chess_move`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    InvalidNotation,
+    GameOver,
+    EmptySquare,
+    NotYourTurn,
+    IllegalMove,
+    KingInCheck,
+    InvalidPromotion,
+    InvalidDrop,
+    MustCapture,
+}
+
+impl MoveError {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidNotation => "invalid_notation",
+            Self::GameOver => "game_over",
+            Self::EmptySquare => "empty_square",
+            Self::NotYourTurn => "not_your_turn",
+            Self::IllegalMove => "illegal_move",
+            Self::KingInCheck => "king_in_check",
+            Self::InvalidPromotion => "invalid_promotion",
+            Self::InvalidDrop => "invalid_drop",
+            Self::MustCapture => "must_capture",
+        }
+    }
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Self::InvalidNotation => "move notation could not be parsed",
+            Self::GameOver => "game is already over",
+            Self::EmptySquare => "there is no piece on the start square",
+            Self::NotYourTurn => "it is not this color's turn to move",
+            Self::IllegalMove => "piece cannot move to that square",
+            Self::KingInCheck => "move would leave the king in check",
+            Self::InvalidPromotion => "pawn promotion needs a queen, rook, bishop or knight",
+            Self::InvalidDrop => "that piece is not in hand or cannot be dropped there",
+            Self::MustCapture => "a capture is available and has to be played",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
 pub fn make_chess_move(
     chess: &mut Chess,
     start_sq: &Square,
     end_sq: &Square,
     promoted_piece: Option<Piece>,
-) {
+) -> bool {
+    try_make_chess_move(chess, start_sq, end_sq, promoted_piece).is_ok()
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub fn try_make_chess_move(
+    chess: &mut Chess,
+    start_sq: &Square,
+    end_sq: &Square,
+    promoted_piece: Option<Piece>,
+) -> Result<(), MoveError> {
     let moving_piece_color = start_sq.piece.color();
-    let opposite_color = moving_piece_color.opposite();
+    let side_to_move = moving_piece_color.opposite();
+
+    validate_move(chess, start_sq, end_sq, moving_piece_color, promoted_piece)?;
+
+    let captured_piece = captured_piece(chess, start_sq, end_sq);
+    let castled = handle_special_moves(chess, start_sq, end_sq, promoted_piece);
+    update_board(chess, start_sq, end_sq, promoted_piece, castled);
+    let rules = chess.variant.rules();
+    if let Some(captured_piece) = captured_piece {
+        rules.after_capture(chess, end_sq, captured_piece);
+    }
+    rules.after_move(chess, start_sq, end_sq, promoted_piece);
+    add_possible_moves_to_squares(chess);
+    handle_game_state(chess, side_to_move);
+    Ok(())
+}
+
+/**
+ * Drops `piece` from the pocket of the side to move onto `end_sq`
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn try_make_drop(chess: &mut Chess, piece: Piece, end_sq: &Square) -> Result<(), MoveError> {
+    validate_drop(chess, piece, end_sq)?;
 
-    if !is_move_valid(chess, start_sq, end_sq, moving_piece_color, promoted_piece) {
-        return;
+    let color = chess.side_to_move();
+    let piece = piece.with_color(color);
+    match color {
+        PieceColor::White => chess.players.0.pocket.take(piece),
+        PieceColor::Black => chess.players.1.pocket.take(piece),
+        PieceColor::None => false,
+    };
+    chess.board[end_sq.file as usize][end_sq.rank as usize].piece = piece;
+    if piece.is_pawn() {
+        chess.fifty_move_rule = 0;
+    } else {
+        chess.fifty_move_rule += 1;
     }
+    chess.latest_move = Some((end_sq.clone(), end_sq.clone(), color));
+    chess.turn_number += 1;
+    let square = (end_sq.file, end_sq.rank);
+    chess
+        .list_of_moves
+        .push((square, square, promoted_piece_to_i32tuple(Some(piece))));
 
-    handle_special_moves(chess, start_sq, end_sq, promoted_piece);
-    update_board(chess, start_sq, end_sq, promoted_piece);
     add_possible_moves_to_squares(chess);
-    handle_game_state(chess, opposite_color);
+    handle_game_state(chess, color.opposite());
+    Ok(())
+}
+
+/**
+ * Whether the side to move has `piece` in its pocket and may drop it on `end_sq`.
+ * Pawns cannot be dropped on the first or eighth rank.
+ */
+#[allow(clippy::missing_errors_doc)]
+pub fn validate_drop(chess: &Chess, piece: Piece, end_sq: &Square) -> Result<(), MoveError> {
+    if !is_game_active(chess) {
+        return Err(MoveError::GameOver);
+    }
+
+    let color = chess.side_to_move();
+    let pocket = match color {
+        PieceColor::White => chess.players.0.pocket,
+        PieceColor::Black => chess.players.1.pocket,
+        PieceColor::None => return Err(MoveError::NotYourTurn),
+    };
+    if pocket.count(piece) == 0
+        || end_sq.has_piece()
+        || (piece.is_pawn() && matches!(end_sq.rank, Rank::First | Rank::Eighth))
+    {
+        return Err(MoveError::InvalidDrop);
+    }
+
+    let mut temp_board = chess.board.clone();
+    temp_board[end_sq.file as usize][end_sq.rank as usize].piece = piece.with_color(color);
+    if chess.variant.rules().is_in_check(&temp_board, color) {
+        Err(MoveError::KingInCheck)
+    } else {
+        Ok(())
+    }
+}
+
+/**
+ * Every piece the side to move may drop and where, colored as they would stand on the board
+ */
+#[must_use]
+pub fn legal_drops(chess: &Chess) -> Vec<(Piece, SquareCoordinates)> {
+    let color = chess.side_to_move();
+    let pocket = match color {
+        PieceColor::White => chess.players.0.pocket,
+        PieceColor::Black => chess.players.1.pocket,
+        PieceColor::None => return Vec::new(),
+    };
+    pocket
+        .kinds()
+        .into_iter()
+        .flat_map(|piece| {
+            chess
+                .board
+                .iter()
+                .flatten()
+                .filter(move |square| validate_drop(chess, piece, square).is_ok())
+                .map(move |square| (piece.with_color(color), (square.file, square.rank)))
+        })
+        .collect()
 }
 
-fn is_move_valid(
+fn validate_move(
     chess: &Chess,
     start_sq: &Square,
     end_sq: &Square,
     moving_piece_color: PieceColor,
     promoted_piece: Option<Piece>,
-) -> bool {
-    if !is_game_active(chess) || !is_correct_turn(chess, moving_piece_color) {
-        return false;
+) -> Result<(), MoveError> {
+    if !is_game_active(chess) {
+        return Err(MoveError::GameOver);
     }
 
-    if end_sq.piece == Piece::King(PieceColor::White)
-        || end_sq.piece == Piece::King(PieceColor::Black)
-    {
-        return false;
+    if start_sq.is_empty() {
+        return Err(MoveError::EmptySquare);
+    }
+
+    if !is_correct_turn(chess, moving_piece_color) {
+        return Err(MoveError::NotYourTurn);
+    }
+
+    let rules = chess.variant.rules();
+
+    // In Chess960 the king castles by moving onto its own rook
+    if move_is_castling(start_sq, end_sq, chess) {
+        return rules.is_legal_move(chess, start_sq, end_sq);
+    }
+
+    if rules.has_royal_king() && end_sq.piece.is_king() {
+        return Err(MoveError::IllegalMove);
     }
 
     if end_sq.has_piece() && end_sq.piece.color() == moving_piece_color {
-        return false;
+        return Err(MoveError::IllegalMove);
     }
 
     if !start_sq.piece.piece_move(start_sq, end_sq, chess) {
-        return false;
+        return Err(MoveError::IllegalMove);
     }
 
-    if is_king_in_check(chess, moving_piece_color)
-        && !king_is_not_in_check_after_move(chess, start_sq, end_sq)
-    {
-        return false;
+    if is_king_in_check(chess, moving_piece_color) {
+        rules.is_legal_move(chess, start_sq, end_sq)?;
     }
 
     if ((start_sq.piece == Piece::Pawn(PieceColor::White) && end_sq.rank == Rank::Eighth)
         || (start_sq.piece == Piece::Pawn(PieceColor::Black) && end_sq.rank == Rank::First))
-        && (promoted_piece.is_none()
-            || promoted_piece == Some(Piece::King(PieceColor::White))
-            || promoted_piece == Some(Piece::King(PieceColor::Black))
-            || promoted_piece == Some(Piece::Pawn(PieceColor::White))
-            || promoted_piece == Some(Piece::Pawn(PieceColor::Black)))
+        && !promoted_piece.is_some_and(|piece| {
+            rules
+                .promotion_pieces()
+                .iter()
+                .any(|option| option.is_same_kind(piece))
+        })
     {
-        return false;
+        return Err(MoveError::InvalidPromotion);
     }
-    king_is_not_in_check_after_move(chess, start_sq, end_sq)
+
+    rules.is_legal_move(chess, start_sq, end_sq)
 }
 
-fn is_game_active(chess: &Chess) -> bool {
+#[must_use]
+pub fn is_game_active(chess: &Chess) -> bool {
     chess.gamestate == GameState::InProgress
         && !chess.players.0.victory
         && !chess.players.1.victory
         && chess.fifty_move_rule < 50
-        && !insufficient_material(chess)
+        && !chess.variant.rules().is_insufficient_material(chess)
 }
 
 fn is_correct_turn(chess: &Chess, moving_piece_color: PieceColor) -> bool {
@@ -96,12 +266,31 @@ const fn is_king_in_check(chess: &Chess, color: PieceColor) -> bool {
     }
 }
 
+/**
+ * The opposing piece the move takes, including a pawn taken en passant
+ */
+fn captured_piece(chess: &Chess, start_sq: &Square, end_sq: &Square) -> Option<Piece> {
+    let opposite_color = start_sq.piece.color().opposite();
+    if end_sq.piece.color() == opposite_color {
+        Some(end_sq.piece)
+    } else if move_is_white_en_passant(start_sq, end_sq, chess)
+        || move_is_black_en_passant(start_sq, end_sq, chess)
+    {
+        Some(Piece::Pawn(opposite_color))
+    } else {
+        None
+    }
+}
+
+/**
+ * Returns true when the move was castling, which moves both the king and the rook
+ */
 fn handle_special_moves(
     chess: &mut Chess,
     start_sq: &Square,
     end_sq: &Square,
     promoted_piece: Option<Piece>,
-) {
+) -> bool {
     if let Some(piece) = is_promotion(
         // chess,
         start_sq,
@@ -109,10 +298,10 @@ fn handle_special_moves(
         promoted_piece,
     ) {
         handle_promotion(chess, start_sq, end_sq, piece);
-        return;
+        return false;
     }
     handle_en_passant(chess, start_sq, end_sq);
-    handle_castling(chess, start_sq, end_sq);
+    handle_castling(chess, start_sq, end_sq)
 }
 
 fn is_promotion(
@@ -131,7 +320,7 @@ fn is_promotion(
 
 fn handle_promotion(chess: &mut Chess, start_sq: &Square, end_sq: &Square, promoted_piece: Piece) {
     if let Some(piece) = match promote(start_sq, end_sq, chess, promoted_piece) {
-        Some(Piece::King(_) | Piece::Pawn(_)) | None => None,
+        Some(Piece::Pawn(_)) | None => None,
         Some(promoted_piece) => Some(promoted_piece),
     } {
         chess.board[end_sq.file as usize][end_sq.rank as usize].piece = piece;
@@ -147,42 +336,22 @@ fn handle_en_passant(chess: &mut Chess, start_sq: &Square, end_sq: &Square) {
     }
 }
 
-fn handle_castling(chess: &mut Chess, start_sq: &Square, end_sq: &Square) {
-    if move_is_castling(start_sq, end_sq, chess) {
-        match (start_sq.rank, end_sq.file) {
-            (Rank::First, File::G) => {
-                chess.board[File::H as usize][Rank::First as usize].piece = Piece::None;
-                chess.board[File::F as usize][Rank::First as usize].piece =
-                    Piece::Rook(PieceColor::White);
-                chess.players.0.castling.kingside = false;
-                chess.players.0.castling.kingside = false;
-            }
-            (Rank::First, File::C) => {
-                chess.board[File::A as usize][Rank::First as usize].piece = Piece::None;
-                chess.board[File::D as usize][Rank::First as usize].piece =
-                    Piece::Rook(PieceColor::White);
-                chess.players.0.castling.kingside = false;
-                chess.players.0.castling.queenside = false;
-            }
-            (Rank::Eighth, File::G) => {
-                chess.board[File::H as usize][Rank::Eighth as usize].piece = Piece::None;
-                chess.board[File::F as usize][Rank::Eighth as usize].piece =
-                    Piece::Rook(PieceColor::Black);
-                chess.players.1.castling.kingside = false;
-                chess.players.1.castling.queenside = false;
-            }
-            (Rank::Eighth, File::C) => {
-                chess.board[File::A as usize][Rank::Eighth as usize].piece = Piece::None;
-                chess.board[File::D as usize][Rank::Eighth as usize].piece =
-                    Piece::Rook(PieceColor::Black);
-                chess.players.1.castling.kingside = false;
-                chess.players.1.castling.queenside = false;
-            }
-            _ => panic!("Trying to castle with wrong start and end square"),
+fn handle_castling(chess: &mut Chess, start_sq: &Square, end_sq: &Square) -> bool {
+    if let Some(side) = castling_side(start_sq, end_sq, chess) {
+        let color = start_sq.piece.color();
+        let rook_file = chess.variant.rook_file(side);
+        castle(&mut chess.board, color, side, start_sq.file, rook_file);
+        match color {
+            PieceColor::White => chess.players.0.castle(),
+            PieceColor::Black => chess.players.1.castle(),
+            PieceColor::None => {}
         }
-    } else if start_sq.piece.is_king() || start_sq.piece.is_rook() {
+        return true;
+    }
+    if start_sq.piece.is_king() || start_sq.piece.is_rook() {
         remove_castling(chess, start_sq);
     }
+    false
 }
 
 fn update_board(
@@ -190,9 +359,12 @@ fn update_board(
     start_sq: &Square,
     end_sq: &Square,
     promoted_piece: Option<Piece>,
+    castled: bool,
 ) {
-    // If the end square has a piece, it's being captured
-    if end_sq.has_piece() {
+    // If the end square has a piece, it's being captured, unless it is the castling rook
+    if castled {
+        chess.fifty_move_rule += 1;
+    } else if end_sq.has_piece() {
         chess.pieces_eaten.add_piece(end_sq.piece);
         chess.fifty_move_rule = 0;
     } else if start_sq.piece.is_pawn() {
@@ -212,10 +384,12 @@ fn update_board(
         chess.board[end_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
     }
 
-    if promoted_piece.is_none() {
-        chess.board[end_sq.file as usize][end_sq.rank as usize].piece = start_sq.piece;
+    if !castled {
+        if promoted_piece.is_none() {
+            chess.board[end_sq.file as usize][end_sq.rank as usize].piece = start_sq.piece;
+        }
+        chess.board[start_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
     }
-    chess.board[start_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
 
     chess.latest_move = Some((start_sq.clone(), end_sq.clone(), start_sq.piece.color()));
     chess.turn_number += 1;
@@ -229,35 +403,45 @@ fn update_board(
     ));
 }
 
-fn handle_game_state(chess: &mut Chess, opposite_color: PieceColor) {
+fn handle_game_state(chess: &mut Chess, side_to_move: PieceColor) {
     update_check_status(chess);
-    check_for_victory(chess);
-    check_for_stalemate(chess, opposite_color);
+    count_checks(chess, side_to_move);
+    apply_outcome(chess, side_to_move);
 }
 
-fn check_for_victory(chess: &mut Chess) {
-    if chess.players.0.in_check && checkmate::is_checkmate_position(chess) {
-        chess.players.1.victory = true;
-        chess.gamestate = GameState::BlackVictory;
-    } else if chess.players.1.in_check && checkmate::is_checkmate_position(chess) {
-        chess.players.0.victory = true;
-        chess.gamestate = GameState::WhiteVictory;
+/**
+ * Ends the game if the rules of its variant say it is over with `side_to_move` to play
+ */
+pub(crate) fn apply_outcome(chess: &mut Chess, side_to_move: PieceColor) {
+    if let Some(gamestate) = chess.variant.rules().outcome(chess, side_to_move) {
+        match gamestate.winner() {
+            PieceColor::White => chess.players.0.victory = true,
+            PieceColor::Black => chess.players.1.victory = true,
+            PieceColor::None => {}
+        }
+        chess.gamestate = gamestate;
     }
 }
 
-fn check_for_stalemate(chess: &mut Chess, opposite_color: PieceColor) {
-    if chess.fifty_move_rule >= 50 || stalemate(chess, opposite_color) {
-        chess.gamestate = GameState::Stalemate;
+const fn count_checks(chess: &mut Chess, side_to_move: PieceColor) {
+    match side_to_move {
+        PieceColor::White if chess.players.0.in_check => chess.players.1.checks += 1,
+        PieceColor::Black if chess.players.1.in_check => chess.players.0.checks += 1,
+        _ => {}
     }
 }
 
-fn update_check_status(chess: &mut Chess) {
-    chess.players.0.in_check = is_king_in_check_state(&chess.board, PieceColor::White);
-    chess.players.1.in_check = is_king_in_check_state(&chess.board, PieceColor::Black);
+pub(crate) fn update_check_status(chess: &mut Chess) {
+    let rules = chess.variant.rules();
+    chess.players.0.in_check = rules.is_in_check(&chess.board, PieceColor::White);
+    chess.players.1.in_check = rules.is_in_check(&chess.board, PieceColor::Black);
 }
 
 #[must_use]
 pub fn king_is_not_in_check_after_move(chess: &Chess, start_sq: &Square, end_sq: &Square) -> bool {
+    if move_is_castling(start_sq, end_sq, chess) {
+        return true;
+    }
     let mut temp_board = chess.board.clone();
     if end_sq.has_piece() && end_sq.piece.color() == start_sq.piece.color() {
         return false;
@@ -265,7 +449,7 @@ pub fn king_is_not_in_check_after_move(chess: &Chess, start_sq: &Square, end_sq:
 
     if !start_sq.piece.piece_move(start_sq, end_sq, chess) {
         return false;
-    };
+    }
 
     if move_is_white_en_passant(start_sq, end_sq, chess)
         || move_is_black_en_passant(start_sq, end_sq, chess)
@@ -275,24 +459,26 @@ pub fn king_is_not_in_check_after_move(chess: &Chess, start_sq: &Square, end_sq:
 
     temp_board[end_sq.file as usize][end_sq.rank as usize].piece = start_sq.piece;
     temp_board[start_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
-    !is_king_in_check_state(&temp_board, start_sq.piece.color())
+    !is_king_in_check_state(&temp_board, start_sq.piece.color()) && !kings_are_adjacent(&temp_board)
 }
 
 fn remove_castling(chess: &mut Chess, start_sq: &Square) {
+    let queenside_rook = chess.variant.rook_file(CastlingSide::Queenside);
+    let kingside_rook = chess.variant.rook_file(CastlingSide::Kingside);
     match start_sq.piece {
         Piece::King(PieceColor::White) => chess.players.0.castle(),
         Piece::King(PieceColor::Black) => chess.players.1.castle(),
         Piece::Rook(PieceColor::White) => {
-            if start_sq.file == File::A && start_sq.rank == Rank::First {
+            if start_sq.file == queenside_rook && start_sq.rank == Rank::First {
                 chess.players.0.no_queenside_castling();
-            } else if start_sq.file == File::H && start_sq.rank == Rank::First {
+            } else if start_sq.file == kingside_rook && start_sq.rank == Rank::First {
                 chess.players.0.no_kingside_castling();
             }
         }
         Piece::Rook(PieceColor::Black) => {
-            if start_sq.file == File::A && start_sq.rank == Rank::Eighth {
+            if start_sq.file == queenside_rook && start_sq.rank == Rank::Eighth {
                 chess.players.1.no_queenside_castling();
-            } else if start_sq.file == File::H && start_sq.rank == Rank::Eighth {
+            } else if start_sq.file == kingside_rook && start_sq.rank == Rank::Eighth {
                 chess.players.1.no_kingside_castling();
             }
         }
@@ -1,10 +1,9 @@
 use crate::{
-    check::is_king_in_check_state,
+    castling::CastlingSide,
+    check::{is_king_in_check_state, kings_are_adjacent},
+    checkmate::MoveFromCoordinates,
     chess::Chess,
-    chessboard::{
-        file::File, get_adjacent_squares, get_black_king, get_white_king, rank::Rank,
-        square::Square, ChessBoard,
-    },
+    chessboard::{file::File, rank::Rank, square::Square, ChessBoard},
     piece::{Piece, PieceColor},
 };
 
@@ -12,9 +11,7 @@ use super::move_helpers::helpers::{is_diagonal, is_horizontal, is_vertical};
 
 #[must_use]
 pub fn move_piece(start_sq: &Square, end_sq: &Square, chess: &Chess) -> bool {
-    if square_is_bordered_by_other_king(&chess.board, start_sq, end_sq) {
-        false
-    } else if move_is_castling(start_sq, end_sq, chess) {
+    if move_is_castling(start_sq, end_sq, chess) {
         true
     } else if is_vertical(start_sq, end_sq) || is_horizontal(start_sq, end_sq) {
         (start_sq.rank as u8).abs_diff(end_sq.rank as u8) == 1
@@ -29,91 +26,125 @@ pub fn move_piece(start_sq: &Square, end_sq: &Square, chess: &Chess) -> bool {
 
 #[must_use]
 pub fn move_is_castling(start_sq: &Square, end_sq: &Square, chess: &Chess) -> bool {
-    if !(start_sq.piece == Piece::King(PieceColor::White))
-        && !(start_sq.piece == Piece::King(PieceColor::Black))
-    {
-        return false;
+    castling_side(start_sq, end_sq, chess).is_some()
+}
+
+/**
+ * The side a legal castling move castles to, None for every other move.
+ * The king moves to the c or g file, or onto its own rook in Chess960.
+ */
+#[must_use]
+pub fn castling_side(start_sq: &Square, end_sq: &Square, chess: &Chess) -> Option<CastlingSide> {
+    let (color, player) = match start_sq.piece {
+        Piece::King(PieceColor::White) => (PieceColor::White, chess.players.0),
+        Piece::King(PieceColor::Black) => (PieceColor::Black, chess.players.1),
+        _ => return None,
     };
+    let rank = home_rank(color);
+    let variant = chess.variant;
+    if start_sq.rank != rank || end_sq.rank != rank || start_sq.file != variant.king_file() {
+        return None;
+    }
 
-    if !(start_sq.file == File::E
-        && (start_sq.file as u8).abs_diff(end_sq.file as u8) == 2
-        && (end_sq.file == File::G || end_sq.file == File::C)
-        && (start_sq.rank == Rank::First || start_sq.rank == Rank::Eighth)
-        && start_sq.rank == end_sq.rank)
+    let side = [CastlingSide::Kingside, CastlingSide::Queenside]
+        .into_iter()
+        .find(|&side| {
+            let king_target = if variant.is_chess960() {
+                variant.rook_file(side)
+            } else {
+                side.king_destination()
+            };
+            end_sq.file == king_target
+        })?;
+    let rook_file = variant.rook_file(side);
+    let rank = rank as usize;
+    if !player.castling.allows(side)
+        || chess.board[rook_file as usize][rank].piece != Piece::Rook(color)
     {
-        return false;
+        return None;
     }
 
-    let players = chess.players;
-    match (start_sq.rank, end_sq.file) {
-        (Rank::First, File::G) => {
-            chess.board[5][0].piece == Piece::None
-                && chess.board[6][0].piece == Piece::None
-                && players.0.castling.kingside
-                && not_checked_while_castling(5, 0, &chess.board)
-        }
-        (Rank::First, File::C) => {
-            chess.board[1][0].piece == Piece::None
-                && chess.board[2][0].piece == Piece::None
-                && chess.board[3][0].piece == Piece::None
-                && players.0.castling.queenside
-                && not_checked_while_castling(3, 0, &chess.board)
-        }
-        (Rank::Eighth, File::G) => {
-            chess.board[5][7].piece == Piece::None
-                && chess.board[6][7].piece == Piece::None
-                && players.1.castling.kingside
-                && not_checked_while_castling(5, 7, &chess.board)
-        }
-        (Rank::Eighth, File::C) => {
-            chess.board[1][7].piece == Piece::None
-                && chess.board[2][7].piece == Piece::None
-                && chess.board[3][7].piece == Piece::None
-                && players.1.castling.queenside
-                && not_checked_while_castling(3, 7, &chess.board)
-        }
-        _ => false,
+    let king_from = start_sq.file as usize;
+    let king_to = side.king_destination() as usize;
+    let rook_from = rook_file as usize;
+    let rook_to = side.rook_destination() as usize;
+    let leftmost = king_from.min(king_to).min(rook_from).min(rook_to);
+    let rightmost = king_from.max(king_to).max(rook_from).max(rook_to);
+    let path_is_empty = (leftmost..=rightmost).all(|file| {
+        file == king_from || file == rook_from || chess.board[file][rank].piece == Piece::None
+    });
+    if !path_is_empty {
+        return None;
     }
+
+    // The king may not castle out of, through or into check
+    let king_is_safe = (king_from.min(king_to)..=king_from.max(king_to)).all(|file| {
+        let mut temp_board = chess.board.clone();
+        temp_board[king_from][rank].piece = Piece::None;
+        temp_board[file][rank].piece = Piece::King(color);
+        !is_king_in_check_state(&temp_board, color) && !kings_are_adjacent(&temp_board)
+    });
+    let mut castled = chess.board.clone();
+    castle(&mut castled, color, side, start_sq.file, rook_file);
+    (king_is_safe && !is_king_in_check_state(&castled, color)).then_some(side)
 }
 
-fn not_checked_while_castling(
-    in_between_king_sq_file: usize,
-    in_between_king_sq_rank: usize,
-    chess_board: &ChessBoard,
-) -> bool {
-    let color = if in_between_king_sq_rank == 0 {
-        PieceColor::White
-    } else {
-        PieceColor::Black
-    };
-    let mut temp_board = chess_board.clone();
-    temp_board[4][in_between_king_sq_rank].piece = Piece::None;
-    temp_board[in_between_king_sq_file][in_between_king_sq_rank].piece = Piece::King(color);
-    !is_king_in_check_state(&temp_board, color)
+/**
+ * Moves the king from `king_from` and the rook from `rook_from` to their squares after castling
+ */
+pub const fn castle(
+    board: &mut ChessBoard,
+    color: PieceColor,
+    side: CastlingSide,
+    king_from: File,
+    rook_from: File,
+) {
+    let rank = home_rank(color) as usize;
+    board[king_from as usize][rank].piece = Piece::None;
+    board[rook_from as usize][rank].piece = Piece::None;
+    board[side.king_destination() as usize][rank].piece = Piece::King(color);
+    board[side.rook_destination() as usize][rank].piece = Piece::Rook(color);
 }
 
-fn square_is_bordered_by_other_king(
-    chessboard: &ChessBoard,
-    start_sq: &Square,
-    end_sq: &Square,
-) -> bool {
-    let king_color = start_sq.piece.color();
-    let enemy_king_sq = match king_color {
-        PieceColor::White => get_black_king(chessboard).expect("Black king not found"),
-        PieceColor::Black => get_white_king(chessboard).expect("White king not found"),
-        PieceColor::None => panic!("King color is neither white nor black"),
-    };
+const fn home_rank(color: PieceColor) -> Rank {
+    match color {
+        PieceColor::Black => Rank::Eighth,
+        PieceColor::White | PieceColor::None => Rank::First,
+    }
+}
 
-    let bordered_squares = get_adjacent_squares(end_sq, chessboard);
-    bordered_squares.contains(enemy_king_sq)
+/**
+ * Squares a king on `sq` could castle to, written the way its variant writes castling.
+ * Whether castling is legal is checked like any other move.
+ */
+#[must_use]
+pub fn castling_moves(sq: &Square, chess: &Chess) -> Vec<MoveFromCoordinates> {
+    if !sq.piece.is_king() || !chess.variant.is_chess960() {
+        return Vec::new();
+    }
+    [CastlingSide::Kingside, CastlingSide::Queenside]
+        .into_iter()
+        .map(|side| {
+            (
+                (sq.file as usize, sq.rank as usize),
+                (chess.variant.rook_file(side) as usize, sq.rank as usize),
+                (0, 0),
+            )
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         chess::Chess,
+        chessboard::{add_possible_moves_to_squares, file::File, rank::Rank},
+        fen::to_fen,
+        make_chess_move::MoveError,
         moves::king::move_piece,
+        notation::{move_to_san, move_to_uci},
         piece::{Piece, PieceColor},
+        variant::GameVariant,
     };
 
     // use super::*;
@@ -191,4 +222,75 @@ mod tests {
         chess.board[3][7].piece = NONE;
         assert!(move_piece(&sq1, &sq2, &chess));
     }
+
+    #[test]
+    fn chess960_castling_works() {
+        // BBQNNRKR, the king starts on g1 between the rooks on f1 and h1
+        let mut chess = Chess::with_variant(GameVariant::Chess960(0));
+        assert_eq!(
+            chess.make_move_from_uci("g1f1"),
+            Err(MoveError::IllegalMove)
+        );
+        for file in [File::C, File::D, File::E] {
+            chess.board[file as usize][Rank::First as usize].piece = NONE;
+        }
+        add_possible_moves_to_squares(&mut chess);
+        assert!(chess
+            .legal_moves()
+            .iter()
+            .any(|chess_move| move_to_uci(chess_move) == "g1f1"));
+        let queenside = ((File::G, Rank::First), (File::F, Rank::First), (0, 0));
+        assert_eq!(move_to_san(&chess, &queenside), "O-O-O");
+
+        chess.make_move_from_uci("g1f1").expect("castling is legal");
+        assert_eq!(
+            chess.get_square(File::C, Rank::First).piece,
+            Piece::King(PieceColor::White)
+        );
+        assert_eq!(
+            chess.get_square(File::D, Rank::First).piece,
+            Piece::Rook(PieceColor::White)
+        );
+        assert_eq!(chess.get_square(File::F, Rank::First).piece, NONE);
+        assert_eq!(chess.get_square(File::G, Rank::First).piece, NONE);
+        assert_eq!(
+            chess.get_square(File::H, Rank::First).piece,
+            Piece::Rook(PieceColor::White)
+        );
+        assert!(chess.pieces_eaten.is_empty());
+        assert!(to_fen(&chess).starts_with("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBKR3R b kq -"));
+
+        // Black castles kingside with the king staying on g8 once the f8 rook is gone
+        chess.board[File::F as usize][Rank::Eighth as usize].piece = NONE;
+        chess.make_move_from_uci("g8h8").expect("castling is legal");
+        assert_eq!(chess.get_square(File::G, Rank::Eighth).piece, BLACKKING);
+        assert_eq!(
+            chess.get_square(File::F, Rank::Eighth).piece,
+            Piece::Rook(PieceColor::Black)
+        );
+        assert_eq!(chess.get_square(File::H, Rank::Eighth).piece, NONE);
+    }
+
+    #[test]
+    fn castling_through_check_is_illegal() {
+        let mut chess = Chess::with_variant(GameVariant::Chess960(0));
+        for file in [File::C, File::D, File::E] {
+            chess.board[file as usize][Rank::First as usize].piece = NONE;
+        }
+        // A black rook on the open d file attacks a square the king crosses
+        chess.board[File::D as usize][Rank::Second as usize].piece = NONE;
+        chess.board[File::D as usize][Rank::Fifth as usize].piece = Piece::Rook(PieceColor::Black);
+        assert_eq!(
+            chess.make_move_from_uci("g1f1"),
+            Err(MoveError::IllegalMove)
+        );
+
+        let mut chess = Chess::new_starting_position();
+        for file in [File::F, File::G] {
+            chess.board[file as usize][Rank::First as usize].piece = NONE;
+        }
+        chess.board[File::E as usize][Rank::Second as usize].piece = NONE;
+        chess.board[File::E as usize][Rank::Fifth as usize].piece = Piece::Rook(PieceColor::Black);
+        assert!(chess.make_move_from_uci("e1g1").is_err());
+    }
 }
@@ -22,7 +22,8 @@ pub fn promote(start_sq: &Square, end_sq: &Square, chess: &Chess, piece: Piece)
     }
 
     match piece {
-        Piece::King(_) | Piece::Pawn(_) | Piece::None => None,
+        Piece::Pawn(_) | Piece::None => None,
+        Piece::King(_) => Some(Piece::King(promoted_piece_color)),
         Piece::Knight(_) => Some(Piece::Knight(promoted_piece_color)),
         Piece::Bishop(_) => Some(Piece::Bishop(promoted_piece_color)),
         Piece::Rook(_) => Some(Piece::Rook(promoted_piece_color)),
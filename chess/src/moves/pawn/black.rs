@@ -24,7 +24,7 @@ fn black_starting_sq_move(start_sq: &Square, end_sq: &Square, chess: &Chess) ->
     if diagonally_one_square_apart(start_sq, end_sq) {
         black_capture(start_sq, end_sq, chess)
     } else if start_sq.file != end_sq.file {
-        return false;
+        false
     } else {
         match square_column_diff(start_sq, end_sq) {
             1 => one_square_forward(end_sq),
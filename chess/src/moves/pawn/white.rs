@@ -5,11 +5,14 @@ use crate::{
     piece::{Piece, PieceColor},
 };
 
+/**
+ * White pawns double-step from the second rank, and from the first where Horde puts them
+ */
 #[must_use]
 pub fn move_white_pawn(start_sq: &Square, end_sq: &Square, chess: &Chess) -> bool {
     if start_sq.rank == Rank::Eighth || start_sq.rank > end_sq.rank || start_sq.is_empty() {
         false
-    } else if start_sq.rank == Rank::Second {
+    } else if matches!(start_sq.rank, Rank::First | Rank::Second) {
         white_starting_sq_move(start_sq, end_sq, chess)
     } else if diagonally_one_square_apart(start_sq, end_sq) {
         white_capture(start_sq, end_sq, chess)
@@ -24,14 +27,14 @@ fn white_starting_sq_move(start_sq: &Square, end_sq: &Square, chess: &Chess) ->
     if diagonally_one_square_apart(start_sq, end_sq) {
         white_capture(start_sq, end_sq, chess)
     } else if start_sq.file != end_sq.file {
-        return false;
+        false
     } else {
         let column_diff = square_column_diff(start_sq, end_sq);
-        return match column_diff {
+        match column_diff {
             1 => one_square_forward(end_sq),
             2 => two_squares_forward(start_sq, end_sq, chess),
             _ => false,
-        };
+        }
     }
 }
 
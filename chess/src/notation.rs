@@ -0,0 +1,282 @@
+use crate::{
+    castling::CastlingSide,
+    chess::{is_drop, Chess, Move, SquareCoordinates},
+    chessboard::{file::File, rank::Rank},
+    game_state::GameState,
+    make_chess_move::{try_make_chess_move, try_make_drop, MoveError},
+    moves::king::castling_side,
+    piece::{tuple_to_promoted_piece, Piece, PieceColor},
+};
+
+/**
+ * Parsed UCI move, for example e7e8q is ((E, Seventh), (E, Eighth), Some(Queen))
+ * The promoted piece has no color, it is given the color of the moving pawn
+ */
+pub type UciMove = (SquareCoordinates, SquareCoordinates, Option<Piece>);
+
+#[must_use]
+pub fn square_to_uci(file: File, rank: Rank) -> String {
+    file.as_str().to_lowercase() + rank.as_str()
+}
+
+/**
+ * Drops are written with the piece letter, for example N@f3
+ */
+#[must_use]
+pub fn move_to_uci(chess_move: &Move) -> String {
+    let ((start_file, start_rank), (end_file, end_rank), promoted_piece) = *chess_move;
+    if is_drop(chess_move) {
+        return drop_to_string(tuple_to_promoted_piece(promoted_piece), end_file, end_rank);
+    }
+    let mut uci = square_to_uci(start_file, start_rank) + &square_to_uci(end_file, end_rank);
+    if let Some(piece_char) = tuple_to_promoted_piece(promoted_piece).and_then(promotion_char) {
+        uci.push(piece_char);
+    }
+    uci
+}
+
+#[must_use]
+pub fn parse_uci_square(square: &str) -> Option<SquareCoordinates> {
+    match square.as_bytes() {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Some((
+            File::try_from(file - b'a').ok()?,
+            Rank::try_from(rank - b'1').ok()?,
+        )),
+        _ => None,
+    }
+}
+
+#[must_use]
+pub fn parse_uci(uci: &str) -> Option<UciMove> {
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+        return None;
+    }
+    let start = parse_uci_square(&uci[0..2])?;
+    let end = parse_uci_square(&uci[2..4])?;
+    let promoted_piece = match uci.as_bytes().get(4) {
+        None => None,
+        Some(b'q') => Some(Piece::Queen(PieceColor::None)),
+        Some(b'r') => Some(Piece::Rook(PieceColor::None)),
+        Some(b'b') => Some(Piece::Bishop(PieceColor::None)),
+        Some(b'n') => Some(Piece::Knight(PieceColor::None)),
+        Some(b'k') => Some(Piece::King(PieceColor::None)),
+        Some(_) => return None,
+    };
+    Some((start, end, promoted_piece))
+}
+
+/**
+ * Parsed drop such as N@f3 or P@e4, the piece has no color
+ */
+#[must_use]
+pub fn parse_drop(uci: &str) -> Option<(Piece, SquareCoordinates)> {
+    let (piece, square) = uci.split_once('@')?;
+    let piece = match piece {
+        "P" => Piece::Pawn(PieceColor::None),
+        "N" => Piece::Knight(PieceColor::None),
+        "B" => Piece::Bishop(PieceColor::None),
+        "R" => Piece::Rook(PieceColor::None),
+        "Q" => Piece::Queen(PieceColor::None),
+        _ => return None,
+    };
+    Some((piece, parse_uci_square(square)?))
+}
+
+fn drop_to_string(piece: Option<Piece>, file: File, rank: Rank) -> String {
+    let letter = piece.map_or('P', |piece| piece_letter(piece).unwrap_or('P'));
+    format!("{letter}@{}", square_to_uci(file, rank))
+}
+
+/**
+ * Standard algebraic notation of `chess_move`, `chess` is the position before the move is made
+ */
+#[must_use]
+pub fn move_to_san(chess: &Chess, chess_move: &Move) -> String {
+    let ((start_file, start_rank), (end_file, end_rank), promoted_piece) = *chess_move;
+    let start_sq = chess.get_square(start_file, start_rank);
+    let end_sq = chess.get_square(end_file, end_rank);
+    let piece = start_sq.piece;
+    let promoted_piece = tuple_to_promoted_piece(promoted_piece);
+
+    let mut san = match castling_side(&start_sq, &end_sq, chess) {
+        _ if is_drop(chess_move) => drop_to_string(promoted_piece, end_file, end_rank),
+        Some(CastlingSide::Kingside) => "O-O".to_string(),
+        Some(CastlingSide::Queenside) => "O-O-O".to_string(),
+        None => {
+            let is_capture = end_sq.has_piece() || (piece.is_pawn() && start_file != end_file);
+            let mut san = String::new();
+            if piece.is_pawn() {
+                if is_capture {
+                    san.push_str(&start_file.as_str().to_lowercase());
+                }
+            } else {
+                san.extend(piece_letter(piece));
+                san.push_str(&disambiguation(chess, chess_move));
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&square_to_uci(end_file, end_rank));
+            if let Some(piece_char) = promoted_piece.and_then(promotion_char) {
+                san.push('=');
+                san.push(piece_char.to_ascii_uppercase());
+            }
+            san
+        }
+    };
+
+    let mut after_move = chess.clone();
+    let played = if is_drop(chess_move) {
+        promoted_piece.map_or(Err(MoveError::InvalidDrop), |piece| {
+            try_make_drop(&mut after_move, piece, &end_sq)
+        })
+    } else {
+        try_make_chess_move(&mut after_move, &start_sq, &end_sq, promoted_piece)
+    };
+    if played.is_ok() {
+        let opponent = match chess.side_to_move() {
+            PieceColor::White => after_move.players.1,
+            PieceColor::Black | PieceColor::None => after_move.players.0,
+        };
+        if opponent.in_check {
+            let is_mate = matches!(
+                after_move.gamestate,
+                GameState::WhiteVictory | GameState::BlackVictory
+            );
+            san.push(if is_mate { '#' } else { '+' });
+        }
+    }
+    san
+}
+
+const fn piece_letter(piece: Piece) -> Option<char> {
+    match piece {
+        Piece::Knight(_) => Some('N'),
+        Piece::Bishop(_) => Some('B'),
+        Piece::Rook(_) => Some('R'),
+        Piece::Queen(_) => Some('Q'),
+        Piece::King(_) => Some('K'),
+        Piece::None | Piece::Pawn(_) => None,
+    }
+}
+
+/**
+ * File, rank or both of the start square when another identical piece can reach the same square
+ */
+fn disambiguation(chess: &Chess, chess_move: &Move) -> String {
+    let ((start_file, start_rank), (end_file, end_rank), _) = *chess_move;
+    let piece = chess.get_square(start_file, start_rank).piece;
+    let rivals: Vec<SquareCoordinates> = chess
+        .board
+        .iter()
+        .flatten()
+        .filter(|square| {
+            square.piece == piece && (square.file, square.rank) != (start_file, start_rank)
+        })
+        .filter(|square| {
+            square
+                .possible_moves
+                .iter()
+                .any(|possible_move| possible_move.1 == (end_file as usize, end_rank as usize))
+        })
+        .map(|square| (square.file, square.rank))
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|(file, _)| *file != start_file) {
+        start_file.as_str().to_lowercase()
+    } else if rivals.iter().all(|(_, rank)| *rank != start_rank) {
+        start_rank.as_str().to_string()
+    } else {
+        square_to_uci(start_file, start_rank)
+    }
+}
+
+const fn promotion_char(piece: Piece) -> Option<char> {
+    match piece {
+        Piece::Queen(_) => Some('q'),
+        Piece::Rook(_) => Some('r'),
+        Piece::Bishop(_) => Some('b'),
+        Piece::Knight(_) => Some('n'),
+        Piece::King(_) => Some('k'),
+        Piece::None | Piece::Pawn(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chess;
+
+    #[test]
+    fn uci_roundtrip_works() {
+        let parsed = parse_uci("e2e4").expect("valid uci");
+        assert_eq!(
+            parsed,
+            ((File::E, Rank::Second), (File::E, Rank::Fourth), None)
+        );
+        let parsed = parse_uci("a7a8n").expect("valid uci");
+        assert_eq!(parsed.2, Some(Piece::Knight(PieceColor::None)));
+
+        assert!(parse_uci("e2e9").is_none());
+        assert!(parse_uci("i2e4").is_none());
+        assert!(parse_uci("e2e4p").is_none());
+        assert_eq!(
+            parse_uci("e7e8k").expect("valid uci").2,
+            Some(Piece::King(PieceColor::None))
+        );
+        assert!(parse_uci("e2").is_none());
+
+        assert_eq!(
+            parse_drop("N@f3"),
+            Some((Piece::Knight(PieceColor::None), (File::F, Rank::Third)))
+        );
+        assert!(parse_drop("K@f3").is_none());
+        assert!(parse_drop("N@f9").is_none());
+        let drop = ((File::F, Rank::Third), (File::F, Rank::Third), (4, 1));
+        assert_eq!(move_to_uci(&drop), "N@f3");
+    }
+
+    #[test]
+    fn san_works() {
+        let mut chess = Chess::new_starting_position();
+        let mut sans = Vec::new();
+        for uci in [
+            "e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "g8f6", "e1g1", "f6e4", "b1c3", "e4c3", "d2c3",
+            "f8c5", "b5c6", "d7c6", "f3e5", "c5f2",
+        ] {
+            let (start, end, _) = parse_uci(uci).expect("valid uci");
+            let chess_move = (start, end, (0, 0));
+            sans.push(move_to_san(&chess, &chess_move));
+            chess.make_move_from_uci(uci).expect("legal move");
+        }
+        assert_eq!(
+            sans,
+            vec![
+                "e4", "e5", "Nf3", "Nc6", "Bb5", "Nf6", "O-O", "Nxe4", "Nc3", "Nxc3", "dxc3",
+                "Bc5", "Bxc6", "dxc6", "Nxe5", "Bxf2+"
+            ]
+        );
+    }
+
+    #[test]
+    fn san_disambiguation_works() {
+        let mut chess = Chess::new_starting_position();
+        for uci in ["g1f3", "a7a6", "b1c3", "a6a5", "c3b5", "a5a4"] {
+            chess.make_move_from_uci(uci).expect("legal move");
+        }
+        let chess_move = ((File::F, Rank::Third), (File::D, Rank::Fourth), (0, 0));
+        assert_eq!(move_to_san(&chess, &chess_move), "Nfd4");
+    }
+
+    #[test]
+    fn list_of_moves_to_uci_works() {
+        let mut chess = Chess::new_starting_position();
+        chess.make_move_from_uci("e2e4").expect("legal move");
+        chess.make_move_from_uci("d7d5").expect("legal move");
+        chess.make_move_from_uci("e4d5").expect("legal move");
+        let moves: Vec<String> = chess.list_of_moves.iter().map(move_to_uci).collect();
+        assert_eq!(moves, vec!["e2e4", "d7d5", "e4d5"]);
+    }
+}
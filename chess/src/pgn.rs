@@ -0,0 +1,161 @@
+use crate::{chess::Chess, fen::to_fen, game_state::GameState, notation::move_to_san};
+
+const MAX_LINE_LENGTH: usize = 80;
+
+#[must_use]
+pub const fn result_to_pgn(gamestate: GameState) -> &'static str {
+    match gamestate {
+        GameState::WhiteVictory | GameState::WhiteKingOfTheHill | GameState::WhiteThreeCheck => {
+            "1-0"
+        }
+        GameState::BlackVictory | GameState::BlackKingOfTheHill | GameState::BlackThreeCheck => {
+            "0-1"
+        }
+        GameState::InsufficientMaterial | GameState::Stalemate | GameState::Draw => "1/2-1/2",
+        GameState::InProgress => "*",
+    }
+}
+
+/**
+ * Exports the game as PGN, `tags` are written after the seven tag roster.
+ * Missing roster tags are written as "?". Variant games get a Variant tag,
+ * Chess960 games also get their starting position.
+ */
+#[must_use]
+pub fn to_pgn(chess: &Chess, tags: &[(&str, String)]) -> String {
+    let start = Chess::with_variant(chess.variant);
+    write_pgn(&start, chess, tags, chess.variant.is_chess960())
+}
+
+/**
+ * Exports a game that was played from the set up position `start`, which is
+ * written in the FEN tag
+ */
+#[must_use]
+pub fn to_pgn_from(start: &Chess, chess: &Chess, tags: &[(&str, String)]) -> String {
+    write_pgn(start, chess, tags, true)
+}
+
+fn write_pgn(start: &Chess, chess: &Chess, tags: &[(&str, String)], set_up: bool) -> String {
+    let result = result_to_pgn(chess.gamestate);
+    let tag_value = |name: &str| {
+        tags.iter()
+            .find(|(tag, _)| *tag == name)
+            .map_or_else(|| "?".to_string(), |(_, value)| value.clone())
+    };
+
+    let mut pgn = String::new();
+    for name in ["Event", "Site", "Date", "Round", "White", "Black"] {
+        pgn.push_str(&format_tag(name, &tag_value(name)));
+    }
+    pgn.push_str(&format_tag("Result", result));
+    for (name, value) in tags {
+        if !matches!(
+            *name,
+            "Event" | "Site" | "Date" | "Round" | "White" | "Black" | "Result"
+        ) {
+            pgn.push_str(&format_tag(name, value));
+        }
+    }
+    if let Some(variant) = chess.variant.pgn_name() {
+        pgn.push_str(&format_tag("Variant", variant));
+    }
+    if set_up {
+        pgn.push_str(&format_tag("SetUp", "1"));
+        pgn.push_str(&format_tag("FEN", &to_fen(start)));
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut replay = start.clone();
+    let first_ply = usize::try_from(start.turn_number).unwrap_or_default();
+    for (ply, chess_move) in chess.list_of_moves.iter().enumerate() {
+        let ply = first_ply + ply;
+        if ply % 2 == 0 {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if ply == first_ply {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(move_to_san(&replay, chess_move));
+        replay.make_move(
+            &replay.get_square(chess_move.0 .0, chess_move.0 .1),
+            &replay.get_square(chess_move.1 .0, chess_move.1 .1),
+            chess_move.2,
+        );
+    }
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.len() + 1 > MAX_LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{name} \"{value}\"]\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_builder::BoardBuilder;
+
+    #[test]
+    fn pgn_export_works() {
+        let mut chess = Chess::new_starting_position();
+        for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            chess.make_move_from_uci(uci).expect("legal move");
+        }
+        let pgn = to_pgn(
+            &chess,
+            &[
+                ("White", "alice".to_string()),
+                ("Annotator", "bob".to_string()),
+            ],
+        );
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"?\"]\n[Round \"?\"]\n[White \"alice\"]\n\
+             [Black \"?\"]\n[Result \"0-1\"]\n[Annotator \"bob\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+    }
+
+    #[test]
+    fn chess960_pgn_has_the_starting_position() {
+        let mut chess = Chess::with_variant(crate::variant::GameVariant::Chess960(0));
+        chess.make_move_from_uci("g2g3").expect("legal move");
+        let pgn = to_pgn(&chess, &[]);
+        assert!(pgn.contains(
+            "[Variant \"Chess960\"]\n[SetUp \"1\"]\n\
+             [FEN \"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1\"]\n\n1. g3 *\n"
+        ));
+    }
+
+    #[test]
+    fn set_up_pgn_starts_from_its_position() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 b Q - 0 1";
+        let start = BoardBuilder::from_fen(fen)
+            .and_then(BoardBuilder::build)
+            .expect("position is valid");
+        let mut chess = start.clone();
+        for uci in ["e8d7", "e1c1"] {
+            chess.make_move_from_uci(uci).expect("legal move");
+        }
+        let pgn = to_pgn_from(&start, &chess, &[]);
+        assert!(pgn.contains(&format!(
+            "[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n1... Kd7 2. O-O-O+ *\n"
+        )));
+    }
+}
@@ -74,6 +74,30 @@ impl Piece {
     pub const fn is_pawn(self) -> bool {
         matches!(self, Self::Pawn(_))
     }
+
+    /**
+     * The same kind of piece in `color`
+     */
+    #[must_use]
+    pub const fn with_color(self, color: PieceColor) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Pawn(_) => Self::Pawn(color),
+            Self::Knight(_) => Self::Knight(color),
+            Self::Bishop(_) => Self::Bishop(color),
+            Self::Rook(_) => Self::Rook(color),
+            Self::Queen(_) => Self::Queen(color),
+            Self::King(_) => Self::King(color),
+        }
+    }
+
+    /**
+     * Whether both are the same kind of piece, whatever their color
+     */
+    #[must_use]
+    pub fn is_same_kind(self, other: Self) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
 }
 
 impl From<Option<&str>> for Piece {
@@ -99,6 +123,10 @@ pub const fn promoted_piece_to_i32tuple(promoted_piece: Option<Piece>) -> (i32,
         Some(Piece::Bishop(PieceColor::Black)) => (3, 1),
         Some(Piece::Knight(PieceColor::White)) => (4, 0),
         Some(Piece::Knight(PieceColor::Black)) => (4, 1),
+        Some(Piece::Pawn(PieceColor::White)) => (5, 0),
+        Some(Piece::Pawn(PieceColor::Black)) => (5, 1),
+        Some(Piece::King(PieceColor::White)) => (6, 0),
+        Some(Piece::King(PieceColor::Black)) => (6, 1),
         _ => (0, 0),
     }
 }
@@ -115,6 +143,10 @@ pub const fn tuple_to_promoted_piece(promoted_piece: (i32, i32)) -> Option<Piece
         (3, 1) => Some(Piece::Bishop(PieceColor::Black)),
         (4, 0) => Some(Piece::Knight(PieceColor::White)),
         (4, 1) => Some(Piece::Knight(PieceColor::Black)),
+        (5, 0) => Some(Piece::Pawn(PieceColor::White)),
+        (5, 1) => Some(Piece::Pawn(PieceColor::Black)),
+        (6, 0) => Some(Piece::King(PieceColor::White)),
+        (6, 1) => Some(Piece::King(PieceColor::Black)),
         _ => None,
     }
 }
@@ -23,6 +23,28 @@ impl PiecesEaten {
             PieceColor::None => {}
         }
     }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.white.len() + self.black.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * The piece of `color` that was captured most recently
+     */
+    #[must_use]
+    pub fn last(&self, color: PieceColor) -> Option<Piece> {
+        match color {
+            PieceColor::White => self.white.last().copied(),
+            PieceColor::Black => self.black.last().copied(),
+            PieceColor::None => None,
+        }
+    }
 }
 
 impl Default for PiecesEaten {
@@ -81,6 +103,8 @@ mod tests {
         assert_eq!(pieces_eaten.white.len(), 2);
         assert_eq!(pieces_eaten.white[0], white_piece);
         assert_eq!(pieces_eaten.black[0], black_piece);
+        assert_eq!(pieces_eaten.len(), 3);
+        assert_eq!(pieces_eaten.last(PieceColor::Black), Some(black_piece));
     }
 
     #[test]
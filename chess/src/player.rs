@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{castling::RightToCastle, piece::PieceColor};
+use crate::{castling::RightToCastle, piece::PieceColor, pocket::Pocket};
 
 /**
  * First is kingside castling, second is queenside castling
@@ -11,6 +11,21 @@ pub struct Player {
     pub in_check: bool,
     pub victory: bool,
     pub castling: RightToCastle,
+    /**
+     * Times this player put the opposing king in check
+     */
+    #[serde(default)]
+    pub checks: u8,
+    /**
+     * Captured pieces this player may drop in Crazyhouse
+     */
+    #[serde(default)]
+    pub pocket: Pocket,
+    /**
+     * Account of the user sitting at this side, None when anyone may play it
+     */
+    #[serde(default)]
+    pub user_id: Option<u64>,
 }
 
 impl Player {
@@ -21,6 +36,9 @@ impl Player {
             victory: false,
             in_check: false,
             castling: RightToCastle::default(),
+            checks: 0,
+            pocket: Pocket::new(),
+            user_id: None,
         }
     }
 
@@ -44,15 +62,15 @@ impl Player {
         self.in_check
     }
 
-    pub fn castle(&mut self) {
+    pub const fn castle(&mut self) {
         self.castling.remove_castling();
     }
 
-    pub fn no_kingside_castling(&mut self) {
+    pub const fn no_kingside_castling(&mut self) {
         self.castling.kingside = false;
     }
 
-    pub fn no_queenside_castling(&mut self) {
+    pub const fn no_queenside_castling(&mut self) {
         self.castling.queenside = false;
     }
 }
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::piece::{Piece, PieceColor};
+
+const NONE: PieceColor = PieceColor::None;
+
+/**
+ * Kinds of pieces a pocket holds, in the order FEN writes them
+ */
+pub const POCKET_PIECES: [Piece; 5] = [
+    Piece::Queen(NONE),
+    Piece::Rook(NONE),
+    Piece::Bishop(NONE),
+    Piece::Knight(NONE),
+    Piece::Pawn(NONE),
+];
+
+/**
+ * Captured pieces a Crazyhouse player may drop back on the board, counted by kind
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pocket {
+    pub queens: u8,
+    pub rooks: u8,
+    pub bishops: u8,
+    pub knights: u8,
+    pub pawns: u8,
+}
+
+impl Pocket {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            queens: 0,
+            rooks: 0,
+            bishops: 0,
+            knights: 0,
+            pawns: 0,
+        }
+    }
+
+    const fn slot(&mut self, piece: Piece) -> Option<&mut u8> {
+        match piece {
+            Piece::Queen(_) => Some(&mut self.queens),
+            Piece::Rook(_) => Some(&mut self.rooks),
+            Piece::Bishop(_) => Some(&mut self.bishops),
+            Piece::Knight(_) => Some(&mut self.knights),
+            Piece::Pawn(_) => Some(&mut self.pawns),
+            Piece::King(_) | Piece::None => None,
+        }
+    }
+
+    /**
+     * How many pieces of the kind of `piece` are in the pocket, whatever its color
+     */
+    #[must_use]
+    pub const fn count(&self, piece: Piece) -> u8 {
+        match piece {
+            Piece::Queen(_) => self.queens,
+            Piece::Rook(_) => self.rooks,
+            Piece::Bishop(_) => self.bishops,
+            Piece::Knight(_) => self.knights,
+            Piece::Pawn(_) => self.pawns,
+            Piece::King(_) | Piece::None => 0,
+        }
+    }
+
+    /**
+     * Kings cannot be pocketed and are ignored
+     */
+    pub const fn add(&mut self, piece: Piece) {
+        if let Some(count) = self.slot(piece) {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    /**
+     * Takes one piece of the kind of `piece` out, false when there is none
+     */
+    pub const fn take(&mut self, piece: Piece) -> bool {
+        match self.slot(piece) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.queens + self.rooks + self.bishops + self.knights + self.pawns == 0
+    }
+
+    /**
+     * Every kind of piece with at least one in the pocket, colorless
+     */
+    #[must_use]
+    pub fn kinds(&self) -> Vec<Piece> {
+        POCKET_PIECES
+            .into_iter()
+            .filter(|&piece| self.count(piece) > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pocket_works() {
+        let mut pocket = Pocket::new();
+        assert!(pocket.is_empty());
+        pocket.add(Piece::Knight(PieceColor::White));
+        pocket.add(Piece::Knight(PieceColor::Black));
+        pocket.add(Piece::Pawn(PieceColor::White));
+        pocket.add(Piece::King(PieceColor::White));
+        assert_eq!(pocket.count(Piece::Knight(NONE)), 2);
+        assert_eq!(pocket.kinds(), vec![Piece::Knight(NONE), Piece::Pawn(NONE)]);
+
+        assert!(pocket.take(Piece::Pawn(PieceColor::Black)));
+        assert!(!pocket.take(Piece::Pawn(NONE)));
+        assert!(!pocket.take(Piece::Queen(NONE)));
+        assert_eq!(pocket.kinds(), vec![Piece::Knight(NONE)]);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    castling::CastlingSide,
+    check::is_king_in_check_state,
+    chess::Chess,
+    chessboard::{file::File, square::Square, ChessBoard},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    piece::{Piece, PieceColor},
+};
+
+pub mod antichess;
+pub mod atomic;
+pub mod crazyhouse;
+pub mod horde;
+pub mod king_of_the_hill;
+pub mod standard;
+pub mod three_check;
+
+use antichess::Antichess;
+use atomic::Atomic;
+use crazyhouse::Crazyhouse;
+use horde::Horde;
+use king_of_the_hill::KingOfTheHill;
+use standard::StandardChess;
+use three_check::ThreeCheck;
+
+/**
+ * Pieces of the first rank from the a file to the h file, their color is ignored
+ */
+pub type BackRank = [Piece; 8];
+
+const NONE: PieceColor = PieceColor::None;
+
+pub const STANDARD_BACK_RANK: BackRank = [
+    Piece::Rook(NONE),
+    Piece::Knight(NONE),
+    Piece::Bishop(NONE),
+    Piece::Queen(NONE),
+    Piece::King(NONE),
+    Piece::Bishop(NONE),
+    Piece::Knight(NONE),
+    Piece::Rook(NONE),
+];
+
+/**
+ * Number of Chess960 starting positions, position 518 is the standard one
+ */
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/**
+ * Knight files among the five squares left once the bishops and queen are placed
+ */
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/**
+ * The rules a game is played by, so a new variant only has to describe how it differs.
+ * Piece movement, castling and en passant stay in the core.
+ */
+pub trait Variant: fmt::Debug + Send + Sync {
+    /**
+     * Sets up the pieces the game starts with
+     */
+    fn starting_position(&self, chess: &mut Chess);
+
+    /**
+     * Whether the side to move may play a move its piece is able to make
+     */
+    #[allow(clippy::missing_errors_doc)]
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError>;
+
+    /**
+     * Pieces a pawn may promote to, their color is ignored
+     */
+    fn promotion_pieces(&self) -> &'static [Piece];
+
+    /**
+     * How the game ended once a move was made, None while it goes on
+     */
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState>;
+
+    /**
+     * Whether neither side can win anymore
+     */
+    fn is_insufficient_material(&self, chess: &Chess) -> bool;
+
+    /**
+     * Whether `color` could never win however its opponent plays, so the opponent
+     * running out of time only draws
+     */
+    fn has_insufficient_material(&self, _chess: &Chess, _color: PieceColor) -> bool {
+        false
+    }
+
+    /**
+     * Whether kings may never be captured, so that leaving one attacked is illegal
+     */
+    fn has_royal_king(&self) -> bool {
+        true
+    }
+
+    /**
+     * Whether the king of `color` is attacked on `board`
+     */
+    fn is_in_check(&self, board: &ChessBoard, color: PieceColor) -> bool {
+        is_king_in_check_state(board, color)
+    }
+
+    /**
+     * Runs once a move took `captured`, with the capturing piece already on `end_sq`
+     */
+    fn after_capture(&self, _chess: &mut Chess, _end_sq: &Square, _captured: Piece) {}
+
+    /**
+     * Runs once every move other than a drop is on the board
+     */
+    fn after_move(
+        &self,
+        _chess: &mut Chess,
+        _start_sq: &Square,
+        _end_sq: &Square,
+        _promoted_piece: Option<Piece>,
+    ) {
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameVariant {
+    #[default]
+    Standard,
+    /**
+     * Fischer Random, one of the 960 back ranks by its Scharnagl number.
+     * Castling is written as the king moving onto its rook, both land on the standard squares.
+     */
+    Chess960(u16),
+    /**
+     * Standard chess where a king reaching d4, e4, d5 or e5 wins
+     */
+    KingOfTheHill,
+    /**
+     * Standard chess where giving check a third time wins
+     */
+    ThreeCheck,
+    /**
+     * Captures explode the pieces around them, blowing up the enemy king wins
+     */
+    Atomic,
+    /**
+     * Captured pieces change sides and can be dropped back on the board
+     */
+    Crazyhouse,
+    /**
+     * Captures are compulsory and losing every piece wins, the king is an ordinary piece
+     */
+    Antichess,
+    /**
+     * Thirty-six white pawns without a king against the standard black army
+     */
+    Horde,
+}
+
+impl GameVariant {
+    #[must_use]
+    pub const fn is_valid(self) -> bool {
+        match self {
+            Self::Standard
+            | Self::KingOfTheHill
+            | Self::ThreeCheck
+            | Self::Atomic
+            | Self::Crazyhouse
+            | Self::Antichess
+            | Self::Horde => true,
+            Self::Chess960(position) => position < CHESS960_POSITIONS,
+        }
+    }
+
+    /**
+     * The rules games of this variant are played by
+     */
+    #[must_use]
+    pub const fn rules(self) -> &'static dyn Variant {
+        match self {
+            Self::Standard | Self::Chess960(_) => &StandardChess,
+            Self::KingOfTheHill => &KingOfTheHill,
+            Self::ThreeCheck => &ThreeCheck,
+            Self::Atomic => &Atomic,
+            Self::Crazyhouse => &Crazyhouse,
+            Self::Antichess => &Antichess,
+            Self::Horde => &Horde,
+        }
+    }
+
+    /**
+     * Value of the PGN Variant tag, None for standard chess
+     */
+    #[must_use]
+    pub const fn pgn_name(self) -> Option<&'static str> {
+        match self {
+            Self::Standard => None,
+            Self::Chess960(_) => Some("Chess960"),
+            Self::KingOfTheHill => Some("King of the Hill"),
+            Self::ThreeCheck => Some("Three-check"),
+            Self::Atomic => Some("Atomic"),
+            Self::Crazyhouse => Some("Crazyhouse"),
+            Self::Antichess => Some("Antichess"),
+            Self::Horde => Some("Horde"),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_chess960(self) -> bool {
+        matches!(self, Self::Chess960(_))
+    }
+
+    #[must_use]
+    pub fn back_rank(self) -> BackRank {
+        match self {
+            Self::Standard
+            | Self::KingOfTheHill
+            | Self::ThreeCheck
+            | Self::Atomic
+            | Self::Crazyhouse
+            | Self::Antichess
+            | Self::Horde => STANDARD_BACK_RANK,
+            Self::Chess960(position) => chess960_back_rank(position),
+        }
+    }
+
+    /**
+     * File the kings start on
+     */
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn king_file(self) -> File {
+        let file = self
+            .back_rank()
+            .iter()
+            .position(|piece| piece.is_king())
+            .expect("Every back rank has a king");
+        file_of(file)
+    }
+
+    /**
+     * File of the rook a king castles with to `side`, the rooks start on either side of the king
+     */
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn rook_file(self, side: CastlingSide) -> File {
+        let back_rank = self.back_rank();
+        let rooks = back_rank
+            .iter()
+            .enumerate()
+            .filter(|(_, piece)| piece.is_rook());
+        let file = match side {
+            CastlingSide::Queenside => rooks.map(|(file, _)| file).next(),
+            CastlingSide::Kingside => rooks.map(|(file, _)| file).next_back(),
+        };
+        file_of(file.expect("Every back rank has two rooks"))
+    }
+}
+
+/**
+ * Written as `standard`, `chess960:<position>` or the snake case name of the variant
+ */
+impl fmt::Display for GameVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Standard => f.write_str("standard"),
+            Self::Chess960(position) => write!(f, "chess960:{position}"),
+            Self::KingOfTheHill => f.write_str("king_of_the_hill"),
+            Self::ThreeCheck => f.write_str("three_check"),
+            Self::Atomic => f.write_str("atomic"),
+            Self::Crazyhouse => f.write_str("crazyhouse"),
+            Self::Antichess => f.write_str("antichess"),
+            Self::Horde => f.write_str("horde"),
+        }
+    }
+}
+
+impl FromStr for GameVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let variant = match s.split_once(':') {
+            None if s == "standard" => Self::Standard,
+            None if s == "king_of_the_hill" => Self::KingOfTheHill,
+            None if s == "three_check" => Self::ThreeCheck,
+            None if s == "atomic" => Self::Atomic,
+            None if s == "crazyhouse" => Self::Crazyhouse,
+            None if s == "antichess" => Self::Antichess,
+            None if s == "horde" => Self::Horde,
+            Some(("chess960", position)) => Self::Chess960(
+                position
+                    .parse()
+                    .map_err(|_| format!("Invalid Chess960 position: {position}"))?,
+            ),
+            _ => return Err(format!("Invalid variant: {s}")),
+        };
+        if variant.is_valid() {
+            Ok(variant)
+        } else {
+            Err(format!("Invalid variant: {s}"))
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn file_of(file: usize) -> File {
+    File::try_from(file as u8).expect("Invalid file")
+}
+
+/**
+ * Places the light squared bishop, the dark squared bishop, the queen and the knights
+ * as given by the digits of `position`, then a rook, the king and a rook on the squares left
+ */
+fn chess960_back_rank(position: u16) -> BackRank {
+    let position = usize::from(position % CHESS960_POSITIONS);
+    let mut back_rank = [Piece::None; 8];
+    back_rank[position % 4 * 2 + 1] = Piece::Bishop(NONE);
+    back_rank[position / 4 % 4 * 2] = Piece::Bishop(NONE);
+
+    let place_on_empty = |back_rank: &mut BackRank, nth: usize, piece: Piece| {
+        let file = (0..8)
+            .filter(|&file| back_rank[file] == Piece::None)
+            .nth(nth)
+            .expect("There are enough empty squares");
+        back_rank[file] = piece;
+    };
+    place_on_empty(&mut back_rank, position / 16 % 6, Piece::Queen(NONE));
+    let (first_knight, second_knight) = KNIGHT_PLACEMENTS[position / 96];
+    // Placing the first knight shifts the empty squares after it by one
+    place_on_empty(&mut back_rank, second_knight, Piece::Knight(NONE));
+    place_on_empty(&mut back_rank, first_knight, Piece::Knight(NONE));
+    for piece in [Piece::Rook(NONE), Piece::King(NONE), Piece::Rook(NONE)] {
+        place_on_empty(&mut back_rank, 0, piece);
+    }
+    back_rank
+}
+
+/**
+ * A game of `variant` with only `pieces` on the board, `turn_number` plies in
+ */
+#[cfg(test)]
+pub(crate) fn test_position(
+    variant: GameVariant,
+    pieces: &[(File, crate::chessboard::rank::Rank, Piece)],
+    turn_number: i32,
+) -> Chess {
+    let mut chess = Chess::with_variant(variant);
+    chess.board = crate::chessboard::new_board();
+    for &(file, rank, piece) in pieces {
+        chess.board[file as usize][rank as usize].piece = piece;
+    }
+    chess.turn_number = turn_number;
+    crate::chessboard::add_possible_moves_to_squares(&mut chess);
+    chess
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chess960_positions_work() {
+        assert_eq!(GameVariant::Chess960(518).back_rank(), STANDARD_BACK_RANK);
+        let to_string = |back_rank: BackRank| -> String {
+            back_rank
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Rook(_) => 'R',
+                    Piece::Knight(_) => 'N',
+                    Piece::Bishop(_) => 'B',
+                    Piece::Queen(_) => 'Q',
+                    Piece::King(_) => 'K',
+                    Piece::Pawn(_) | Piece::None => '?',
+                })
+                .collect()
+        };
+        assert_eq!(to_string(chess960_back_rank(0)), "BBQNNRKR");
+        assert_eq!(to_string(chess960_back_rank(959)), "RKRNNQBB");
+        assert_eq!(to_string(chess960_back_rank(100)), "QBBNRNKR");
+
+        for position in 0..CHESS960_POSITIONS {
+            let variant = GameVariant::Chess960(position);
+            let back_rank = variant.back_rank();
+            assert!(back_rank.iter().all(|piece| *piece != Piece::None));
+            let bishops: Vec<usize> = (0..8)
+                .filter(|&f| back_rank[f] == Piece::Bishop(NONE))
+                .collect();
+            assert_eq!(bishops.len(), 2);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "position {position}");
+            assert!(variant.rook_file(CastlingSide::Queenside) < variant.king_file());
+            assert!(variant.king_file() < variant.rook_file(CastlingSide::Kingside));
+        }
+        assert!(!GameVariant::Chess960(960).is_valid());
+        for variant in [
+            GameVariant::Standard,
+            GameVariant::Chess960(42),
+            GameVariant::KingOfTheHill,
+            GameVariant::ThreeCheck,
+            GameVariant::Atomic,
+            GameVariant::Crazyhouse,
+            GameVariant::Antichess,
+            GameVariant::Horde,
+        ] {
+            assert_eq!(variant.to_string().parse(), Ok(variant));
+        }
+        assert!("chess960:960".parse::<GameVariant>().is_err());
+        assert!("bughouse".parse::<GameVariant>().is_err());
+    }
+}
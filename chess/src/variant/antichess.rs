@@ -0,0 +1,208 @@
+use crate::{
+    checkmate::possible_legal_moves,
+    chess::Chess,
+    chessboard::{square::Square, ChessBoard},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    moves::{
+        king::move_is_castling,
+        move_helpers::helpers::{move_is_black_en_passant, move_is_white_en_passant},
+    },
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+const NONE: PieceColor = PieceColor::None;
+
+const PROMOTION_PIECES: [Piece; 5] = [
+    Piece::Queen(NONE),
+    Piece::Rook(NONE),
+    Piece::Bishop(NONE),
+    Piece::Knight(NONE),
+    Piece::King(NONE),
+];
+
+/**
+ * Losing chess: whoever can capture has to, there is no check and the king is captured
+ * like any other piece. A player without pieces or without moves wins.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Antichess;
+
+fn is_capture(chess: &Chess, start_sq: &Square, end_sq: &Square) -> bool {
+    end_sq.piece.color() == start_sq.piece.color().opposite()
+        || move_is_white_en_passant(start_sq, end_sq, chess)
+        || move_is_black_en_passant(start_sq, end_sq, chess)
+}
+
+/**
+ * Whether `color` has any capture, which makes every other move illegal
+ */
+fn can_capture(chess: &Chess, color: PieceColor) -> bool {
+    possible_legal_moves(chess, color)
+        .iter()
+        .any(|possible_move| {
+            let start_sq = &chess.board[possible_move.0 .0][possible_move.0 .1];
+            let end_sq = &chess.board[possible_move.1 .0][possible_move.1 .1];
+            is_capture(chess, start_sq, end_sq)
+                && start_sq.piece.piece_move(start_sq, end_sq, chess)
+        })
+}
+
+impl Variant for Antichess {
+    /**
+     * The standard position without castling rights
+     */
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+        chess.players.0.castle();
+        chess.players.1.castle();
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        if move_is_castling(start_sq, end_sq, chess)
+            || (end_sq.has_piece() && end_sq.piece.color() == start_sq.piece.color())
+            || !start_sq.piece.piece_move(start_sq, end_sq, chess)
+        {
+            return Err(MoveError::IllegalMove);
+        }
+        if !is_capture(chess, start_sq, end_sq) && can_capture(chess, start_sq.piece.color()) {
+            return Err(MoveError::MustCapture);
+        }
+        Ok(())
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        &PROMOTION_PIECES
+    }
+
+    /**
+     * The side to move wins when it has nothing left to move
+     */
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        let stuck = chess
+            .board
+            .iter()
+            .flatten()
+            .filter(|square| square.piece.color() == side_to_move)
+            .all(|square| square.possible_moves.is_empty());
+        if stuck {
+            return Some(match side_to_move {
+                PieceColor::White => GameState::WhiteVictory,
+                _ => GameState::BlackVictory,
+            });
+        }
+        (chess.fifty_move_rule >= 50).then_some(GameState::Stalemate)
+    }
+
+    fn is_insufficient_material(&self, _chess: &Chess) -> bool {
+        false
+    }
+
+    fn has_royal_king(&self) -> bool {
+        false
+    }
+
+    fn is_in_check(&self, _board: &ChessBoard, _color: PieceColor) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chessboard::{file::File, rank::Rank},
+        fen::to_fen,
+        variant::{test_position, GameVariant},
+    };
+
+    const WHITE: PieceColor = PieceColor::White;
+    const BLACK: PieceColor = PieceColor::Black;
+
+    fn position(pieces: &[(File, Rank, Piece)], turn_number: i32) -> Chess {
+        test_position(GameVariant::Antichess, pieces, turn_number)
+    }
+
+    #[test]
+    fn captures_are_compulsory() {
+        let mut chess = Chess::with_variant(GameVariant::Antichess);
+        assert!(to_fen(&chess).contains(" w - - "));
+        for uci in ["e2e3", "b7b5"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(chess.legal_moves().len(), 1);
+        assert_eq!(
+            chess.make_move_from_uci("a2a3"),
+            Err(MoveError::MustCapture)
+        );
+        assert_eq!(chess.make_move_from_uci("f1b5"), Ok(()));
+    }
+
+    #[test]
+    fn kings_are_ordinary_pieces() {
+        let mut chess = position(
+            &[
+                (File::E, Rank::First, Piece::King(WHITE)),
+                (File::E, Rank::Eighth, Piece::Rook(WHITE)),
+                (File::E, Rank::Fourth, Piece::King(BLACK)),
+                (File::B, Rank::Second, Piece::Pawn(BLACK)),
+            ],
+            1,
+        );
+        assert!(!chess.players.1.in_check);
+        // Black has nothing to capture, so the attacked king can be left where it is
+        assert_eq!(chess.make_move_from_uci("b2b1k"), Ok(()));
+        assert_eq!(
+            chess.get_square(File::B, Rank::First).piece,
+            Piece::King(BLACK)
+        );
+        assert_eq!(chess.make_move_from_uci("e8e4"), Ok(()));
+        assert_eq!(
+            chess.get_square(File::E, Rank::Fourth).piece,
+            Piece::Rook(WHITE)
+        );
+        assert_eq!(chess.gamestate, GameState::InProgress);
+    }
+
+    #[test]
+    fn losing_every_piece_wins() {
+        let mut chess = position(
+            &[
+                (File::B, Rank::First, Piece::Rook(WHITE)),
+                (File::H, Rank::Third, Piece::Pawn(WHITE)),
+                (File::B, Rank::Sixth, Piece::Knight(BLACK)),
+            ],
+            0,
+        );
+        assert_eq!(
+            chess.make_move_from_uci("h3h4"),
+            Err(MoveError::MustCapture)
+        );
+        assert_eq!(chess.make_move_from_uci("b1b6"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::BlackVictory);
+        assert!(chess.players.1.victory);
+    }
+
+    #[test]
+    fn being_stalemated_wins() {
+        let mut chess = position(
+            &[
+                (File::A, Rank::Second, Piece::Pawn(WHITE)),
+                (File::A, Rank::Fourth, Piece::Pawn(BLACK)),
+                (File::H, Rank::Eighth, Piece::Knight(BLACK)),
+            ],
+            0,
+        );
+        assert_eq!(chess.make_move_from_uci("a2a3"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::InProgress);
+        assert_eq!(chess.make_move_from_uci("h8g6"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::WhiteVictory);
+    }
+}
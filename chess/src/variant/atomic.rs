@@ -0,0 +1,210 @@
+use crate::{
+    check::{is_king_in_check_state, kings_are_adjacent},
+    chess::Chess,
+    chessboard::{
+        get_adjacent_squares, get_black_king, get_white_king, square::Square, ChessBoard,
+    },
+    game_state::GameState,
+    make_chess_move::MoveError,
+    moves::{
+        king::move_is_castling,
+        move_helpers::helpers::{move_is_black_en_passant, move_is_white_en_passant},
+    },
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+/**
+ * Every capture explodes, taking the capturing piece and every piece but pawns
+ * on the eight squares around it off the board. Kings cannot capture and
+ * kings standing next to each other are never in check.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Atomic;
+
+/**
+ * Removes the piece on `square` and the non-pawn pieces around it, returning what was removed
+ */
+pub fn explode(board: &mut ChessBoard, square: &Square) -> Vec<Piece> {
+    let center = &mut board[square.file as usize][square.rank as usize].piece;
+    let mut exploded = vec![*center];
+    *center = Piece::None;
+    for adjacent in get_adjacent_squares(square, board) {
+        if adjacent.has_piece() && !adjacent.piece.is_pawn() {
+            exploded.push(adjacent.piece);
+            board[adjacent.file as usize][adjacent.rank as usize].piece = Piece::None;
+        }
+    }
+    exploded
+}
+
+fn has_king(board: &ChessBoard, color: PieceColor) -> bool {
+    match color {
+        PieceColor::White => get_white_king(board).is_some(),
+        PieceColor::Black => get_black_king(board).is_some(),
+        PieceColor::None => false,
+    }
+}
+
+/**
+ * The board after the move, explosion included
+ */
+fn board_after(chess: &Chess, start_sq: &Square, end_sq: &Square) -> ChessBoard {
+    let mut board = chess.board.clone();
+    let captures = if move_is_white_en_passant(start_sq, end_sq, chess)
+        || move_is_black_en_passant(start_sq, end_sq, chess)
+    {
+        board[end_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
+        true
+    } else {
+        end_sq.has_piece()
+    };
+    board[end_sq.file as usize][end_sq.rank as usize].piece = start_sq.piece;
+    board[start_sq.file as usize][start_sq.rank as usize].piece = Piece::None;
+    if captures {
+        explode(&mut board, end_sq);
+    }
+    board
+}
+
+impl Variant for Atomic {
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        if move_is_castling(start_sq, end_sq, chess) {
+            return StandardChess.is_legal_move(chess, start_sq, end_sq);
+        }
+        let color = start_sq.piece.color();
+        if (end_sq.has_piece() && end_sq.piece.color() == color)
+            || !start_sq.piece.piece_move(start_sq, end_sq, chess)
+            || (start_sq.piece.is_king() && end_sq.has_piece())
+        {
+            return Err(MoveError::IllegalMove);
+        }
+
+        let board = board_after(chess, start_sq, end_sq);
+        if !has_king(&board, color) {
+            // Blowing up your own king is never allowed
+            Err(MoveError::IllegalMove)
+        } else if !has_king(&board, color.opposite()) || !self.is_in_check(&board, color) {
+            Ok(())
+        } else {
+            Err(MoveError::KingInCheck)
+        }
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        StandardChess.promotion_pieces()
+    }
+
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        if has_king(&chess.board, side_to_move) {
+            return StandardChess.outcome(chess, side_to_move);
+        }
+        Some(match side_to_move {
+            PieceColor::White => GameState::BlackVictory,
+            _ => GameState::WhiteVictory,
+        })
+    }
+
+    fn is_insufficient_material(&self, chess: &Chess) -> bool {
+        StandardChess.is_insufficient_material(chess)
+    }
+
+    fn is_in_check(&self, board: &ChessBoard, color: PieceColor) -> bool {
+        !kings_are_adjacent(board) && is_king_in_check_state(board, color)
+    }
+
+    fn after_capture(&self, chess: &mut Chess, end_sq: &Square, _captured: Piece) {
+        for piece in explode(&mut chess.board, end_sq) {
+            chess.pieces_eaten.add_piece(piece);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chessboard::{file::File, rank::Rank},
+        variant::{test_position, GameVariant},
+    };
+
+    fn position(pieces: &[(File, Rank, Piece)]) -> Chess {
+        test_position(GameVariant::Atomic, pieces, 0)
+    }
+
+    #[test]
+    fn captures_explode() {
+        let mut chess = Chess::with_variant(GameVariant::Atomic);
+        for uci in ["g1f3", "a7a6", "f3e5", "a6a5", "e5f7"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        for (file, rank) in [
+            (File::F, Rank::Seventh),
+            (File::E, Rank::Eighth),
+            (File::F, Rank::Eighth),
+            (File::G, Rank::Eighth),
+        ] {
+            assert!(chess.get_square(file, rank).is_empty());
+        }
+        // Pawns next to the explosion survive it
+        assert!(chess.get_square(File::E, Rank::Seventh).has_piece());
+        assert!(chess.get_square(File::G, Rank::Seventh).has_piece());
+        assert_eq!(chess.gamestate, GameState::WhiteVictory);
+        assert!(chess.players.0.victory);
+        // The f pawn, the white knight and the king, bishop and knight around f7
+        assert_eq!(chess.pieces_eaten.len(), 5);
+    }
+
+    #[test]
+    fn kings_cannot_capture() {
+        let white = PieceColor::White;
+        let black = PieceColor::Black;
+        let mut chess = position(&[
+            (File::E, Rank::First, Piece::King(white)),
+            (File::A, Rank::First, Piece::Rook(white)),
+            (File::D, Rank::Second, Piece::Pawn(black)),
+            (File::H, Rank::Eighth, Piece::King(black)),
+        ]);
+        assert_eq!(
+            chess.make_move_from_uci("e1d2"),
+            Err(MoveError::IllegalMove)
+        );
+        assert_eq!(chess.make_move_from_uci("e1f1"), Ok(()));
+    }
+
+    #[test]
+    fn adjacent_kings_do_not_give_check() {
+        let white = PieceColor::White;
+        let black = PieceColor::Black;
+        let mut chess = position(&[
+            (File::E, Rank::Fourth, Piece::King(white)),
+            (File::E, Rank::Fifth, Piece::King(black)),
+            (File::E, Rank::First, Piece::Rook(black)),
+            (File::A, Rank::Second, Piece::Pawn(white)),
+        ]);
+        assert!(!Atomic.is_in_check(&chess.board, white));
+        assert!(is_king_in_check_state(&chess.board, white));
+        assert_eq!(chess.make_move_from_uci("a2a3"), Ok(()));
+        // The rook covers the whole d file, except next to the black king
+        let mut chess = position(&[
+            (File::E, Rank::Fourth, Piece::King(white)),
+            (File::E, Rank::Sixth, Piece::King(black)),
+            (File::D, Rank::First, Piece::Rook(black)),
+        ]);
+        assert_eq!(
+            chess.make_move_from_uci("e4d4"),
+            Err(MoveError::KingInCheck)
+        );
+        assert_eq!(chess.make_move_from_uci("e4d5"), Ok(()));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    chess::Chess,
+    chessboard::{rank::Rank, square::Square},
+    game_state::GameState,
+    make_chess_move::{legal_drops, MoveError},
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+/**
+ * Standard chess where every captured piece goes to the pocket of the capturing player,
+ * who may drop it on an empty square instead of moving. Promoted pieces are pocketed as pawns.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        StandardChess.is_legal_move(chess, start_sq, end_sq)
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        StandardChess.promotion_pieces()
+    }
+
+    /**
+     * A side without piece moves may still drop, which blocks a check or breaks a stalemate.
+     * Only the fifty-move rule ends the game regardless.
+     */
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        match StandardChess.outcome(chess, side_to_move) {
+            Some(GameState::Stalemate) if chess.fifty_move_rule >= 50 => Some(GameState::Stalemate),
+            Some(_) if !legal_drops(chess).is_empty() => None,
+            outcome => outcome,
+        }
+    }
+
+    /**
+     * Captured pieces always come back
+     */
+    fn is_insufficient_material(&self, _chess: &Chess) -> bool {
+        false
+    }
+
+    fn after_capture(&self, chess: &mut Chess, end_sq: &Square, captured: Piece) {
+        let pocketed = if chess.promoted_squares.contains(&(end_sq.file, end_sq.rank)) {
+            Piece::Pawn(PieceColor::None)
+        } else {
+            captured
+        };
+        match captured.color() {
+            PieceColor::White => chess.players.1.pocket.add(pocketed),
+            PieceColor::Black => chess.players.0.pocket.add(pocketed),
+            PieceColor::None => {}
+        }
+    }
+
+    fn after_move(
+        &self,
+        chess: &mut Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+        promoted_piece: Option<Piece>,
+    ) {
+        let start = (start_sq.file, start_sq.rank);
+        let end = (end_sq.file, end_sq.rank);
+        chess.promoted_squares.retain(|&square| square != end);
+        if let Some(square) = chess
+            .promoted_squares
+            .iter_mut()
+            .find(|square| **square == start)
+        {
+            *square = end;
+        } else if start_sq.piece.is_pawn()
+            && promoted_piece.is_some()
+            && matches!(end_sq.rank, Rank::First | Rank::Eighth)
+        {
+            chess.promoted_squares.push(end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chessboard::file::File,
+        fen::to_fen,
+        notation::move_to_san,
+        pgn::to_pgn,
+        variant::{test_position, GameVariant},
+    };
+
+    const WHITE: PieceColor = PieceColor::White;
+    const BLACK: PieceColor = PieceColor::Black;
+
+    #[test]
+    fn captured_pieces_can_be_dropped() {
+        let mut chess = Chess::with_variant(GameVariant::Crazyhouse);
+        assert!(to_fen(&chess).starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w"));
+        for uci in ["e2e4", "d7d5", "e4d5", "d8d5"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(chess.players.0.pocket.count(Piece::Pawn(WHITE)), 1);
+        assert_eq!(chess.players.1.pocket.count(Piece::Pawn(BLACK)), 1);
+        assert!(to_fen(&chess).starts_with("rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w"));
+
+        assert_eq!(
+            chess.make_move_from_uci("P@d8"),
+            Err(MoveError::InvalidDrop)
+        );
+        assert_eq!(
+            chess.make_move_from_uci("N@d4"),
+            Err(MoveError::InvalidDrop)
+        );
+        assert_eq!(chess.make_move_from_uci("P@e4"), Ok(()));
+        assert!(chess.players.0.pocket.is_empty());
+        assert!(chess.legal_moves().contains(&(
+            (File::E, Rank::Third),
+            (File::E, Rank::Third),
+            (5, 1)
+        )));
+        assert_eq!(chess.make_move_from_uci("P@e6"), Ok(()));
+        assert_eq!(
+            chess.list_of_moves.last(),
+            Some(&((File::E, Rank::Sixth), (File::E, Rank::Sixth), (5, 1)))
+        );
+
+        let pgn = to_pgn(&chess, &[]);
+        assert!(pgn.contains("[Variant \"Crazyhouse\"]"));
+        assert!(pgn.contains("3. P@e4 P@e6"), "{pgn}");
+    }
+
+    #[test]
+    fn drops_block_checks() {
+        let mut chess = test_position(
+            GameVariant::Crazyhouse,
+            &[
+                (File::H, Rank::First, Piece::King(WHITE)),
+                (File::H, Rank::Second, Piece::Pawn(WHITE)),
+                (File::G, Rank::Second, Piece::Pawn(WHITE)),
+                (File::A, Rank::Eighth, Piece::King(BLACK)),
+                (File::A, Rank::Second, Piece::Rook(BLACK)),
+            ],
+            1,
+        );
+        chess.players.0.pocket.add(Piece::Knight(WHITE));
+
+        let chess_move = ((File::A, Rank::Second), (File::A, Rank::First), (0, 0));
+        assert_eq!(move_to_san(&chess, &chess_move), "Ra1+");
+        assert_eq!(chess.make_move_from_uci("a2a1"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::InProgress);
+        // Only dropping the knight between the king and the rook is legal
+        assert_eq!(
+            chess.make_move_from_uci("N@e2"),
+            Err(MoveError::KingInCheck)
+        );
+        assert_eq!(chess.make_move_from_uci("N@e1"), Ok(()));
+    }
+
+    #[test]
+    fn drops_break_a_stalemate() {
+        let mut chess = test_position(
+            GameVariant::Crazyhouse,
+            &[
+                (File::H, Rank::First, Piece::King(WHITE)),
+                (File::C, Rank::Fifth, Piece::Queen(WHITE)),
+                (File::A, Rank::Eighth, Piece::King(BLACK)),
+            ],
+            0,
+        );
+        chess.players.1.pocket.add(Piece::Knight(BLACK));
+
+        // The black king has no square left, but the knight can still be dropped
+        assert_eq!(chess.make_move_from_uci("c5b6"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::InProgress);
+        assert!(chess
+            .legal_moves()
+            .iter()
+            .all(|(start, end, _)| start == end));
+        assert_eq!(chess.make_move_from_uci("N@d5"), Ok(()));
+
+        let mut chess = test_position(
+            GameVariant::Crazyhouse,
+            &[
+                (File::H, Rank::First, Piece::King(WHITE)),
+                (File::C, Rank::Fifth, Piece::Queen(WHITE)),
+                (File::A, Rank::Eighth, Piece::King(BLACK)),
+            ],
+            0,
+        );
+        // With an empty pocket it is a stalemate as in standard chess
+        assert_eq!(chess.make_move_from_uci("c5b6"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::Stalemate);
+    }
+
+    #[test]
+    fn promoted_pieces_are_pocketed_as_pawns() {
+        let mut chess = test_position(
+            GameVariant::Crazyhouse,
+            &[
+                (File::E, Rank::First, Piece::King(WHITE)),
+                (File::B, Rank::Seventh, Piece::Pawn(WHITE)),
+                (File::H, Rank::Eighth, Piece::King(BLACK)),
+                (File::D, Rank::Eighth, Piece::Rook(BLACK)),
+            ],
+            0,
+        );
+
+        assert_eq!(chess.make_move_from_uci("b7b8q"), Ok(()));
+        assert_eq!(chess.promoted_squares, vec![(File::B, Rank::Eighth)]);
+        assert!(to_fen(&chess).starts_with("1Q~1r3k/"));
+        assert_eq!(chess.make_move_from_uci("d8b8"), Ok(()));
+        assert!(chess.promoted_squares.is_empty());
+        assert_eq!(chess.players.1.pocket.count(Piece::Pawn(BLACK)), 1);
+        assert_eq!(chess.players.1.pocket.count(Piece::Queen(BLACK)), 0);
+    }
+}
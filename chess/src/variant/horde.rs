@@ -0,0 +1,131 @@
+use crate::{
+    chess::Chess,
+    chessboard::{get_squares_with_white_pieces, square::Square},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+/**
+ * Ranks the horde fills completely, counted from white's first rank
+ */
+const HORDE_RANKS: usize = 4;
+
+/**
+ * Files of the four pawns in front of the horde on the fifth rank
+ */
+const VANGUARD_FILES: [usize; 4] = [1, 2, 5, 6];
+
+/**
+ * Thirty-six white pawns against the standard black army, white has no king and loses once
+ * every one of its pieces is captured
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Horde;
+
+impl Variant for Horde {
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+        for file in &mut chess.board {
+            for square in &mut file[..HORDE_RANKS] {
+                square.piece = Piece::Pawn(PieceColor::White);
+            }
+        }
+        for file in VANGUARD_FILES {
+            chess.board[file][HORDE_RANKS].piece = Piece::Pawn(PieceColor::White);
+        }
+        chess.players.0.castle();
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        StandardChess.is_legal_move(chess, start_sq, end_sq)
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        StandardChess.promotion_pieces()
+    }
+
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        if side_to_move == PieceColor::White
+            && get_squares_with_white_pieces(&chess.board).is_empty()
+        {
+            return Some(GameState::BlackVictory);
+        }
+        StandardChess.outcome(chess, side_to_move)
+    }
+
+    /**
+     * A single pawn can still promote, and black always has its king to mate with
+     */
+    fn is_insufficient_material(&self, _chess: &Chess) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chessboard::{file::File, rank::Rank},
+        fen::to_fen,
+        pgn::to_pgn,
+        variant::{test_position, GameVariant},
+    };
+
+    const WHITE: PieceColor = PieceColor::White;
+    const BLACK: PieceColor = PieceColor::Black;
+
+    #[test]
+    fn horde_starts_with_thirty_six_pawns() {
+        let mut chess = Chess::with_variant(GameVariant::Horde);
+        assert_eq!(
+            to_fen(&chess),
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+        );
+        assert_eq!(get_squares_with_white_pieces(&chess.board).len(), 36);
+        for uci in ["b5b6", "a7b6", "c5b6", "c7b6"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(chess.gamestate, GameState::InProgress);
+        assert!(to_pgn(&chess, &[]).contains("[Variant \"Horde\"]\n"));
+    }
+
+    #[test]
+    fn first_rank_pawns_double_step() {
+        let mut chess = test_position(
+            GameVariant::Horde,
+            &[
+                (File::A, Rank::First, Piece::Pawn(WHITE)),
+                (File::H, Rank::Eighth, Piece::King(BLACK)),
+            ],
+            0,
+        );
+        assert_eq!(chess.legal_moves().len(), 2);
+        assert_eq!(chess.make_move_from_uci("a1a3"), Ok(()));
+        assert!(to_fen(&chess).contains(" b - - "), "{}", to_fen(&chess));
+    }
+
+    #[test]
+    fn white_loses_without_pieces() {
+        let mut chess = test_position(
+            GameVariant::Horde,
+            &[
+                (File::C, Rank::Third, Piece::Pawn(WHITE)),
+                (File::A, Rank::First, Piece::Queen(BLACK)),
+                (File::H, Rank::Eighth, Piece::King(BLACK)),
+            ],
+            1,
+        );
+        assert_eq!(chess.make_move_from_uci("a1c3"), Ok(()));
+        assert!(!chess.players.0.in_check);
+        assert_eq!(chess.gamestate, GameState::BlackVictory);
+        assert!(chess.players.1.victory);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    chess::Chess,
+    chessboard::{file::File, get_black_king, get_white_king, rank::Rank, square::Square},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+/**
+ * The four center squares a king wins the game on
+ */
+pub const HILL: [(File, Rank); 4] = [
+    (File::D, Rank::Fourth),
+    (File::E, Rank::Fourth),
+    (File::D, Rank::Fifth),
+    (File::E, Rank::Fifth),
+];
+
+/**
+ * Standard chess where bringing the king to the center also wins
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KingOfTheHill;
+
+impl KingOfTheHill {
+    fn is_on_the_hill(chess: &Chess, color: PieceColor) -> bool {
+        let king = match color {
+            PieceColor::White => get_white_king(&chess.board),
+            PieceColor::Black => get_black_king(&chess.board),
+            PieceColor::None => None,
+        };
+        king.is_some_and(|king| HILL.contains(&(king.file, king.rank)))
+    }
+}
+
+impl Variant for KingOfTheHill {
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        StandardChess.is_legal_move(chess, start_sq, end_sq)
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        StandardChess.promotion_pieces()
+    }
+
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        match side_to_move.opposite() {
+            PieceColor::White if Self::is_on_the_hill(chess, PieceColor::White) => {
+                Some(GameState::WhiteKingOfTheHill)
+            }
+            PieceColor::Black if Self::is_on_the_hill(chess, PieceColor::Black) => {
+                Some(GameState::BlackKingOfTheHill)
+            }
+            _ => StandardChess.outcome(chess, side_to_move),
+        }
+    }
+
+    /**
+     * A lone king can still walk to the center
+     */
+    fn is_insufficient_material(&self, _chess: &Chess) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pgn::to_pgn, variant::GameVariant};
+
+    #[test]
+    fn king_of_the_hill_works() {
+        let mut chess = Chess::with_variant(GameVariant::KingOfTheHill);
+        for uci in ["e2e4", "e7e5", "e1e2", "d7d5", "e2d3", "d5e4"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        // The king may not step into check on the way up
+        assert_eq!(
+            chess.make_move_from_uci("d3d4"),
+            Err(MoveError::KingInCheck)
+        );
+        assert_eq!(chess.gamestate, GameState::InProgress);
+        assert_eq!(chess.make_move_from_uci("d3e4"), Ok(()));
+        assert_eq!(chess.gamestate, GameState::WhiteKingOfTheHill);
+        assert_eq!(chess.gamestate.winner(), PieceColor::White);
+        assert!(chess.players.0.victory);
+        assert_eq!(chess.make_move_from_uci("e8e7"), Err(MoveError::GameOver));
+        let pgn = to_pgn(&chess, &[]);
+        assert!(pgn.contains("[Result \"1-0\"]\n[Variant \"King of the Hill\"]\n"));
+        assert!(pgn.ends_with("4. Kxe4 1-0\n"), "{pgn}");
+
+        let mut standard = Chess::new_starting_position();
+        for uci in ["e2e4", "e7e5", "e1e2", "d7d5", "e2d3", "d5e4", "d3e4"] {
+            assert_eq!(standard.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(standard.gamestate, GameState::InProgress);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    checkmate::is_checkmated,
+    chess::Chess,
+    chessboard::{square::Square, starting_position_with},
+    game_state::{insufficient_material, insufficient_material_of, stalemate, GameState},
+    make_chess_move::{king_is_not_in_check_after_move, MoveError},
+    piece::{Piece, PieceColor},
+};
+
+use super::Variant;
+
+const NONE: PieceColor = PieceColor::None;
+
+const PROMOTION_PIECES: [Piece; 4] = [
+    Piece::Queen(NONE),
+    Piece::Rook(NONE),
+    Piece::Bishop(NONE),
+    Piece::Knight(NONE),
+];
+
+/**
+ * The FIDE rules, also used by Chess960 which only changes the back rank
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StandardChess;
+
+impl Variant for StandardChess {
+    fn starting_position(&self, chess: &mut Chess) {
+        chess.board = starting_position_with(&chess.variant.back_rank());
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        if king_is_not_in_check_after_move(chess, start_sq, end_sq) {
+            Ok(())
+        } else {
+            Err(MoveError::KingInCheck)
+        }
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        &PROMOTION_PIECES
+    }
+
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        if is_checkmated(chess, side_to_move) {
+            return Some(match side_to_move {
+                PieceColor::White => GameState::BlackVictory,
+                _ => GameState::WhiteVictory,
+            });
+        }
+        if chess.fifty_move_rule >= 50 || stalemate(chess, side_to_move) {
+            return Some(GameState::Stalemate);
+        }
+        None
+    }
+
+    fn is_insufficient_material(&self, chess: &Chess) -> bool {
+        insufficient_material(chess)
+    }
+
+    fn has_insufficient_material(&self, chess: &Chess, color: PieceColor) -> bool {
+        insufficient_material_of(chess, color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chessboard::file::File, chessboard::rank::Rank, variant::GameVariant};
+
+    #[test]
+    fn standard_rules_work() {
+        for variant in [GameVariant::Standard, GameVariant::Chess960(42)] {
+            assert_eq!(
+                format!("{:?}", variant.rules()),
+                format!("{StandardChess:?}")
+            );
+        }
+
+        let mut chess = Chess::new_starting_position();
+        let rules = chess.variant.rules();
+        assert!(rules.promotion_pieces().contains(&Piece::Knight(NONE)));
+        assert!(!rules.promotion_pieces().contains(&Piece::King(NONE)));
+
+        for uci in ["e2e4", "e7e5", "d1h5", "b8c6"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        // The f pawn is pinned to the king by the queen on h5
+        let start_sq = chess.get_square(File::F, Rank::Seventh);
+        let end_sq = chess.get_square(File::F, Rank::Sixth);
+        assert_eq!(
+            rules.is_legal_move(&chess, &start_sq, &end_sq),
+            Err(MoveError::KingInCheck)
+        );
+        let start_sq = chess.get_square(File::G, Rank::Seventh);
+        let end_sq = chess.get_square(File::G, Rank::Sixth);
+        assert!(rules.is_legal_move(&chess, &start_sq, &end_sq).is_ok());
+        assert_eq!(rules.outcome(&chess, PieceColor::White), None);
+
+        for uci in ["f1c4", "g8f6", "h5f7"] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(
+            rules.outcome(&chess, PieceColor::Black),
+            Some(GameState::WhiteVictory)
+        );
+        assert_eq!(chess.gamestate, GameState::WhiteVictory);
+        assert!(chess.players.0.victory);
+    }
+}
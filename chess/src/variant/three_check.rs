@@ -0,0 +1,99 @@
+use crate::{
+    chess::Chess,
+    chessboard::{get_squares_with_black_pieces, get_squares_with_white_pieces, square::Square},
+    game_state::GameState,
+    make_chess_move::MoveError,
+    piece::{Piece, PieceColor},
+};
+
+use super::{standard::StandardChess, Variant};
+
+/**
+ * Checks a player has to give to win
+ */
+pub const CHECKS_TO_WIN: u8 = 3;
+
+/**
+ * Standard chess where the third check wins, the checks are counted on each `Player`
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn starting_position(&self, chess: &mut Chess) {
+        StandardChess.starting_position(chess);
+    }
+
+    fn is_legal_move(
+        &self,
+        chess: &Chess,
+        start_sq: &Square,
+        end_sq: &Square,
+    ) -> Result<(), MoveError> {
+        StandardChess.is_legal_move(chess, start_sq, end_sq)
+    }
+
+    fn promotion_pieces(&self) -> &'static [Piece] {
+        StandardChess.promotion_pieces()
+    }
+
+    fn outcome(&self, chess: &Chess, side_to_move: PieceColor) -> Option<GameState> {
+        match side_to_move.opposite() {
+            PieceColor::White if chess.players.0.checks >= CHECKS_TO_WIN => {
+                Some(GameState::WhiteThreeCheck)
+            }
+            PieceColor::Black if chess.players.1.checks >= CHECKS_TO_WIN => {
+                Some(GameState::BlackThreeCheck)
+            }
+            _ => StandardChess.outcome(chess, side_to_move),
+        }
+    }
+
+    /**
+     * Any piece besides the kings can still give check
+     */
+    fn is_insufficient_material(&self, chess: &Chess) -> bool {
+        get_squares_with_white_pieces(&chess.board)
+            .into_iter()
+            .chain(get_squares_with_black_pieces(&chess.board))
+            .all(|square| square.piece.is_king())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::to_fen, variant::GameVariant};
+
+    #[test]
+    fn three_check_works() {
+        let mut chess = Chess::with_variant(GameVariant::ThreeCheck);
+        assert!(to_fen(&chess).ends_with(" 0 1 +0+0"));
+        for uci in [
+            "e2e4", "e7e5", "f1c4", "d7d6", "c4f7", "e8f7", "d1h5", "g7g6",
+        ] {
+            assert_eq!(chess.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(chess.players.0.checks, 2);
+        assert_eq!(chess.players.1.checks, 0);
+        assert!(to_fen(&chess).ends_with(" +2+0"));
+        assert_eq!(chess.gamestate, GameState::InProgress);
+
+        assert_eq!(chess.make_move_from_uci("h5g6"), Ok(()));
+        assert_eq!(chess.players.0.checks, 3);
+        assert_eq!(chess.gamestate, GameState::WhiteThreeCheck);
+        assert!(chess.players.0.victory);
+
+        let restored = Chess::from_json(&chess.to_json()).expect("valid json");
+        assert_eq!(restored.players.0.checks, 3);
+
+        let mut standard = Chess::new_starting_position();
+        for uci in [
+            "e2e4", "e7e5", "f1c4", "d7d6", "c4f7", "e8f7", "d1h5", "g7g6", "h5g6",
+        ] {
+            assert_eq!(standard.make_move_from_uci(uci), Ok(()));
+        }
+        assert_eq!(standard.gamestate, GameState::InProgress);
+        assert!(!to_fen(&standard).contains('+'));
+    }
+}
@@ -53,6 +53,16 @@ export const playerSchema = z.object({
 	color: z.string(),
 	in_check: z.boolean(),
 	victory: z.boolean(),
+	checks: z.number().default(0),
+	pocket: z
+		.object({
+			queens: z.number(),
+			rooks: z.number(),
+			bishops: z.number(),
+			knights: z.number(),
+			pawns: z.number(),
+		})
+		.optional(),
 	castling: castlingSchema,
 });
 
@@ -73,6 +83,12 @@ export const gameStateSchema = z.enum([
 	"InProgress",
 	"WhiteVictory",
 	"BlackVictory",
+	"WhiteKingOfTheHill",
+	"BlackKingOfTheHill",
+	"WhiteThreeCheck",
+	"BlackThreeCheck",
+	"InsufficientMaterial",
+	"Stalemate",
 	"Draw",
 ]);
 
@@ -99,6 +115,22 @@ export type Piece = z.TypeOf<typeof pieceTypeSchema>;
 export type Square = z.TypeOf<typeof squareSchema>;
 export type ListOfMoves = z.TypeOf<typeof listOfMovesSchema>;
 export type GameState = z.TypeOf<typeof gameStateSchema>;
+
+/** The side that won a finished game, null for draws and games in progress */
+export function winner(state: GameState): "white" | "black" | null {
+	switch (state) {
+		case "WhiteVictory":
+		case "WhiteKingOfTheHill":
+		case "WhiteThreeCheck":
+			return "white";
+		case "BlackVictory":
+		case "BlackKingOfTheHill":
+		case "BlackThreeCheck":
+			return "black";
+		default:
+			return null;
+	}
+}
 export type PossibleMoves = z.TypeOf<typeof possibleMoveSchema>;
 export type Castling = z.TypeOf<typeof castlingSchema>;
 export type PiecesEaten = z.TypeOf<typeof piecesEatenSchema>;
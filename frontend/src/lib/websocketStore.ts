@@ -6,14 +6,25 @@ interface WebSocketStore {
 	send: (message: string) => void;
 }
 
+const RECONNECT_DELAY_MS = 2000;
+
 export const createWebSocketStore = (url: string): WebSocketStore => {
 	const { subscribe, set } = writable<WebSocket | null>(null);
 	let socket: WebSocket | null = null;
 
+	// Reconnect after the connection drops, such as when the server restarts
+	const connect = () => {
+		const current = new WebSocket(url);
+		socket = current;
+		current.addEventListener("open", () => set(current));
+		current.addEventListener("close", () => {
+			set(null);
+			setTimeout(connect, RECONNECT_DELAY_MS);
+		});
+	};
+
 	if (typeof window !== "undefined") {
-		socket = new WebSocket(url);
-		socket.addEventListener("open", () => set(socket));
-		socket.addEventListener("close", () => set(null));
+		connect();
 	}
 
 	return {
@@ -1,28 +1,172 @@
-import type { Chess } from "./types";
+import type { Chess, GameState, Piece } from "./types";
 
-export interface InitialStateMessage {
-	type: "initial_state";
-	chess: Chess;
+export const PROTOCOL_VERSION = 1;
+
+type PieceColor = "White" | "Black";
+
+export type ClientMessage =
+	| { type: "join"; game_id?: number }
+	| { type: "move"; uci: string }
+	| { type: "resign"; color: PieceColor }
+	| { type: "offer_draw"; color: PieceColor }
+	| {
+			type: "request_takeback";
+			color: PieceColor;
+			scope?: "last_move" | "full_move";
+	  }
+	| { type: "answer_takeback"; color: PieceColor; accept: boolean }
+	| { type: "chat"; text: string; channel?: ChatChannel }
+	| { type: "ping" }
+	| { type: "reset" };
+
+export interface Clock {
+	white_ms: number;
+	black_ms: number;
 }
 
-export interface UpdateMessage {
-	type: "update";
+export interface StateMessage {
+	type: "state";
+	game_id: number;
 	chess: Chess;
+	clock: Clock | null;
+	/** Milliseconds since the epoch when the side to move of a correspondence game loses */
+	deadline: number | null;
 }
 
-export interface ResetMessage {
-	type: "reset";
-	chess: Chess;
+export interface MoveMadeMessage {
+	type: "move_made";
+	game_id: number;
+	uci: string;
+	san: string;
+	fen: string;
+	state: GameState;
+	clock: Clock | null;
+	deadline: number | null;
+	captured: Piece | null;
+}
+
+export interface DrawOfferedMessage {
+	type: "draw_offered";
+	game_id: number;
+	color: PieceColor;
+}
+
+export interface TakebackRequestedMessage {
+	type: "takeback_requested";
+	game_id: number;
+	color: PieceColor;
+	plies: number;
+}
+
+export interface TakebackDeclinedMessage {
+	type: "takeback_declined";
+	game_id: number;
+	color: PieceColor;
+}
+
+export interface TakenBackMessage {
+	type: "taken_back";
+	game_id: number;
+	plies: number;
+	fen: string;
+	clock: Clock | null;
+	deadline: number | null;
+}
+
+export interface GameOverMessage {
+	type: "game_over";
+	game_id: number;
+	result: GameState;
+}
+
+/** The room is everyone watching, the players channel only the two seated players */
+export type ChatChannel = "room" | "players";
+
+export interface ChatMessage {
+	type: "chat";
+	game_id: number;
+	channel: ChatChannel;
+	/** Null for spectators who are not signed in */
+	author: string | null;
+	text: string;
+	sent_at: number;
+}
+
+export interface TimeControl {
+	initial_ms: number;
+	increment_ms: number;
+}
+
+/** A registered user sitting at one side of a game */
+export interface Seat {
+	user_id: number;
+	username: string;
+}
+
+export type GameVariant =
+	| "standard"
+	| { chess960: number }
+	| "king_of_the_hill"
+	| "three_check"
+	| "atomic"
+	| "crazyhouse"
+	| "antichess"
+	| "horde";
+
+export interface Challenge {
+	id: number;
+	player: Seat;
+	time_control: TimeControl | null;
+	days_per_move: number | null;
+	variant: GameVariant;
+	color: "white" | "black" | "random";
+	created_at: number;
+}
+
+export interface ChallengesMessage {
+	type: "challenges";
+	challenges: Challenge[];
+}
+
+export interface GameStartedMessage {
+	type: "game_started";
+	game_id: number;
+	white: Seat;
+	black: Seat;
+	time_control: TimeControl | null;
+	days_per_move: number | null;
+	variant: GameVariant;
+}
+
+export interface PongMessage {
+	type: "pong";
+}
+
+export interface ServerRestartingMessage {
+	type: "server_restarting";
+}
+
+export interface ErrorMessage {
+	type: "error";
+	code: string;
+	message: string;
 }
 
-// type OtherMessageType = Exclude<string, "initial_state" | "update" | "reset">;
+export type ServerMessage = { version: number } & (
+	| StateMessage
+	| MoveMadeMessage
+	| DrawOfferedMessage
+	| TakebackRequestedMessage
+	| TakebackDeclinedMessage
+	| TakenBackMessage
+	| GameOverMessage
+	| ChatMessage
+	| ChallengesMessage
+	| GameStartedMessage
+	| PongMessage
+	| ServerRestartingMessage
+	| ErrorMessage
+);
 
-// export interface OtherMessage {
-//     type: OtherMessageType;
-//     [key: string]: unknown;
-// }
-export type WebSocketMessage =
-	| InitialStateMessage
-	| UpdateMessage
-	| ResetMessage;
-// | OtherMessage;
+export const encodeMessage = (message: ClientMessage): string =>
+	JSON.stringify({ version: PROTOCOL_VERSION, ...message });
@@ -7,7 +7,7 @@ export const load: PageServerLoad = async ({ fetch }) => {
     // Use server-side environment variables for backend connection in Docker
     const backendHost = env.BACKEND_HOST || "localhost";
     const backendPort = env.BACKEND_PORT || "8000";
-    const apiUrl = `http://${backendHost}:${backendPort}/status`;
+    const apiUrl = `http://${backendHost}:${backendPort}/readyz`;
 
     try {
         const response = await fetch(apiUrl);